@@ -0,0 +1,50 @@
+#!/bin/sh
+# Noble QEMU runner
+# Boots a Helium kernel binary under QEMU through Limine, used by cargo as the kernel target's runner
+#
+# Requires: LIMINE_DIR (a Limine binary release), xorriso, and qemu-system-x86_64
+# Test binaries (built into target/.../deps by cargo test) run headless, with results read from serial and
+# the exit status taken from QEMU's isa-debug-exit device. Other binaries boot with .config/limine.cfg.
+# Extra QEMU arguments may be given in QEMU_FLAGS (e.g. QEMU_FLAGS="-cpu max,la57=on" for 5-level paging in a kernel built with the five_level feature).
+
+set -e
+KERNEL="$1"
+CONFIG_DIR="$(dirname "$0")"
+ISO_DIR="$(dirname "$KERNEL")/iso-$(basename "$KERNEL")"
+ISO="$ISO_DIR.iso"
+
+#Image layout
+rm -rf "$ISO_DIR"
+mkdir -p "$ISO_DIR/noble/helium"
+cp "$KERNEL" "$ISO_DIR/noble/helium/x86-64.elf"
+cp "$LIMINE_DIR/limine-bios.sys" "$LIMINE_DIR/limine-bios-cd.bin" "$LIMINE_DIR/limine-uefi-cd.bin" "$ISO_DIR/"
+case "$KERNEL" in
+    */deps/*)
+        TEST=1
+        printf 'TIMEOUT=0\n\n:NobleOS Tests\nPROTOCOL=limine\nKERNEL_PATH=boot:///noble/helium/x86-64.elf\nKERNEL_CMDLINE=log=quiet\nKASLR=no\n' > "$ISO_DIR/limine.cfg"
+        ;;
+    *)
+        TEST=0
+        cp "$CONFIG_DIR/limine.cfg" "$ISO_DIR/"
+        cp "$CONFIG_DIR/manifest.cfg" "$ISO_DIR/noble/"
+        if [ -n "$NEON" ]; then mkdir -p "$ISO_DIR/noble/neon" && cp "$NEON" "$ISO_DIR/noble/neon/x86-64.elf"; fi
+        ;;
+esac
+
+#Bootable image
+xorriso -as mkisofs -b limine-bios-cd.bin -no-emul-boot -boot-load-size 4 -boot-info-table \
+    --efi-boot limine-uefi-cd.bin -efi-boot-part --efi-boot-image --protective-msdos-label \
+    "$ISO_DIR" -o "$ISO" 2>/dev/null
+"$LIMINE_DIR/limine" bios-install "$ISO" 2>/dev/null
+
+#Run
+if [ "$TEST" = 1 ]; then
+    set +e
+    timeout 300 qemu-system-x86_64 -cdrom "$ISO" -m 512M -no-reboot -display none -serial stdio \
+        -device isa-debug-exit,iobase=0xF4,iosize=0x04 $QEMU_FLAGS
+    STATUS=$?
+    #isa-debug-exit reports (0x10 << 1) | 1 on success
+    if [ "$STATUS" = 33 ]; then exit 0; else exit 1; fi
+else
+    exec qemu-system-x86_64 -cdrom "$ISO" -m 512M -serial stdio $QEMU_FLAGS
+fi
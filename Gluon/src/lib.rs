@@ -4,32 +4,46 @@
 //! * Instruction Set Architectures:
 //!   * Modules handling the x86-64 instruction set architecture:
 //!     * instructions:  Functions that shortcut intrinsic instructions from the x86-64 instruction set architecture
+//!     * ioapic:        Structs and functions related to the handling of the I/O Advanced Programmable Interrupt Controller
 //!     * lapic:         Functions and objects related to the handling of the Local Advanced Programmable Interrupt Controller
 //!     * msr:           Structs and objects handling Model Specific Registers
-//!     * paging:        Structs, enums, and traits related to the contents and handling of x86-64 page tables
+//!     * page_operations: Traits, structs, and functions which edit x86-64 page tables through address translation and physical page allocation
+//!     * paging:        Structs, enums, and traits related to the contents, handling, and walking of x86-64 page tables
 //!     * port:          Structs, functions, and traits related to the handling of ports
 //!     * segmentation:  Structs and enums related to the contents and handling of x86-64 GDT, IDT, and other segmentation structures
 //!     * syscall:       Functions and Structs related to the handling of system calls on x86-64
 //! * System Architectures:
 //!   * Modules handling the PC de-facto standard system architecture:
+//!     * acpi:          Structs and functions related to the reading of Advanced Configuration and Power Interface tables
+//!     * clock:         Traits, structs, and functions related to clock sources and the calibrated monotonic clock built on them
 //!     * fat:           Structs and enums related to the contents and handling of the FAT16 file system
+//!     * hpet:          Structs and functions related to the handling of the High Precision Event Timer
 //!     * ports:         Functions and objects related to the handling of the PC architecture's standard port-space layout
 //!     * pci:           Structs and objects related to the handling of the PCI bus
 //!     * pic:           Functions related to the handling of the Programmable Interrupt Controller
 //!     * pit:           Consts, Functions, and Enums related to the handling of the 8253 and 8254 Programmable Interval Timer
+//!     * power:         Functions related to resetting and powering off the system through the 8042, the reset control port, and ACPI
 //!     * ps2:           Functions and objects related to the handling of the PS/2 controller and devices
+//!     * rtc:           Structs and functions related to the handling of the MC146818 CMOS real-time clock
+//!     * serial:        Structs and functions related to the handling of 16550 UART serial ports
 //! * Operating System Architectures:
 //!   * Modules handling the Unix System V operating system architecture:
 //!     * executable:    Structs and enums related to the contents and handling of System V object files (ELF files)
 //!   * Modules handling the Noble operating system architecture:
 //!     * address_space: Constants related to the Noble address space layout
+//!     * director:      Constants, structs, and functions for the protocol used to register and look up services with the director process
+//!     * display:       Structs describing the framebuffer handed to a display server
+//!     * handle:        Structs and enums related to the handles through which processes access kernel objects
+//!     * signal:        Constants and functions related to the numbered notifications posted to processes
+//!     * snapshot:      Structs and enums describing the processes and threads running under Noble
+//!     * sync:          Structs providing blocking synchronization between the threads of a program
 //!     * input_events:  Structs, enums, and functions for handling user keyboard, mouse, and controller inputs
 //!     * file_system:   Structs and traits for handling file systems in a generic manner
 
 
 // HEADER
 //Flags
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 #![allow(non_camel_case_types)]
 #![allow(clippy::inconsistent_digit_grouping)]
 #![allow(clippy::missing_safety_doc)]
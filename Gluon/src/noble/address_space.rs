@@ -22,8 +22,16 @@ pub const PAGE_MAP_OCT        : usize = 0o_________777__________________usize; /
 //Limine Constants
 //                                       SIGN PM5 PM4 PM3 PM2 PM1 OFFSET
 pub const PHYSICAL_MEMORY_PTR : usize = 0o_177_777_400_000_000_000_0000_usize; pub const PHYSICAL_MEMORY_LVL: PageMapLevel = PageMapLevel::L4;
+pub const PHYSICAL_MEMORY_57  : usize = 0o_177_400_000_000_000_000_0000_usize; //PHYSICAL MEMORY LOCATION WHEN 5-LEVEL PAGING IS ACTIVE
 pub const MODULE_CODE_PTR     : usize = 0o_177_777_401_000_000_000_0000_usize; pub const MODULE_CODE_LVL:     PageMapLevel = PageMapLevel::L4;
 pub const KERNEL_HEAP_PTR     : usize = 0o_177_777_777_774_000_000_0000_usize; pub const KERNEL_HEAP_LVL:     PageMapLevel = PageMapLevel::L3;
 pub const KERNEL_STACKS_PTR   : usize = 0o_177_777_777_775_000_000_0000_usize; pub const KERNEL_STACKS_LVL:   PageMapLevel = PageMapLevel::L3;
 pub const ALLOCATOR_STACK_PTR : usize = 0o_177_777_777_776_000_000_0000_usize; pub const ALLOCATOR_STACK_LVL: PageMapLevel = PageMapLevel::L3;
 pub const KERNEL_CODE_PTR     : usize = 0o_177_777_777_777_000_000_0000_usize; pub const KERNEL_CODE_LVL:     PageMapLevel = PageMapLevel::L3;
+
+
+// FUNCTIONS
+//Location of all physical memory as mapped by Limine, which depends on the number of paging levels
+pub fn physical_memory_ptr(root: PageMapLevel) -> usize {
+    if root == PageMapLevel::L5 {PHYSICAL_MEMORY_57} else {PHYSICAL_MEMORY_PTR}
+}
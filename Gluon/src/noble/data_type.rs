@@ -1,5 +1,6 @@
 // DATA TYPE
 #[repr(u64)]
+#[derive(Clone, Copy)]
 #[derive(Debug)]
 pub enum DataType {
     Binary = 0x0000_0000_0000_0000
@@ -0,0 +1,217 @@
+// GLUON: NOBLE DIRECTOR
+// Constants, structs, and functions for the protocol through which processes register and look up services with the director process
+
+
+// HEADER
+//Imports
+use crate::numeric_enum;
+use crate::noble::handle::*;
+use crate::noble::return_code::ReturnCode;
+use crate::noble::system_calls::*;
+use core::convert::TryFrom;
+
+//Constants
+pub const BOOTSTRAP_HANDLE:      Handle = Handle(0x00); //HANDLE TO THE DIRECTOR PORT GIVEN TO EVERY PROCESS AT CREATION
+pub const SERVICE_NAME_LENGTH:   usize  = 32;           //MAXIMUM LENGTH OF A SERVICE NAME IN BYTES
+pub const SERVICE_DISPLAY:       &str   = "display";
+pub const SERVICE_KEYBOARD:      &str   = "keyboard";
+pub const SERVICE_FILE_SYSTEM:   &str   = "fs";
+
+//Rights given to handles which are passed to the director and to the processes which look services up
+pub const SERVICE_RIGHTS: HandleRights = HandleRights(HandleRights::WRITE.0 | HandleRights::DUPLICATE.0 | HandleRights::TRANSFER.0);
+
+
+// PROTOCOL
+//Request Type (message data word 0)
+numeric_enum! {
+    #[repr(u64)]
+    #[derive(Clone, Copy)]
+    #[derive(PartialEq, Eq)]
+    #[derive(Debug)]
+    pub enum DirectorRequest {
+        Register = 0x01, //Message handle is a service port, word 1 is the name length, words 2-5 are the name
+        Lookup   = 0x02, //Message handle is a reply port, word 1 is the name length, words 2-5 are the name
+        Reply    = 0x03, //Message handle is the service port if word 1 is ReturnCode::NoError
+    }
+}
+
+//Service Name
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub struct ServiceName {
+    bytes: [u8; SERVICE_NAME_LENGTH],
+    length: usize,
+}
+impl ServiceName {
+    //Constructor
+    pub fn new(name: &str) -> Result<Self, ReturnCode> {
+        if name.is_empty()                    {return Err(ReturnCode::InvalidData)}
+        if name.len() > SERVICE_NAME_LENGTH   {return Err(ReturnCode::BufferTooLarge)}
+        if !name.is_ascii()                   {return Err(ReturnCode::InvalidCharacter)}
+        let mut bytes = [0u8; SERVICE_NAME_LENGTH];
+        bytes[..name.len()].copy_from_slice(name.as_bytes());
+        Ok(Self {bytes, length: name.len()})
+    }
+
+    //Read as string
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.length]).unwrap_or("")
+    }
+
+    //Write into message data
+    fn write(&self, data: &mut [u64; MESSAGE_DATA_SIZE]) {
+        data[1] = self.length as u64;
+        for (word, chunk) in data[2..].iter_mut().zip(self.bytes.chunks(8)) {
+            let mut buffer = [0u8; 8];
+            buffer.copy_from_slice(chunk);
+            *word = u64::from_le_bytes(buffer);
+        }
+    }
+
+    //Read from message data
+    fn read(data: &[u64; MESSAGE_DATA_SIZE]) -> Result<Self, ReturnCode> {
+        let length = data[1] as usize;
+        if length == 0 || length > SERVICE_NAME_LENGTH {return Err(ReturnCode::InvalidData)}
+        let mut bytes = [0u8; SERVICE_NAME_LENGTH];
+        for (chunk, word) in bytes.chunks_mut(8).zip(data[2..].iter()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        if !bytes[..length].is_ascii() {return Err(ReturnCode::InvalidCharacter)}
+        Ok(Self {bytes, length})
+    }
+}
+
+//Build a request message
+pub fn request_message(request: DirectorRequest, name: &ServiceName, handle: Handle) -> Message {
+    let mut message = Message::new();
+    message.data[0] = request as u64;
+    name.write(&mut message.data);
+    message.handle = handle;
+    message
+}
+
+//Build a reply message
+pub fn reply_message(result: Result<Handle, ReturnCode>) -> Message {
+    let mut message = Message::new();
+    message.data[0] = DirectorRequest::Reply as u64;
+    match result {
+        Ok(handle) => {message.data[1] = ReturnCode::NoError as u64; message.handle = handle;},
+        Err(error) => {message.data[1] = error as u64;},
+    }
+    message
+}
+
+//Read a request message
+pub fn parse_request(message: &Message) -> Result<(DirectorRequest, ServiceName, Handle), ReturnCode> {
+    let request = DirectorRequest::try_from(message.data[0]).map_err(|_| ReturnCode::InvalidData)?;
+    if request == DirectorRequest::Reply || message.handle == Handle::NONE {return Err(ReturnCode::InvalidData)}
+    Ok((request, ServiceName::read(&message.data)?, message.handle))
+}
+
+//Read a reply message
+pub fn parse_reply(message: &Message) -> Result<Handle, ReturnCode> {
+    if message.data[0] != DirectorRequest::Reply as u64 {return Err(ReturnCode::InvalidData)}
+    match ReturnCode::try_from(message.data[1]) {
+        Ok(ReturnCode::NoError) => Ok(message.handle),
+        Ok(error)               => Err(error),
+        Err(_)                  => Err(ReturnCode::UnknownError),
+    }
+}
+
+
+// CLIENT
+//Register a service port with the director under a name
+pub fn director_register(name: &str, service: Handle) -> Result<(), ReturnCode> {
+    let name = ServiceName::new(name)?;
+    let transfer = system_call_handle_duplicate(service, SERVICE_RIGHTS)?;
+    let result = system_call_port_send(BOOTSTRAP_HANDLE, &request_message(DirectorRequest::Register, &name, transfer));
+    if result.is_err() {system_call_handle_close(transfer)?;}
+    result
+}
+
+//Look up a service port by name, waiting for the director to reply
+pub fn director_lookup(name: &str) -> Result<Handle, ReturnCode> {
+    let name = ServiceName::new(name)?;
+    let reply = system_call_port_create()?;
+    let result = director_lookup_reply(&name, reply);
+    system_call_handle_close(reply)?;
+    result
+}
+
+//Send a lookup request and wait for its reply on the given port
+fn director_lookup_reply(name: &ServiceName, reply: Handle) -> Result<Handle, ReturnCode> {
+    let transfer = system_call_handle_duplicate(reply, HandleRights(HandleRights::WRITE.0 | HandleRights::TRANSFER.0))?;
+    if let Err(error) = system_call_port_send(BOOTSTRAP_HANDLE, &request_message(DirectorRequest::Lookup, name, transfer)) {
+        system_call_handle_close(transfer)?;
+        return Err(error);
+    }
+    let mut message = Message::new();
+    loop {
+        match system_call_port_receive(reply, &mut message) {
+            Ok(())                      => return parse_reply(&message),
+            Err(ReturnCode::NotReady)   => system_call_yield(),
+            Err(error)                  => return Err(error),
+        }
+    }
+}
+
+
+// SERVER
+//Directory of services held by the director process
+pub struct Directory<const SIZE: usize> {
+    entries: [Option<(ServiceName, Handle)>; SIZE],
+}
+impl<const SIZE: usize> Directory<SIZE> {
+    //Constructor
+    pub const fn new() -> Self {
+        Self {entries: [None; SIZE]}
+    }
+
+    //Find the handle registered under a name
+    pub fn find(&self, name: &ServiceName) -> Option<Handle> {
+        self.entries.iter().flatten().find(|(entry_name, _)| entry_name == name).map(|(_, handle)| *handle)
+    }
+
+    //Register a service, rejecting names which are already taken
+    pub fn register(&mut self, name: ServiceName, service: Handle) -> Result<(), ReturnCode> {
+        if self.find(&name).is_some() {return Err(ReturnCode::AddressConflict)}
+        let slot = self.entries.iter_mut().find(|entry| entry.is_none()).ok_or(ReturnCode::DirectoryFull)?;
+        *slot = Some((name, service));
+        Ok(())
+    }
+
+    //Handle one request received on the bootstrap port
+    pub fn handle_request(&mut self, message: &Message) -> Result<(), ReturnCode> {
+        let (request, name, handle) = parse_request(message)?;
+        match request {
+            DirectorRequest::Register => {
+                let result = self.register(name, handle);
+                if result.is_err() {system_call_handle_close(handle)?;}
+                result
+            },
+            DirectorRequest::Lookup => {
+                let service = self.find(&name).ok_or(ReturnCode::NotFound).and_then(|service| system_call_handle_duplicate(service, SERVICE_RIGHTS));
+                let result = system_call_port_send(handle, &reply_message(service));
+                if let (Err(_), Ok(service)) = (&result, service) {system_call_handle_close(service)?;}
+                system_call_handle_close(handle)?;
+                result
+            },
+            DirectorRequest::Reply => Err(ReturnCode::InvalidData),
+        }
+    }
+
+    //Serve requests on the bootstrap port forever
+    pub fn serve(&mut self) -> ! {
+        let mut message = Message::new();
+        loop {
+            match system_call_port_receive(BOOTSTRAP_HANDLE, &mut message) {
+                Ok(())  => {let _ = self.handle_request(&message);},
+                Err(_)  => system_call_yield(),
+            }
+        }
+    }
+}
+impl<const SIZE: usize> Default for Directory<SIZE> {
+    fn default() -> Self {Self::new()}
+}
@@ -0,0 +1,26 @@
+// GLUON: NOBLE DISPLAY
+// Structs describing the framebuffer handed to a display server
+
+
+// FRAMEBUFFER
+//Framebuffer Information
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub struct FramebufferInfo {
+    pub size:           u64, //Bytes covered by the memory port, a whole number of pages
+    pub width:          u64, //Pixels per row
+    pub height:         u64, //Rows
+    pub pitch:          u64, //Bytes from the start of one row to the next
+    pub bits_per_pixel: u16,
+    pub red_size:       u8,  //Bits in each color channel
+    pub red_shift:      u8,  //Position of each color channel within a pixel
+    pub green_size:     u8,
+    pub green_shift:    u8,
+    pub blue_size:      u8,
+    pub blue_shift:     u8,
+}
+impl FramebufferInfo {
+    pub const EMPTY: Self = Self {size: 0, width: 0, height: 0, pitch: 0, bits_per_pixel: 0, red_size: 0, red_shift: 0, green_size: 0, green_shift: 0, blue_size: 0, blue_shift: 0};
+}
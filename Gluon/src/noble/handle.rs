@@ -0,0 +1,87 @@
+// GLUON: NOBLE HANDLES
+// Structs and enums related to the handles through which processes access kernel objects
+
+
+// HEADER
+//Imports
+use crate::numeric_enum;
+use core::convert::TryFrom;
+use core::ops::BitOr;
+
+//Constants
+pub const MESSAGE_DATA_SIZE: usize = 6; //NUMBER OF DATA WORDS CARRIED BY A PORT MESSAGE
+
+
+// HANDLES
+//Handle
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub struct Handle(pub u64);
+impl Handle {
+    pub const NONE: Self = Self(0xFFFF_FFFF_FFFF_FFFF);
+}
+
+//Handle Rights
+#[repr(transparent)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub struct HandleRights(pub u64);
+impl HandleRights {
+    pub const NONE:      Self = Self(0x0000);
+    pub const READ:      Self = Self(0x0001); //Object may be read from or waited on
+    pub const WRITE:     Self = Self(0x0002); //Object may be written to or modified
+    pub const MAP:       Self = Self(0x0004); //Object may be mapped into an address space
+    pub const DUPLICATE: Self = Self(0x0008); //Handle may be duplicated
+    pub const TRANSFER:  Self = Self(0x0010); //Handle may be sent to another process through a port
+    pub const ALL:       Self = Self(0x001F);
+
+    pub fn contains(&self, rights: HandleRights) -> bool {
+        self.0 & rights.0 == rights.0
+    }
+    pub fn intersect(&self, rights: HandleRights) -> Self {
+        Self(self.0 & rights.0)
+    }
+}
+impl BitOr for HandleRights {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+//Kernel Object Type
+numeric_enum! {
+    #[repr(u64)]
+    #[derive(Clone, Copy)]
+    #[derive(PartialEq, Eq)]
+    #[derive(Debug)]
+    pub enum KernelObjectType {
+        Port       = 0x01,
+        MemoryPort = 0x02,
+        Thread     = 0x03,
+        Timer      = 0x04,
+        Process    = 0x05,
+    }
+}
+
+
+// MESSAGES
+//Message sent through a port, optionally carrying a handle
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct Message {
+    pub data:   [u64; MESSAGE_DATA_SIZE],
+    pub handle: Handle,
+}
+impl Message {
+    pub const fn new() -> Self {
+        Self {data: [0; MESSAGE_DATA_SIZE], handle: Handle::NONE}
+    }
+}
+impl Default for Message {
+    fn default() -> Self {Self::new()}
+}
@@ -1,6 +1,12 @@
 // GLUON: Noble
 // Modules handling the Noble OS architecture:
 //   address_space: Constants and functions related to the Noble address space layout
+//   director:      Constants, structs, and functions for the protocol used to register and look up services with the director process
+//   display:       Structs describing the framebuffer handed to a display server
+//   handle:        Structs and enums related to the handles through which processes access kernel objects
+//   signal:        Constants and functions related to the numbered notifications posted to processes
+//   snapshot:      Structs and enums describing the processes and threads running under Noble
+//   sync:          Structs providing blocking synchronization between the threads of a program
 //   input_events:  Structs, enums, and functions for handling user keyboard, mouse, and controller inputs
 //   file_system:   Structs and traits for handling file systems in a generic manner
 
@@ -9,7 +15,13 @@
 //Modules
 pub mod address_space;
 pub mod data_type;
+pub mod director;
+pub mod display;
+pub mod handle;
 pub mod input_events;
 pub mod file_system;
 pub mod return_code;
+pub mod signal;
+pub mod snapshot;
+pub mod sync;
 pub mod system_calls;
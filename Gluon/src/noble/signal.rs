@@ -0,0 +1,21 @@
+// GLUON: NOBLE SIGNALS
+// Constants and functions related to the numbered notifications posted to processes
+
+
+// SIGNALS
+//Kernel Generated Signals
+pub const SIGNAL_CHILD_EXIT:  u64 = 0x00; //A child process has exited
+pub const SIGNAL_CHILD_FAULT: u64 = 0x01; //A child process was terminated by a processor fault
+pub const SIGNAL_TIMER:       u64 = 0x02; //A timer owned by the process has expired
+
+//User Signals
+pub const SIGNAL_USER_FIRST:  u64 = 0x08; //LOWEST SIGNAL NUMBER WHICH PROCESSES MAY SEND TO EACH OTHER
+pub const SIGNAL_COUNT:       u64 = 0x40; //NUMBER OF SIGNALS (ONE BIT PER SIGNAL IN A SIGNAL SET)
+
+//Exit code given to a process terminated by a processor fault
+pub const EXIT_CODE_FAULT: u64 = 0xFFFF_FFFF_FFFF_FFFF;
+
+//Signal Set Bit
+pub fn signal_bit(signal: u64) -> u64 {
+    if signal < SIGNAL_COUNT {1 << signal} else {0}
+}
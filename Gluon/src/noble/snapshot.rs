@@ -0,0 +1,101 @@
+// GLUON: NOBLE SNAPSHOT
+// Structs and enums describing the processes and threads running under Noble, as returned by the system snapshot system call
+
+
+// HEADER
+//Imports
+use crate::numeric_enum;
+use core::convert::TryFrom;
+
+//Constants
+pub const SNAPSHOT_PROCESSES: usize = 32; //MAXIMUM NUMBER OF PROCESSES RECORDED IN ONE SNAPSHOT
+pub const SNAPSHOT_THREADS:   usize = 32; //MAXIMUM NUMBER OF THREADS RECORDED IN ONE SNAPSHOT
+
+
+// THREADS
+//Thread Status
+numeric_enum! {
+    #[repr(u64)]
+    #[derive(Clone, Copy)]
+    #[derive(PartialEq, Eq)]
+    #[derive(Debug)]
+    pub enum ThreadStatus {
+        Running     = 0x00, //Thread made the snapshot system call
+        Ready       = 0x01, //Thread may be scheduled
+        WaitProcess = 0x02, //Thread is waiting for a process to exit
+        WaitSignal  = 0x03, //Thread is waiting for a signal
+        Exited      = 0x04, //Thread's process has exited but has not been reaped
+        WaitFutex   = 0x05, //Thread is waiting on a futex
+    }
+}
+
+//Thread Snapshot
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub struct ThreadSnapshot {
+    pub thread:        u64,
+    pub process:       u64,
+    pub status:        ThreadStatus,
+    pub priority:      u64,
+    pub user_cycles:   u64, //TSC cycles spent running in ring 3
+    pub kernel_cycles: u64, //TSC cycles spent running in ring 0, in system calls or kernel code
+    pub switches:      u64, //Times the scheduler switched to the thread
+    pub wakeups:       u64, //Times the thread left a waiting state
+}
+impl ThreadSnapshot {
+    pub const EMPTY: Self = Self {thread: 0, process: 0, status: ThreadStatus::Exited, priority: 0, user_cycles: 0, kernel_cycles: 0, switches: 0, wakeups: 0};
+}
+
+
+// PROCESSES
+//Process Snapshot
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub struct ProcessSnapshot {
+    pub process:   u64,
+    pub parent:    u64,  //Equal to the process for the kernel process, which has no parent
+    pub threads:   u64,
+    pub memory:    u64,  //Bytes in memory areas owned by the process
+    pub exited:    bool,
+    pub exit_code: u64,
+}
+impl ProcessSnapshot {
+    pub const EMPTY: Self = Self {process: 0, parent: 0, threads: 0, memory: 0, exited: false, exit_code: 0};
+}
+
+
+// SYSTEM
+//System Snapshot
+#[repr(C)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub struct SystemSnapshot {
+    pub uptime:        u64, //Nanoseconds since the monotonic clock started
+    pub tsc_frequency: u64, //TSC cycles per second, for converting thread cycle counts to time
+    pub process_count: u64,
+    pub thread_count:  u64,
+    pub processes:     [ProcessSnapshot; SNAPSHOT_PROCESSES],
+    pub threads:       [ThreadSnapshot; SNAPSHOT_THREADS],
+}
+impl SystemSnapshot {
+    pub const EMPTY: Self = Self {uptime: 0, tsc_frequency: 0, process_count: 0, thread_count: 0, processes: [ProcessSnapshot::EMPTY; SNAPSHOT_PROCESSES], threads: [ThreadSnapshot::EMPTY; SNAPSHOT_THREADS]};
+
+    //Recorded processes and threads
+    pub fn processes(&self) -> &[ProcessSnapshot] {
+        &self.processes[..(self.process_count as usize).min(SNAPSHOT_PROCESSES)]
+    }
+    pub fn threads(&self) -> &[ThreadSnapshot] {
+        &self.threads[..(self.thread_count as usize).min(SNAPSHOT_THREADS)]
+    }
+
+    //Convert a cycle count to nanoseconds
+    pub fn nanoseconds(&self, cycles: u64) -> u64 {
+        if self.tsc_frequency == 0 {return 0}
+        (cycles as u128 * 1_000_000_000 / self.tsc_frequency as u128) as u64
+    }
+}
@@ -0,0 +1,125 @@
+// GLUON: NOBLE SYNC
+// Structs providing blocking synchronization between the threads of a program, built on the futex system calls
+
+
+// HEADER
+//Imports
+use crate::noble::return_code::ReturnCode;
+use crate::noble::system_calls::*;
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+//Constants
+const UNLOCKED:  u32 = 0; //MUTEX IS FREE
+const LOCKED:    u32 = 1; //MUTEX IS HELD AND NO THREAD IS WAITING FOR IT
+const CONTENDED: u32 = 2; //MUTEX IS HELD AND THREADS MAY BE WAITING FOR IT
+
+
+// MUTEX
+//Mutex
+pub struct Mutex<T> {
+    state: AtomicU32,
+    data: UnsafeCell<T>,
+}
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+impl<T> Mutex<T> {
+    //Constructor
+    pub const fn new(data: T) -> Self {
+        Self {state: AtomicU32::new(UNLOCKED), data: UnsafeCell::new(data)}
+    }
+
+    //Take the lock, blocking until it is free
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        if self.state.compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed).is_err() {
+            //Mark the lock contended so that whoever releases it wakes a waiter
+            while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+                let _ = system_call_futex_wait(&self.state, CONTENDED, None);
+            }
+        }
+        MutexGuard {mutex: self}
+    }
+
+    //Take the lock if it is free
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.state.compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed).ok().map(|_| MutexGuard {mutex: self})
+    }
+
+    //Access the data without locking, which the exclusive borrow makes safe
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    //Release the lock, waking one waiter if there may be any
+    fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            let _ = system_call_futex_wake(&self.state, 1);
+        }
+    }
+}
+
+//Mutex Guard (releases the lock when dropped)
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe {&*self.mutex.data.get()}
+    }
+}
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe {&mut *self.mutex.data.get()}
+    }
+}
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        self.mutex.unlock();
+    }
+}
+
+
+// CONDITION VARIABLE
+//Condition Variable (waiters sleep on a sequence number which every notification advances)
+pub struct Condvar {
+    sequence: AtomicU32,
+}
+impl Condvar {
+    //Constructor
+    pub const fn new() -> Self {
+        Self {sequence: AtomicU32::new(0)}
+    }
+
+    //Release a lock and block until notified, then take the lock again (wakeups may be spurious, so the condition must be checked again)
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        self.wait_timeout(guard, None).0
+    }
+
+    //Release a lock and block until notified or a timeout in nanoseconds passes, then take the lock again, also returning whether the timeout passed
+    pub fn wait_timeout<'a, T>(&self, guard: MutexGuard<'a, T>, timeout: Option<u64>) -> (MutexGuard<'a, T>, bool) {
+        let sequence = self.sequence.load(Ordering::Relaxed);
+        let mutex = guard.mutex;
+        drop(guard);
+        let timed_out = system_call_futex_wait(&self.sequence, sequence, timeout) == Err(ReturnCode::TimeOut);
+        (mutex.lock(), timed_out)
+    }
+
+    //Wake one waiting thread
+    pub fn notify_one(&self) {
+        self.sequence.fetch_add(1, Ordering::Release);
+        let _ = system_call_futex_wake(&self.sequence, 1);
+    }
+
+    //Wake every waiting thread
+    pub fn notify_all(&self) {
+        self.sequence.fetch_add(1, Ordering::Release);
+        let _ = system_call_futex_wake(&self.sequence, u64::MAX);
+    }
+}
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -4,7 +4,48 @@
 
 // HEADER
 //Imports
+use crate::noble::display::FramebufferInfo;
+use crate::noble::handle::*;
+use crate::noble::return_code::ReturnCode;
+use crate::noble::snapshot::SystemSnapshot;
 use core::arch::asm;
+use core::convert::TryFrom;
+use core::sync::atomic::AtomicU32;
+
+//System Call Numbers
+pub const SYSTEM_CALL_DUMMY_RETURN:     u64 = 0x00;
+pub const SYSTEM_CALL_DUMMY_PRINT:      u64 = 0x01;
+pub const SYSTEM_CALL_TIME:             u64 = 0x02;
+pub const SYSTEM_CALL_HANDLE_CLOSE:     u64 = 0x03;
+pub const SYSTEM_CALL_HANDLE_DUPLICATE: u64 = 0x04;
+pub const SYSTEM_CALL_PORT_CREATE:      u64 = 0x05;
+pub const SYSTEM_CALL_PORT_SEND:        u64 = 0x06;
+pub const SYSTEM_CALL_PORT_RECEIVE:     u64 = 0x07;
+pub const SYSTEM_CALL_MEMORY_PORT_MAP:  u64 = 0x08;
+pub const SYSTEM_CALL_TIMER_CREATE:     u64 = 0x09;
+pub const SYSTEM_CALL_EXIT:             u64 = 0x0A;
+pub const SYSTEM_CALL_WAIT:             u64 = 0x0B;
+pub const SYSTEM_CALL_SIGNAL_THREAD:    u64 = 0x0C;
+pub const SYSTEM_CALL_SIGNAL_SEND:      u64 = 0x0D;
+pub const SYSTEM_CALL_SIGNAL_MASK:      u64 = 0x0E;
+pub const SYSTEM_CALL_SIGNAL_POLL:      u64 = 0x0F;
+pub const SYSTEM_CALL_SIGNAL_WAIT:      u64 = 0x10;
+pub const SYSTEM_CALL_WALL_TIME:        u64 = 0x11;
+pub const SYSTEM_CALL_REBOOT:           u64 = 0x12;
+pub const SYSTEM_CALL_POWER_OFF:        u64 = 0x13;
+pub const SYSTEM_CALL_FRAMEBUFFER_OPEN: u64 = 0x14;
+pub const SYSTEM_CALL_INTERRUPT_BIND:   u64 = 0x15;
+pub const SYSTEM_CALL_INTERRUPT_ACK:    u64 = 0x16;
+pub const SYSTEM_CALL_IO_PORT_GRANT:    u64 = 0x17;
+pub const SYSTEM_CALL_SYSTEM_SNAPSHOT:  u64 = 0x18;
+pub const SYSTEM_CALL_FUTEX_WAIT:       u64 = 0x19;
+pub const SYSTEM_CALL_FUTEX_WAKE:       u64 = 0x1A;
+pub const SYSTEM_CALL_MEMORY_PROTECT:   u64 = 0x1B;
+pub const SYSTEM_CALL_MODULE_OPEN:      u64 = 0x1C;
+
+//Memory Protection Flags
+pub const PROTECT_WRITE:   u64 = 0x01; //PAGES MAY BE WRITTEN
+pub const PROTECT_EXECUTE: u64 = 0x02; //PAGES MAY BE EXECUTED (NOT ALLOWED TOGETHER WITH WRITE)
 
 
 // STRUCTS
@@ -40,20 +81,193 @@ extern "sysv64" fn system_call(call_number: u64, arg1: u64, arg2: u64, arg3: u64
     SystemCallInternalReturnValue {code: output_a, value: output_b}
 }
 
+//Convert the output of a system call into a result
+#[inline(always)]
+fn system_call_result(output: SystemCallInternalReturnValue) -> Result<u64, ReturnCode> {
+    match ReturnCode::try_from(output.code) {
+        Ok(ReturnCode::NoError) => Ok(output.value),
+        Ok(code)                => Err(code),
+        Err(_)                  => Err(ReturnCode::UnknownError),
+    }
+}
+
+//Yield (gives up the remainder of the current time slice)
+#[inline(always)]
+pub fn system_call_yield() {
+    unsafe {asm!("INT 31h")}
+}
+
 //System Call 00 (Dummy Return)
 #[inline(always)]
 pub extern "sysv64" fn system_call_00() -> u64 {
-    system_call(0, 0, 0, 0).code
+    system_call(SYSTEM_CALL_DUMMY_RETURN, 0, 0, 0).code
 }
 
 //System Call 01 (Dummy Print)
 #[inline(always)]
 pub extern "sysv64" fn system_call_01() {
-    system_call(1, 0, 0, 0);
+    system_call(SYSTEM_CALL_DUMMY_PRINT, 0, 0, 0);
 }
 
-//System Call 02 (Time)
+//System Call 02 (Time, in nanoseconds since boot)
 #[inline(always)]
 pub extern "sysv64" fn system_call_02() -> u64 {
-    system_call(2, 0, 0, 0).code
+    system_call(SYSTEM_CALL_TIME, 0, 0, 0).code
+}
+
+//System Call 03 (Handle Close)
+#[inline(always)]
+pub fn system_call_handle_close(handle: Handle) -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_HANDLE_CLOSE, handle.0, 0, 0)).map(|_| ())
+}
+
+//System Call 04 (Handle Duplicate)
+#[inline(always)]
+pub fn system_call_handle_duplicate(handle: Handle, rights: HandleRights) -> Result<Handle, ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_HANDLE_DUPLICATE, handle.0, rights.0, 0)).map(Handle)
+}
+
+//System Call 05 (Port Create)
+#[inline(always)]
+pub fn system_call_port_create() -> Result<Handle, ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_PORT_CREATE, 0, 0, 0)).map(Handle)
+}
+
+//System Call 06 (Port Send)
+#[inline(always)]
+pub fn system_call_port_send(port: Handle, message: &Message) -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_PORT_SEND, port.0, message as *const Message as u64, 0)).map(|_| ())
+}
+
+//System Call 07 (Port Receive)
+#[inline(always)]
+pub fn system_call_port_receive(port: Handle, message: &mut Message) -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_PORT_RECEIVE, port.0, message as *mut Message as u64, 0)).map(|_| ())
+}
+
+//System Call 08 (Memory Port Map)
+#[inline(always)]
+pub fn system_call_memory_port_map(memory_port: Handle, address: usize) -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_MEMORY_PORT_MAP, memory_port.0, address as u64, 0)).map(|_| ())
+}
+
+//System Call 09 (Timer Create, expiring every divisor milliseconds at an offset of remainder milliseconds)
+#[inline(always)]
+pub fn system_call_timer_create(divisor: u64, remainder: u64) -> Result<Handle, ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_TIMER_CREATE, divisor, remainder, 0)).map(Handle)
+}
+
+//System Call 0A (Exit)
+#[inline(always)]
+pub fn system_call_exit(code: u64) -> ! {
+    system_call(SYSTEM_CALL_EXIT, code, 0, 0);
+    unreachable!()
+}
+
+//System Call 0B (Wait)
+#[inline(always)]
+pub fn system_call_wait(child: Handle) -> Result<u64, ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_WAIT, child.0, 0, 0))
+}
+
+//System Call 0C (Signal Thread)
+#[inline(always)]
+pub fn system_call_signal_thread(thread: Handle) -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_SIGNAL_THREAD, thread.0, 0, 0)).map(|_| ())
+}
+
+//System Call 0D (Signal Send)
+#[inline(always)]
+pub fn system_call_signal_send(process: Handle, signal: u64) -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_SIGNAL_SEND, process.0, signal, 0)).map(|_| ())
+}
+
+//System Call 0E (Signal Mask)
+#[inline(always)]
+pub fn system_call_signal_mask(mask: u64) -> Result<u64, ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_SIGNAL_MASK, mask, 0, 0))
+}
+
+//System Call 0F (Signal Poll)
+#[inline(always)]
+pub fn system_call_signal_poll() -> Result<u64, ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_SIGNAL_POLL, 0, 0, 0))
+}
+
+//System Call 10 (Signal Wait)
+#[inline(always)]
+pub fn system_call_signal_wait() -> Result<u64, ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_SIGNAL_WAIT, 0, 0, 0))
+}
+
+//System Call 11 (Wall Time, in nanoseconds since the Unix epoch)
+#[inline(always)]
+pub fn system_call_wall_time() -> Result<u64, ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_WALL_TIME, 0, 0, 0))
+}
+
+//System Call 12 (Reboot, privileged, returning only if every reset method fails)
+#[inline(always)]
+pub fn system_call_reboot() -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_REBOOT, 0, 0, 0)).map(|_| ())
+}
+
+//System Call 13 (Power Off, privileged, returning only if ACPI S5 is unavailable or fails)
+#[inline(always)]
+pub fn system_call_power_off() -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_POWER_OFF, 0, 0, 0)).map(|_| ())
+}
+
+//System Call 14 (Framebuffer Open, privileged, giving a memory port covering the framebuffer and describing its layout; once mapped, the kernel console stops drawing and discards keyboard input, which a keyboard driver must take over by binding IRQ 1)
+#[inline(always)]
+pub fn system_call_framebuffer_open(info: &mut FramebufferInfo) -> Result<Handle, ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_FRAMEBUFFER_OPEN, info as *mut FramebufferInfo as u64, 0, 0)).map(Handle)
+}
+
+//System Call 15 (Interrupt Bind, privileged, sending a message holding the IRQ in its first data word whenever the ISA IRQ line fires)
+#[inline(always)]
+pub fn system_call_interrupt_bind(irq: u8, port: Handle) -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_INTERRUPT_BIND, irq as u64, port.0, 0)).map(|_| ())
+}
+
+//System Call 16 (Interrupt Acknowledge, unmasking an IRQ line which was masked when its message was sent)
+#[inline(always)]
+pub fn system_call_interrupt_acknowledge(irq: u8, port: Handle) -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_INTERRUPT_ACK, irq as u64, port.0, 0)).map(|_| ())
+}
+
+//System Call 17 (I/O Port Grant, privileged, allowing a process to access a range of I/O ports from ring 3)
+#[inline(always)]
+pub fn system_call_io_port_grant(process: Handle, first: u16, count: u32) -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_IO_PORT_GRANT, process.0, first as u64, count as u64)).map(|_| ())
+}
+
+//System Call 18 (System Snapshot, recording every process and thread with its state, CPU time, and memory use)
+#[inline(always)]
+pub fn system_call_system_snapshot(snapshot: &mut SystemSnapshot) -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_SYSTEM_SNAPSHOT, snapshot as *mut SystemSnapshot as u64, 0, 0)).map(|_| ())
+}
+
+//System Call 19 (Futex Wait, blocking while a word holds an expected value until woken or, given a timeout in nanoseconds, failing with TimeOut once it passes)
+#[inline(always)]
+pub fn system_call_futex_wait(word: &AtomicU32, expected: u32, timeout: Option<u64>) -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_FUTEX_WAIT, word as *const AtomicU32 as u64, expected as u64, timeout.unwrap_or(u64::MAX))).map(|_| ())
+}
+
+//System Call 1A (Futex Wake, waking up to a number of threads waiting on a word and returning how many were woken)
+#[inline(always)]
+pub fn system_call_futex_wake(word: &AtomicU32, count: u64) -> Result<u64, ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_FUTEX_WAKE, word as *const AtomicU32 as u64, count, 0))
+}
+
+//System Call 1B (Memory Protect, changing whether pages of memory the process owns may be written or executed)
+#[inline(always)]
+pub fn system_call_memory_protect(address: usize, length: usize, flags: u64) -> Result<(), ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_MEMORY_PROTECT, address as u64, length as u64, flags)).map(|_| ())
+}
+
+//System Call 1C (Module Open, privileged, giving a read-only memory port covering a data module named in the boot manifest and its size in bytes)
+#[inline(always)]
+pub fn system_call_module_open(name: &str, size: &mut u64) -> Result<Handle, ReturnCode> {
+    system_call_result(system_call(SYSTEM_CALL_MODULE_OPEN, name.as_ptr() as u64, name.len() as u64, size as *mut u64 as u64)).map(Handle)
 }
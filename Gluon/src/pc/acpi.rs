@@ -0,0 +1,849 @@
+// GLUON: PC ACPI
+// Structs and functions related to the reading of Advanced Configuration and Power Interface tables
+//
+// Tables are read through a Volume addressed by physical address, so that the kernel can read them through its
+// physical memory window and tests can read them from byte dumps.
+
+
+// HEADER
+//Imports
+use crate::numeric_enum;
+use crate::noble::file_system::Volume;
+use crate::noble::return_code::ReturnCode;
+use core::convert::TryFrom;
+
+//Constants
+pub const RSDP_SIGNATURE:     [u8; 8] = *b"RSD PTR "; //SIGNATURE AT THE START OF THE ROOT SYSTEM DESCRIPTION POINTER
+pub const RSDP_SIZE_1:        usize   = 20;           //SIZE OF AN ACPI 1.0 RSDP
+pub const RSDP_SIZE_2:        usize   = 36;           //SIZE OF AN ACPI 2.0+ RSDP
+pub const HEADER_SIZE:        usize   = 36;           //SIZE OF A SYSTEM DESCRIPTION TABLE HEADER
+pub const SIGNATURE_RSDT:     [u8; 4] = *b"RSDT";     //ROOT SYSTEM DESCRIPTION TABLE (32-BIT ENTRIES)
+pub const SIGNATURE_XSDT:     [u8; 4] = *b"XSDT";     //EXTENDED SYSTEM DESCRIPTION TABLE (64-BIT ENTRIES)
+pub const SIGNATURE_MADT:     [u8; 4] = *b"APIC";     //MULTIPLE APIC DESCRIPTION TABLE
+pub const SIGNATURE_FADT:     [u8; 4] = *b"FACP";     //FIXED ACPI DESCRIPTION TABLE
+pub const SIGNATURE_HPET:     [u8; 4] = *b"HPET";     //HIGH PRECISION EVENT TIMER TABLE
+pub const SIGNATURE_MCFG:     [u8; 4] = *b"MCFG";     //PCI EXPRESS MEMORY MAPPED CONFIGURATION TABLE
+pub const SIGNATURE_DSDT:     [u8; 4] = *b"DSDT";     //DIFFERENTIATED SYSTEM DESCRIPTION TABLE
+pub const FADT_RESET_REG_SUP: u32     = 1 << 10;      //FADT FLAG: RESET REGISTER IS SUPPORTED
+pub const FADT_HW_REDUCED:    u32     = 1 << 20;      //FADT FLAG: HARDWARE REDUCED ACPI (NO FIXED HARDWARE)
+const FADT_SIZE:              usize   = 276;          //SIZE OF AN ACPI 6 FADT, OLDER TABLES ARE ZERO EXTENDED
+const MADT_ENTRIES:           usize   = 44;           //OFFSET OF THE FIRST MADT ENTRY
+const MCFG_ENTRIES:           usize   = 44;           //OFFSET OF THE FIRST MCFG ENTRY
+const MCFG_ENTRY_SIZE:        usize   = 16;           //SIZE OF AN MCFG ENTRY
+const AML_SCAN_WINDOW:        usize   = 0x40;         //AML BYTES AFTER A NAME CANDIDATE AVAILABLE WHILE DECODING IT
+const AML_NAME_OP:            u8      = 0x08;
+const AML_ROOT_CHAR:          u8      = b'\\';
+const AML_PACKAGE_OP:         u8      = 0x12;
+const AML_ZERO_OP:            u8      = 0x00;
+const AML_ONE_OP:             u8      = 0x01;
+const AML_BYTE_PREFIX:        u8      = 0x0A;
+const AML_WORD_PREFIX:        u8      = 0x0B;
+const AML_DWORD_PREFIX:       u8      = 0x0C;
+
+
+// ROOT SYSTEM DESCRIPTION POINTER
+//Root System Description Pointer
+#[derive(Clone, Copy, Debug)]
+pub struct Rsdp {
+    pub oem_id:       [u8; 6],
+    pub revision:     u8,
+    pub rsdt_address: u32,
+    pub xsdt_address: Option<u64>, //Only present from ACPI 2.0
+}
+impl Rsdp {
+    //Read and validate an RSDP
+    pub fn new<V: Volume>(volume: &V, address: u64) -> Result<Self, ReturnCode> {
+        let mut bytes = [0u8; RSDP_SIZE_2];
+        volume.read_all(address, &mut bytes[..RSDP_SIZE_1])?;
+        if bytes[0..8] != RSDP_SIGNATURE {return Err(ReturnCode::InvalidData)}
+        checksum(volume, address, RSDP_SIZE_1)?;
+        let revision = bytes[15];
+        let xsdt_address = if revision >= 2 {
+            volume.read_all(address, &mut bytes)?;
+            let length = le_u32(&bytes, 20) as usize;
+            if length < RSDP_SIZE_2 {return Err(ReturnCode::InvalidData)}
+            checksum(volume, address, length)?;
+            Some(le_u64(&bytes, 24))
+        } else {None};
+        Ok(Self {
+            oem_id: [bytes[9], bytes[10], bytes[11], bytes[12], bytes[13], bytes[14]],
+            revision,
+            rsdt_address: le_u32(&bytes, 16),
+            xsdt_address,
+        })
+    }
+}
+
+
+// SYSTEM DESCRIPTION TABLES
+//Table Header
+#[derive(Clone, Copy, Debug)]
+pub struct TableHeader {
+    pub address:      u64,
+    pub signature:    [u8; 4],
+    pub length:       u32,
+    pub revision:     u8,
+    pub oem_id:       [u8; 6],
+    pub oem_table_id: [u8; 8],
+}
+impl TableHeader {
+    //Read a table header without validating the table
+    pub fn new<V: Volume>(volume: &V, address: u64) -> Result<Self, ReturnCode> {
+        let mut bytes = [0u8; HEADER_SIZE];
+        volume.read_all(address, &mut bytes)?;
+        let length = le_u32(&bytes, 4);
+        if (length as usize) < HEADER_SIZE {return Err(ReturnCode::InvalidData)}
+        let mut oem_id = [0u8; 6];
+        let mut oem_table_id = [0u8; 8];
+        oem_id.copy_from_slice(&bytes[10..16]);
+        oem_table_id.copy_from_slice(&bytes[16..24]);
+        Ok(Self {
+            address,
+            signature: [bytes[0], bytes[1], bytes[2], bytes[3]],
+            length,
+            revision: bytes[8],
+            oem_id,
+            oem_table_id,
+        })
+    }
+
+    //Read the table body following the header, zero extending tables shorter than the buffer
+    fn read_body<V: Volume>(&self, volume: &V, buffer: &mut [u8]) -> Result<(), ReturnCode> {
+        let length = (self.length as usize).min(buffer.len());
+        volume.read_all(self.address, &mut buffer[..length])
+    }
+}
+
+//ACPI Tables
+pub struct Acpi<'v, V: Volume> {
+    volume:   &'v V,
+    pub rsdp: Rsdp,
+    pub root: TableHeader,
+}
+impl<'v, V: Volume> Acpi<'v, V> {
+    //Constructor, preferring the XSDT when present
+    pub fn new(volume: &'v V, rsdp_address: u64) -> Result<Self, ReturnCode> {
+        let rsdp = Rsdp::new(volume, rsdp_address)?;
+        let (address, signature) = match rsdp.xsdt_address {
+            Some(xsdt_address) if xsdt_address != 0 => (xsdt_address, SIGNATURE_XSDT),
+            _ => (rsdp.rsdt_address as u64, SIGNATURE_RSDT),
+        };
+        let root = TableHeader::new(volume, address)?;
+        if root.signature != signature {return Err(ReturnCode::InvalidData)}
+        checksum(volume, address, root.length as usize)?;
+        Ok(Self {volume, rsdp, root})
+    }
+
+    //Iterate over the addresses of the tables listed in the root table
+    pub fn tables(&self) -> impl Iterator<Item = Result<u64, ReturnCode>> + '_ {
+        let entry_size = if self.root.signature == SIGNATURE_XSDT {8} else {4};
+        let count = (self.root.length as usize - HEADER_SIZE) / entry_size;
+        (0..count).map(move |index| {
+            let mut bytes = [0u8; 8];
+            self.volume.read_all(self.root.address + (HEADER_SIZE + index * entry_size) as u64, &mut bytes[..entry_size])?;
+            Ok(le_u64(&bytes, 0))
+        })
+    }
+
+    //Find and validate a table by signature
+    pub fn find(&self, signature: [u8; 4]) -> Result<TableHeader, ReturnCode> {
+        for address in self.tables() {
+            let header = TableHeader::new(self.volume, address?)?;
+            if header.signature == signature {
+                checksum(self.volume, header.address, header.length as usize)?;
+                return Ok(header)
+            }
+        }
+        Err(ReturnCode::NotFound)
+    }
+
+    //Typed tables
+    pub fn madt(&self) -> Result<Madt<'v, V>, ReturnCode> {Madt::new(self.volume, self.find(SIGNATURE_MADT)?)}
+    pub fn fadt(&self) -> Result<Fadt,        ReturnCode> {Fadt::new(self.volume, self.find(SIGNATURE_FADT)?)}
+    pub fn hpet(&self) -> Result<Hpet,        ReturnCode> {Hpet::new(self.volume, self.find(SIGNATURE_HPET)?)}
+    pub fn mcfg(&self) -> Result<Mcfg<'v, V>, ReturnCode> {Ok(Mcfg {volume: self.volume, header: self.find(SIGNATURE_MCFG)?})}
+
+    //Find and validate the DSDT, which is referenced by the FADT rather than the root table
+    pub fn dsdt(&self) -> Result<TableHeader, ReturnCode> {
+        let header = TableHeader::new(self.volume, self.fadt()?.dsdt_address)?;
+        if header.signature != SIGNATURE_DSDT {return Err(ReturnCode::InvalidData)}
+        checksum(self.volume, header.address, header.length as usize)?;
+        Ok(header)
+    }
+
+    //Find the SLP_TYP values of a sleep state (5 for soft off) from its \_Sx package in the DSDT
+    pub fn sleep_type(&self, state: u8) -> Result<SleepType, ReturnCode> {
+        if state > 5 {return Err(ReturnCode::InvalidData)}
+        let header = self.dsdt()?;
+        let name = [b'_', b'S', b'0' + state, b'_'];
+        //Scan overlapping windows so that every candidate is preceded by its prefix bytes and followed by a full window of AML
+        let mut buffer = [0u8; AML_SCAN_WINDOW * 2 + 2];
+        let mut position = HEADER_SIZE;
+        let length = header.length as usize;
+        while position < length {
+            let start = position.saturating_sub(2).max(HEADER_SIZE);
+            let chunk = (length - start).min(buffer.len());
+            self.volume.read_all(header.address + start as u64, &mut buffer[..chunk])?;
+            let bytes = &buffer[..chunk];
+            for index in position - start..(position - start + AML_SCAN_WINDOW).min(chunk) {
+                if bytes[index..].starts_with(&name) && aml_named(bytes, index) {
+                    return SleepType::decode(&bytes[index + name.len()..])
+                }
+            }
+            position += AML_SCAN_WINDOW;
+        }
+        Err(ReturnCode::NotFound)
+    }
+}
+
+
+// GENERIC ADDRESS STRUCTURE
+//Address Space
+numeric_enum! {
+    #[repr(u8)]
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum AddressSpace {
+        SystemMemory            = 0x00,
+        SystemIo                = 0x01,
+        PciConfiguration        = 0x02,
+        EmbeddedController      = 0x03,
+        SystemManagementBus     = 0x04,
+        SystemCmos              = 0x05,
+        PciBarTarget            = 0x06,
+        FunctionalFixedHardware = 0x7F,
+    }
+}
+
+//Generic Address
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GenericAddress {
+    pub address_space: AddressSpace,
+    pub bit_width:     u8,
+    pub bit_offset:    u8,
+    pub access_size:   u8,
+    pub address:       u64,
+}
+impl GenericAddress {
+    //Read from the 12 bytes at an offset, a zero address meaning the register is absent
+    fn new(bytes: &[u8], offset: usize) -> Result<Option<Self>, ReturnCode> {
+        let address = le_u64(bytes, offset + 4);
+        if address == 0 {return Ok(None)}
+        Ok(Some(Self {
+            address_space: AddressSpace::try_from(bytes[offset]).map_err(|_| ReturnCode::InvalidData)?,
+            bit_width:     bytes[offset + 1],
+            bit_offset:    bytes[offset + 2],
+            access_size:   bytes[offset + 3],
+            address,
+        }))
+    }
+}
+
+
+// MULTIPLE APIC DESCRIPTION TABLE
+//Interrupt Polarity
+numeric_enum! {
+    #[repr(u16)]
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum Polarity {
+        Conforming = 0b00, //Conforms to the bus (active high for ISA)
+        ActiveHigh = 0b01,
+        ActiveLow  = 0b11,
+    }
+}
+
+//Interrupt Trigger Mode
+numeric_enum! {
+    #[repr(u16)]
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum TriggerMode {
+        Conforming = 0b00, //Conforms to the bus (edge for ISA)
+        Edge       = 0b01,
+        Level      = 0b11,
+    }
+}
+
+//Interrupt Flags
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct InterruptFlags {
+    pub polarity: Polarity,
+    pub trigger:  TriggerMode,
+}
+impl TryFrom<u16> for InterruptFlags {
+    type Error = ReturnCode;
+    fn try_from(flags: u16) -> Result<Self, ReturnCode> {
+        Ok(Self {
+            polarity: Polarity::try_from(flags & 0b11).map_err(|_| ReturnCode::InvalidData)?,
+            trigger:  TriggerMode::try_from((flags >> 2) & 0b11).map_err(|_| ReturnCode::InvalidData)?,
+        })
+    }
+}
+
+//MADT Entry
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MadtEntry {
+    LocalApic                {processor_id: u8, apic_id: u8, enabled: bool, online_capable: bool},
+    IoApic                   {id: u8, address: u32, interrupt_base: u32},
+    InterruptSourceOverride  {bus: u8, source: u8, interrupt: u32, flags: InterruptFlags},
+    NmiSource                {interrupt: u32, flags: InterruptFlags},
+    LocalApicNmi             {processor_id: u8, flags: InterruptFlags, lint: u8},
+    LocalApicAddressOverride {address: u64},
+    LocalX2Apic              {processor_id: u32, apic_id: u32, enabled: bool, online_capable: bool},
+    Unknown                  {entry_type: u8},
+}
+impl MadtEntry {
+    //Decode an entry from its type and body
+    fn new(entry_type: u8, body: &[u8]) -> Result<Self, ReturnCode> {
+        let minimum = match entry_type {0x00 => 6, 0x01 => 10, 0x02 => 8, 0x03 => 6, 0x04 => 4, 0x05 => 10, 0x09 => 14, _ => 0};
+        if body.len() < minimum {return Err(ReturnCode::InvalidData)}
+        Ok(match entry_type {
+            0x00 => Self::LocalApic {
+                processor_id: body[0],
+                apic_id: body[1],
+                enabled: le_u32(body, 2) & 0b01 != 0,
+                online_capable: le_u32(body, 2) & 0b10 != 0,
+            },
+            0x01 => Self::IoApic {id: body[0], address: le_u32(body, 2), interrupt_base: le_u32(body, 6)},
+            0x02 => Self::InterruptSourceOverride {
+                bus: body[0],
+                source: body[1],
+                interrupt: le_u32(body, 2),
+                flags: InterruptFlags::try_from(le_u16(body, 6))?,
+            },
+            0x03 => Self::NmiSource {flags: InterruptFlags::try_from(le_u16(body, 0))?, interrupt: le_u32(body, 2)},
+            0x04 => Self::LocalApicNmi {processor_id: body[0], flags: InterruptFlags::try_from(le_u16(body, 1))?, lint: body[3]},
+            0x05 => Self::LocalApicAddressOverride {address: le_u64(body, 2)},
+            0x09 => Self::LocalX2Apic {
+                apic_id: le_u32(body, 2),
+                enabled: le_u32(body, 6) & 0b01 != 0,
+                online_capable: le_u32(body, 6) & 0b10 != 0,
+                processor_id: le_u32(body, 10),
+            },
+            entry_type => Self::Unknown {entry_type},
+        })
+    }
+}
+
+//Multiple APIC Description Table
+pub struct Madt<'v, V: Volume> {
+    volume:                 &'v V,
+    pub header:             TableHeader,
+    pub local_apic_address: u64, //Includes any address override entry
+    pub flags:              u32,
+}
+impl<'v, V: Volume> Madt<'v, V> {
+    //Constructor
+    fn new(volume: &'v V, header: TableHeader) -> Result<Self, ReturnCode> {
+        let mut bytes = [0u8; MADT_ENTRIES];
+        if (header.length as usize) < MADT_ENTRIES {return Err(ReturnCode::InvalidData)}
+        header.read_body(volume, &mut bytes)?;
+        let mut madt = Self {volume, header, local_apic_address: le_u32(&bytes, 36) as u64, flags: le_u32(&bytes, 40)};
+        for entry in madt.entries() {
+            if let MadtEntry::LocalApicAddressOverride {address} = entry? {madt.local_apic_address = address}
+        }
+        Ok(madt)
+    }
+
+    //Test if the system also has dual 8259 PICs which must be disabled before using the APICs
+    pub fn pc_at_compatible(&self) -> bool {
+        self.flags & 0b1 != 0
+    }
+
+    //Iterate over entries
+    pub fn entries(&self) -> MadtIterator<'v, V> {
+        MadtIterator {
+            volume: self.volume,
+            position: self.header.address + MADT_ENTRIES as u64,
+            end: self.header.address + self.header.length as u64,
+        }
+    }
+}
+
+//MADT Entry Iterator
+pub struct MadtIterator<'v, V: Volume> {
+    volume:   &'v V,
+    position: u64,
+    end:      u64,
+}
+impl<'v, V: Volume> Iterator for MadtIterator<'v, V> {
+    type Item = Result<MadtEntry, ReturnCode>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position + 2 > self.end {return None}
+        let mut bytes = [0u8; 0x20];
+        if let Err(error) = self.volume.read_all(self.position, &mut bytes[..2]) {self.position = self.end; return Some(Err(error))}
+        //Stop at an entry without a valid length, as the following entries cannot be located
+        let length = bytes[1] as usize;
+        if length < 2 || self.position + length as u64 > self.end {self.position = self.end; return Some(Err(ReturnCode::InvalidData))}
+        //Longer entries (of types not decoded here) are skipped past by their length
+        let read = length.min(bytes.len());
+        let result = self.volume.read_all(self.position, &mut bytes[..read]).and_then(|_| MadtEntry::new(bytes[0], &bytes[2..read]));
+        self.position += length as u64;
+        Some(result)
+    }
+}
+
+
+// FIXED ACPI DESCRIPTION TABLE
+//Fixed ACPI Description Table
+#[derive(Clone, Copy, Debug)]
+pub struct Fadt {
+    pub header:                TableHeader,
+    pub firmware_control:      u64,
+    pub dsdt_address:          u64,
+    pub sci_interrupt:         u16,
+    pub smi_command:           u32,
+    pub acpi_enable:           u8,
+    pub acpi_disable:          u8,
+    pub pm1a_event_block:      u32,
+    pub pm1b_event_block:      u32,
+    pub pm1a_control_block:    u32,
+    pub pm1b_control_block:    u32,
+    pub pm_timer_block:        u32,
+    pub pm_timer_length:       u8,
+    pub century:               u8,  //CMOS index of the RTC century, zero if not present
+    pub boot_architecture:     u16, //IA-PC boot architecture flags
+    pub flags:                 u32,
+    pub reset_register:        Option<GenericAddress>,
+    pub reset_value:           u8,
+}
+impl Fadt {
+    //Constructor
+    fn new<V: Volume>(volume: &V, header: TableHeader) -> Result<Self, ReturnCode> {
+        let mut bytes = [0u8; FADT_SIZE];
+        header.read_body(volume, &mut bytes)?;
+        //64-bit addresses replace 32-bit addresses where present
+        let x_firmware_control = le_u64(&bytes, 132);
+        let x_dsdt = le_u64(&bytes, 140);
+        Ok(Self {
+            header,
+            firmware_control:   if x_firmware_control != 0 {x_firmware_control} else {le_u32(&bytes, 36) as u64},
+            dsdt_address:       if x_dsdt != 0 {x_dsdt} else {le_u32(&bytes, 40) as u64},
+            sci_interrupt:      le_u16(&bytes, 46),
+            smi_command:        le_u32(&bytes, 48),
+            acpi_enable:        bytes[52],
+            acpi_disable:       bytes[53],
+            pm1a_event_block:   le_u32(&bytes, 56),
+            pm1b_event_block:   le_u32(&bytes, 60),
+            pm1a_control_block: le_u32(&bytes, 64),
+            pm1b_control_block: le_u32(&bytes, 68),
+            pm_timer_block:     le_u32(&bytes, 76),
+            pm_timer_length:    bytes[91],
+            century:            bytes[108],
+            boot_architecture:  le_u16(&bytes, 109),
+            flags:              le_u32(&bytes, 112),
+            reset_register:     if le_u32(&bytes, 112) & FADT_RESET_REG_SUP != 0 {GenericAddress::new(&bytes, 116)?} else {None},
+            reset_value:        bytes[128],
+        })
+    }
+
+    //Test if legacy devices (such as the RTC and PS/2 controller) are present
+    pub fn legacy_devices(&self) -> bool {
+        self.boot_architecture & 0b01 != 0 || self.header.revision < 2
+    }
+}
+
+
+// HIGH PRECISION EVENT TIMER TABLE
+//High Precision Event Timer Table
+#[derive(Clone, Copy, Debug)]
+pub struct Hpet {
+    pub header:             TableHeader,
+    pub hardware_revision:  u8,
+    pub comparator_count:   u8,
+    pub counter_64:         bool,
+    pub legacy_replacement: bool,
+    pub pci_vendor_id:      u16,
+    pub base_address:       GenericAddress,
+    pub number:             u8,
+    pub minimum_tick:       u16,
+}
+impl Hpet {
+    //Constructor
+    fn new<V: Volume>(volume: &V, header: TableHeader) -> Result<Self, ReturnCode> {
+        let mut bytes = [0u8; 56];
+        if (header.length as usize) < bytes.len() {return Err(ReturnCode::InvalidData)}
+        header.read_body(volume, &mut bytes)?;
+        let block_id = le_u32(&bytes, 36);
+        Ok(Self {
+            header,
+            hardware_revision:  block_id as u8,
+            comparator_count:   ((block_id >> 8) & 0x1F) as u8 + 1,
+            counter_64:         block_id & (1 << 13) != 0,
+            legacy_replacement: block_id & (1 << 15) != 0,
+            pci_vendor_id:      (block_id >> 16) as u16,
+            base_address:       GenericAddress::new(&bytes, 40)?.ok_or(ReturnCode::InvalidData)?,
+            number:             bytes[52],
+            minimum_tick:       le_u16(&bytes, 53),
+        })
+    }
+}
+
+
+// DIFFERENTIATED SYSTEM DESCRIPTION TABLE
+//Sleep Type (values for the SLP_TYP fields of the PM1a and PM1b control registers)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SleepType {
+    pub pm1a: u8,
+    pub pm1b: u8,
+}
+impl SleepType {
+    //Decode the package following a \_Sx name, being a PackageOp, PkgLength, NumElements, and at least two integers
+    fn decode(bytes: &[u8]) -> Result<Self, ReturnCode> {
+        if bytes.first() != Some(&AML_PACKAGE_OP) {return Err(ReturnCode::InvalidData)}
+        let length_bytes = 1 + (*bytes.get(1).ok_or(ReturnCode::InvalidData)? >> 6) as usize;
+        let mut position = 1 + length_bytes;
+        if *bytes.get(position).ok_or(ReturnCode::InvalidData)? < 2 {return Err(ReturnCode::InvalidData)}
+        position += 1;
+        let pm1a = aml_integer(bytes, &mut position)?;
+        let pm1b = aml_integer(bytes, &mut position)?;
+        Ok(Self {pm1a: pm1a as u8 & 0b111, pm1b: pm1b as u8 & 0b111})
+    }
+}
+
+
+// PCI EXPRESS MEMORY MAPPED CONFIGURATION TABLE
+//MCFG Entry
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct McfgEntry {
+    pub base_address:  u64,
+    pub segment_group: u16,
+    pub start_bus:     u8,
+    pub end_bus:       u8,
+}
+
+//PCI Express Memory Mapped Configuration Table
+pub struct Mcfg<'v, V: Volume> {
+    volume:     &'v V,
+    pub header: TableHeader,
+}
+impl<'v, V: Volume> Mcfg<'v, V> {
+    //Iterate over configuration space areas
+    pub fn entries(&self) -> impl Iterator<Item = Result<McfgEntry, ReturnCode>> + '_ {
+        let count = (self.header.length as usize).saturating_sub(MCFG_ENTRIES) / MCFG_ENTRY_SIZE;
+        (0..count).map(move |index| {
+            let mut bytes = [0u8; MCFG_ENTRY_SIZE];
+            self.volume.read_all(self.header.address + (MCFG_ENTRIES + index * MCFG_ENTRY_SIZE) as u64, &mut bytes)?;
+            Ok(McfgEntry {
+                base_address:  le_u64(&bytes, 0),
+                segment_group: le_u16(&bytes, 8),
+                start_bus:     bytes[10],
+                end_bus:       bytes[11],
+            })
+        })
+    }
+}
+
+
+// FUNCTIONS
+//Check that the bytes of a structure sum to zero
+fn checksum<V: Volume>(volume: &V, address: u64, length: usize) -> Result<(), ReturnCode> {
+    let mut buffer = [0u8; 0x40];
+    let mut sum: u8 = 0;
+    let mut position = 0;
+    while position < length {
+        let chunk = (length - position).min(buffer.len());
+        volume.read_all(address + position as u64, &mut buffer[..chunk])?;
+        sum = buffer[..chunk].iter().fold(sum, |sum, byte| sum.wrapping_add(*byte));
+        position += chunk;
+    }
+    if sum == 0 {Ok(())} else {Err(ReturnCode::CrcError)}
+}
+
+//Test if a name found in AML is being defined by a NameOp, optionally through a root prefix
+fn aml_named(bytes: &[u8], index: usize) -> bool {
+    let before = |distance: usize| if index >= distance {Some(bytes[index - distance])} else {None};
+    match before(1) {
+        Some(AML_NAME_OP)   => true,
+        Some(AML_ROOT_CHAR) => before(2) == Some(AML_NAME_OP),
+        _                   => false,
+    }
+}
+
+//Decode an AML integer constant, advancing the position past it
+fn aml_integer(bytes: &[u8], position: &mut usize) -> Result<u32, ReturnCode> {
+    let opcode = *bytes.get(*position).ok_or(ReturnCode::InvalidData)?;
+    let size = match opcode {
+        AML_ZERO_OP | AML_ONE_OP => 0,
+        AML_BYTE_PREFIX          => 1,
+        AML_WORD_PREFIX          => 2,
+        AML_DWORD_PREFIX         => 4,
+        _                        => return Err(ReturnCode::InvalidData),
+    };
+    let data = bytes.get(*position + 1..*position + 1 + size).ok_or(ReturnCode::InvalidData)?;
+    *position += 1 + size;
+    Ok(match opcode {
+        AML_ONE_OP => 1,
+        _          => data.iter().rev().fold(0, |value, byte| value << 8 | *byte as u32),
+    })
+}
+
+//Little endian reads
+fn le_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+fn le_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+fn le_u64(bytes: &[u8], offset: usize) -> u64 {
+    le_u32(bytes, offset) as u64 | (le_u32(bytes, offset + 4) as u64) << 32
+}
+
+
+// TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Table dumps from a QEMU q35 machine with two processors
+    const RSDP: [u8; 36] = [
+        0x52, 0x53, 0x44, 0x20, 0x50, 0x54, 0x52, 0x20, 0x10, 0x42, 0x4F, 0x43, 0x48, 0x53, 0x20, 0x02,
+        0x40, 0x00, 0x00, 0x00, 0x24, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x5C, 0x00, 0x00, 0x00,
+    ];
+    const RSDT: [u8; 52] = [
+        0x52, 0x53, 0x44, 0x54, 0x34, 0x00, 0x00, 0x00, 0x01, 0x3A, 0x42, 0x4F, 0x43, 0x48, 0x53, 0x20,
+        0x42, 0x58, 0x50, 0x43, 0x20, 0x20, 0x20, 0x20, 0x01, 0x00, 0x00, 0x00, 0x42, 0x58, 0x50, 0x43,
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x40, 0x03, 0x00, 0x00,
+        0xA0, 0x03, 0x00, 0x00,
+    ];
+    const XSDT: [u8; 68] = [
+        0x58, 0x53, 0x44, 0x54, 0x44, 0x00, 0x00, 0x00, 0x01, 0x24, 0x42, 0x4F, 0x43, 0x48, 0x53, 0x20,
+        0x42, 0x58, 0x50, 0x43, 0x20, 0x20, 0x20, 0x20, 0x01, 0x00, 0x00, 0x00, 0x42, 0x58, 0x50, 0x43,
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x40, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xA0, 0x03, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+    ];
+    const MADT: [u8; 128] = [
+        0x41, 0x50, 0x49, 0x43, 0x80, 0x00, 0x00, 0x00, 0x01, 0x77, 0x42, 0x4F, 0x43, 0x48, 0x53, 0x20,
+        0x42, 0x58, 0x50, 0x43, 0x20, 0x20, 0x20, 0x20, 0x01, 0x00, 0x00, 0x00, 0x42, 0x58, 0x50, 0x43,
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE0, 0xFE, 0x01, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00,
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x08, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x01, 0x0C, 0x00, 0x00,
+        0x00, 0x00, 0xC0, 0xFE, 0x00, 0x00, 0x00, 0x00, 0x02, 0x0A, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x02, 0x0A, 0x00, 0x05, 0x05, 0x00, 0x00, 0x00, 0x0D, 0x00, 0x02, 0x0A, 0x00, 0x09,
+        0x09, 0x00, 0x00, 0x00, 0x0D, 0x00, 0x02, 0x0A, 0x00, 0x0A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x00,
+        0x02, 0x0A, 0x00, 0x0B, 0x0B, 0x00, 0x00, 0x00, 0x0D, 0x00, 0x04, 0x06, 0xFF, 0x00, 0x00, 0x01,
+    ];
+    const FADT: [u8; 244] = [
+        0x46, 0x41, 0x43, 0x50, 0xF4, 0x00, 0x00, 0x00, 0x03, 0xB9, 0x42, 0x4F, 0x43, 0x48, 0x53, 0x20,
+        0x42, 0x58, 0x50, 0x43, 0x20, 0x20, 0x20, 0x20, 0x01, 0x00, 0x00, 0x00, 0x42, 0x58, 0x50, 0x43,
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFE, 0x7F, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00,
+        0xB2, 0x00, 0x00, 0x00, 0xF1, 0xF0, 0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x04, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x06, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x32, 0x02, 0x00, 0x00,
+        0xA5, 0x84, 0x00, 0x00, 0x01, 0x08, 0x00, 0x00, 0xF9, 0x0C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x0F, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00,
+    ];
+    const HPET: [u8; 56] = [
+        0x48, 0x50, 0x45, 0x54, 0x38, 0x00, 0x00, 0x00, 0x01, 0x34, 0x42, 0x4F, 0x43, 0x48, 0x53, 0x20,
+        0x42, 0x58, 0x50, 0x43, 0x20, 0x20, 0x20, 0x20, 0x01, 0x00, 0x00, 0x00, 0x42, 0x58, 0x50, 0x43,
+        0x01, 0x00, 0x00, 0x00, 0x01, 0xA2, 0x86, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xD0, 0xFE,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x80, 0x00, 0x00,
+    ];
+    const MCFG: [u8; 60] = [
+        0x4D, 0x43, 0x46, 0x47, 0x3C, 0x00, 0x00, 0x00, 0x01, 0x8C, 0x42, 0x4F, 0x43, 0x48, 0x53, 0x20,
+        0x42, 0x58, 0x50, 0x43, 0x20, 0x20, 0x20, 0x20, 0x01, 0x00, 0x00, 0x00, 0x42, 0x58, 0x50, 0x43,
+        0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xB0,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    //Hand assembled DSDT holding sleep packages, a decoy string, and a buffer pushing \_S5 past the first scan window
+    const DSDT: [u8; 180] = [
+        0x44, 0x53, 0x44, 0x54, 0xB4, 0x00, 0x00, 0x00, 0x01, 0xA9, 0x42, 0x4F, 0x43, 0x48, 0x53, 0x20,
+        0x42, 0x58, 0x50, 0x43, 0x20, 0x20, 0x20, 0x20, 0x01, 0x00, 0x00, 0x00, 0x42, 0x58, 0x50, 0x43,
+        0x01, 0x00, 0x00, 0x00, 0x08, 0x5F, 0x53, 0x33, 0x5F, 0x12, 0x06, 0x04, 0x01, 0x01, 0x00, 0x00,
+        0x08, 0x53, 0x54, 0x52, 0x30, 0x0D, 0x5F, 0x53, 0x35, 0x5F, 0x00, 0x08, 0x42, 0x55, 0x46, 0x30,
+        0x11, 0x40, 0x62, 0x0A, 0x60, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x08, 0x5C, 0x5F, 0x53, 0x35, 0x5F, 0x12, 0x0A, 0x04, 0x0A, 0x05,
+        0x0A, 0x05, 0x00, 0x00,
+    ];
+
+
+    //Physical memory image holding the dumps
+    const RSDP_ADDRESS: usize = 0x000;
+    const RSDT_ADDRESS: usize = 0x040;
+    const XSDT_ADDRESS: usize = 0x080;
+    const MADT_ADDRESS: usize = 0x100;
+    const FADT_ADDRESS: usize = 0x200;
+    const HPET_ADDRESS: usize = 0x340;
+    const MCFG_ADDRESS: usize = 0x3A0;
+    const DSDT_ADDRESS: usize = 0x800;
+    struct TestMemory([u8; 0x900]);
+    impl TestMemory {
+        fn new() -> Self {
+            let mut memory = Self([0u8; 0x900]);
+            for (address, table) in [(RSDP_ADDRESS, &RSDP[..]), (RSDT_ADDRESS, &RSDT[..]), (XSDT_ADDRESS, &XSDT[..]), (MADT_ADDRESS, &MADT[..]), (FADT_ADDRESS, &FADT[..]), (HPET_ADDRESS, &HPET[..]), (MCFG_ADDRESS, &MCFG[..]), (DSDT_ADDRESS, &DSDT[..])] {
+                memory.0[address..address + table.len()].copy_from_slice(table);
+            }
+            memory
+        }
+    }
+    impl Volume for TestMemory {
+        fn read(&self, offset: u64, buffer: &mut [u8]) -> Result<u64, ReturnCode> {
+            let offset = offset as usize;
+            let bytes = self.0.get(offset..offset + buffer.len()).ok_or(ReturnCode::EndOfVolume)?;
+            buffer.copy_from_slice(bytes);
+            Ok(buffer.len() as u64)
+        }
+        fn write(&self, _offset: u64, _buffer: &[u8]) -> Result<u64, ReturnCode> {
+            Err(ReturnCode::WriteProtected)
+        }
+    }
+
+    #[test]
+    fn rsdp() {
+        let memory = TestMemory::new();
+        let rsdp = Rsdp::new(&memory, RSDP_ADDRESS as u64).unwrap();
+        assert_eq!(&rsdp.oem_id, b"BOCHS ");
+        assert_eq!(rsdp.revision, 2);
+        assert_eq!(rsdp.rsdt_address, RSDT_ADDRESS as u32);
+        assert_eq!(rsdp.xsdt_address, Some(XSDT_ADDRESS as u64));
+    }
+
+    #[test]
+    fn rsdp_corrupted() {
+        let mut memory = TestMemory::new();
+        memory.0[RSDP_ADDRESS + 16] ^= 0x01;
+        assert_eq!(Rsdp::new(&memory, RSDP_ADDRESS as u64).unwrap_err(), ReturnCode::CrcError);
+        memory.0[RSDP_ADDRESS] = b'X';
+        assert_eq!(Rsdp::new(&memory, RSDP_ADDRESS as u64).unwrap_err(), ReturnCode::InvalidData);
+    }
+
+    #[test]
+    fn root_tables() {
+        let memory = TestMemory::new();
+        let acpi = Acpi::new(&memory, RSDP_ADDRESS as u64).unwrap();
+        assert_eq!(acpi.root.signature, SIGNATURE_XSDT);
+        let tables: Vec<u64> = acpi.tables().map(Result::unwrap).collect();
+        assert_eq!(tables, [MADT_ADDRESS as u64, FADT_ADDRESS as u64, HPET_ADDRESS as u64, MCFG_ADDRESS as u64]);
+        assert_eq!(acpi.find(*b"SSDT").unwrap_err(), ReturnCode::NotFound);
+    }
+
+    #[test]
+    fn root_tables_acpi_1() {
+        //Without an XSDT address the RSDT is used
+        let mut memory = TestMemory::new();
+        memory.0[RSDP_ADDRESS + 15] = 0;
+        memory.0[RSDP_ADDRESS + 8] = memory.0[RSDP_ADDRESS + 8].wrapping_add(2);
+        let acpi = Acpi::new(&memory, RSDP_ADDRESS as u64).unwrap();
+        assert_eq!(acpi.root.signature, SIGNATURE_RSDT);
+        assert_eq!(acpi.tables().count(), 4);
+        assert_eq!(acpi.find(SIGNATURE_HPET).unwrap().address, HPET_ADDRESS as u64);
+    }
+
+    #[test]
+    fn table_corrupted() {
+        let mut memory = TestMemory::new();
+        memory.0[MADT_ADDRESS + 40] ^= 0x01;
+        let acpi = Acpi::new(&memory, RSDP_ADDRESS as u64).unwrap();
+        assert_eq!(acpi.madt().err(), Some(ReturnCode::CrcError));
+        assert!(acpi.fadt().is_ok());
+    }
+
+    #[test]
+    fn madt() {
+        let memory = TestMemory::new();
+        let acpi = Acpi::new(&memory, RSDP_ADDRESS as u64).unwrap();
+        let madt = acpi.madt().unwrap();
+        assert_eq!(madt.local_apic_address, 0xFEE0_0000);
+        assert!(madt.pc_at_compatible());
+        let entries: Vec<MadtEntry> = madt.entries().map(Result::unwrap).collect();
+        assert_eq!(entries.len(), 9);
+        assert_eq!(entries[0], MadtEntry::LocalApic {processor_id: 0, apic_id: 0, enabled: true, online_capable: false});
+        assert_eq!(entries[1], MadtEntry::LocalApic {processor_id: 1, apic_id: 1, enabled: true, online_capable: false});
+        assert_eq!(entries[2], MadtEntry::IoApic {id: 0, address: 0xFEC0_0000, interrupt_base: 0});
+        assert_eq!(entries[3], MadtEntry::InterruptSourceOverride {
+            bus: 0, source: 0, interrupt: 2,
+            flags: InterruptFlags {polarity: Polarity::Conforming, trigger: TriggerMode::Conforming},
+        });
+        assert_eq!(entries[4], MadtEntry::InterruptSourceOverride {
+            bus: 0, source: 5, interrupt: 5,
+            flags: InterruptFlags {polarity: Polarity::ActiveHigh, trigger: TriggerMode::Level},
+        });
+        assert_eq!(entries[8], MadtEntry::LocalApicNmi {
+            processor_id: 0xFF, lint: 1,
+            flags: InterruptFlags {polarity: Polarity::Conforming, trigger: TriggerMode::Conforming},
+        });
+    }
+
+    #[test]
+    fn madt_long_entries() {
+        //An entry longer than any decoded here is skipped by its length, and a zero length ends the entries
+        let mut memory = TestMemory::new();
+        let entries = [0x0E, 0x30, 0x00, 0x00];
+        let local_apic = [0x00, 0x08, 0x03, 0x04, 0x01, 0x00, 0x00, 0x00];
+        memory.0[0x400..0x404].copy_from_slice(&entries);
+        memory.0[0x430..0x438].copy_from_slice(&local_apic);
+        memory.0[0x438..0x43A].copy_from_slice(&[0x01, 0x00]);
+        let iterator = MadtIterator {volume: &memory, position: 0x400, end: 0x440};
+        let entries: Vec<Result<MadtEntry, ReturnCode>> = iterator.collect();
+        assert_eq!(entries, [
+            Ok(MadtEntry::Unknown {entry_type: 0x0E}),
+            Ok(MadtEntry::LocalApic {processor_id: 3, apic_id: 4, enabled: true, online_capable: false}),
+            Err(ReturnCode::InvalidData),
+        ]);
+    }
+
+    #[test]
+    fn fadt() {
+        let memory = TestMemory::new();
+        let acpi = Acpi::new(&memory, RSDP_ADDRESS as u64).unwrap();
+        let fadt = acpi.fadt().unwrap();
+        assert_eq!(fadt.dsdt_address, 0x800);
+        assert_eq!(fadt.sci_interrupt, 9);
+        assert_eq!(fadt.smi_command, 0xB2);
+        assert_eq!((fadt.acpi_enable, fadt.acpi_disable), (0xF1, 0xF0));
+        assert_eq!(fadt.pm1a_control_block, 0x604);
+        assert_eq!((fadt.pm_timer_block, fadt.pm_timer_length), (0x608, 4));
+        assert_eq!(fadt.century, 0x32);
+        assert_eq!(fadt.boot_architecture, 0x0002);
+        assert!(!fadt.legacy_devices());
+        assert_eq!(fadt.reset_register, Some(GenericAddress {address_space: AddressSpace::SystemIo, bit_width: 8, bit_offset: 0, access_size: 0, address: 0xCF9}));
+        assert_eq!(fadt.reset_value, 0x0F);
+    }
+
+    #[test]
+    fn hpet() {
+        let memory = TestMemory::new();
+        let acpi = Acpi::new(&memory, RSDP_ADDRESS as u64).unwrap();
+        let hpet = acpi.hpet().unwrap();
+        assert_eq!(hpet.hardware_revision, 1);
+        assert_eq!(hpet.comparator_count, 3);
+        assert!(hpet.counter_64);
+        assert!(hpet.legacy_replacement);
+        assert_eq!(hpet.pci_vendor_id, 0x8086);
+        assert_eq!(hpet.base_address.address_space, AddressSpace::SystemMemory);
+        assert_eq!(hpet.base_address.address, 0xFED0_0000);
+        assert_eq!(hpet.minimum_tick, 0x80);
+    }
+
+    #[test]
+    fn mcfg() {
+        let memory = TestMemory::new();
+        let acpi = Acpi::new(&memory, RSDP_ADDRESS as u64).unwrap();
+        let mcfg = acpi.mcfg().unwrap();
+        let entries: Vec<McfgEntry> = mcfg.entries().map(Result::unwrap).collect();
+        assert_eq!(entries, [McfgEntry {base_address: 0xB000_0000, segment_group: 0, start_bus: 0, end_bus: 0xFF}]);
+    }
+
+    #[test]
+    fn sleep_type() {
+        let memory = TestMemory::new();
+        let acpi = Acpi::new(&memory, RSDP_ADDRESS as u64).unwrap();
+        assert_eq!(acpi.dsdt().unwrap().length, DSDT.len() as u32);
+        assert_eq!(acpi.sleep_type(3).unwrap(), SleepType {pm1a: 1, pm1b: 1});
+        assert_eq!(acpi.sleep_type(5).unwrap(), SleepType {pm1a: 5, pm1b: 5});
+        assert_eq!(acpi.sleep_type(4).unwrap_err(), ReturnCode::NotFound);
+    }
+}
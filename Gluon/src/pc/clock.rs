@@ -0,0 +1,332 @@
+// GLUON: PC CLOCK
+// Traits, structs, and functions related to clock sources and the calibrated monotonic clock built on them
+
+
+// HEADER
+//Imports
+use crate::pc::hpet::HpetDevice;
+use crate::pc::pit;
+use crate::x86_64::instructions::cpuid;
+use crate::x86_64::instructions::rdtsc;
+
+//Constants
+pub const NANOSECONDS:         u64 = 1_000_000_000; //NANOSECONDS IN A SECOND
+pub const CALIBRATION_PERIOD:  u64 = 10_000_000;    //DEFAULT CALIBRATION INTERVAL IN NANOSECONDS
+const CPUID_EXTENDED_MAX:      u32 = 0x8000_0000;   //LEAF GIVING THE HIGHEST EXTENDED LEAF
+const CPUID_ADVANCED_POWER:    u32 = 0x8000_0007;   //LEAF DESCRIBING ADVANCED POWER MANAGEMENT FEATURES
+const CPUID_INVARIANT_TSC:     u32 = 1 << 8;        //EDX BIT SET WHEN THE TSC RUNS AT A CONSTANT RATE IN ALL STATES
+
+
+// CLOCK SOURCES
+//Clock Source
+pub trait ClockSource {
+    //Short name for diagnostics
+    fn name(&self) -> &'static str;
+    //Counter increments per second
+    fn frequency(&self) -> u64;
+    //Significant bits of the underlying hardware counter
+    fn width(&self) -> u32;
+    //Counter value, extended to 64 bits (wrapping sources must be read at least once per wrap period)
+    fn counter(&mut self) -> u64;
+}
+
+//Wrapping Counter Extension
+#[derive(Clone, Copy, Debug)]
+pub struct WrappingCounter {
+    mask:  u64, //Significant bits of the raw counter
+    last:  u64, //Raw counter at the previous read
+    total: u64, //Extended counter at the previous read
+}
+impl WrappingCounter {
+    //Constructor
+    pub const fn new(bits: u32, raw: u64) -> Self {
+        let mask = if bits >= 64 {u64::MAX} else {(1 << bits) - 1};
+        Self {mask, last: raw & mask, total: 0}
+    }
+
+    //Accumulate the distance travelled since the previous read
+    pub fn update(&mut self, raw: u64) -> u64 {
+        let raw = raw & self.mask;
+        self.total = self.total.wrapping_add(raw.wrapping_sub(self.last) & self.mask);
+        self.last = raw;
+        self.total
+    }
+}
+
+//PIT Clock (channel 0 free running over its full 16 bit range)
+#[derive(Clone, Copy, Debug)]
+pub struct PitClock {
+    extension: WrappingCounter,
+}
+impl PitClock {
+    //Constructor, reprogramming channel 0 (its interrupt should be masked)
+    pub unsafe fn new() -> Self {
+        pit::send_command(pit::Channel::C1, pit::AccessMode::Full, pit::OperatingMode::RateGenerator, pit::BinaryMode::Binary);
+        pit::set_reload_full(pit::Channel::C1, 0);
+        Self {extension: WrappingCounter::new(16, Self::raw())}
+    }
+
+    //The channel counts down, so the count is inverted to count up
+    unsafe fn raw() -> u64 {
+        0x1_0000 - pit::read_count_full(pit::Channel::C1) as u64
+    }
+}
+impl ClockSource for PitClock {
+    fn name(&self) -> &'static str {"PIT"}
+    fn frequency(&self) -> u64 {pit::PIT_FREQUENCY as u64}
+    fn width(&self) -> u32 {16}
+    fn counter(&mut self) -> u64 {
+        let raw = unsafe {Self::raw()};
+        self.extension.update(raw)
+    }
+}
+
+//HPET Clock
+#[derive(Clone, Copy, Debug)]
+pub struct HpetClock {
+    pub device: HpetDevice,
+    extension:  WrappingCounter,
+}
+impl HpetClock {
+    //Constructor, starting the main counter
+    pub unsafe fn new(device: HpetDevice) -> Self {
+        device.enable();
+        let mut clock = Self {device, extension: WrappingCounter::new(64, 0)};
+        clock.extension = WrappingCounter::new(clock.width(), device.counter());
+        clock
+    }
+}
+impl ClockSource for HpetClock {
+    fn name(&self) -> &'static str {"HPET"}
+    fn frequency(&self) -> u64 {self.device.frequency()}
+    fn width(&self) -> u32 {if self.device.counter_64 {64} else {32}}
+    fn counter(&mut self) -> u64 {
+        let raw = unsafe {self.device.counter()};
+        self.extension.update(raw)
+    }
+}
+
+//TSC Clock
+#[derive(Clone, Copy, Debug)]
+pub struct TscClock {
+    pub frequency: u64,  //Calibrated against another clock source
+    pub invariant: bool, //Rate is unaffected by power and frequency states
+}
+impl TscClock {
+    //Constructor, calibrating against a reference source
+    pub fn new<R: ClockSource>(reference: &mut R, nanoseconds: u64) -> Self {
+        Self {frequency: calibrate(reference, nanoseconds, rdtsc), invariant: tsc_invariant()}
+    }
+}
+impl ClockSource for TscClock {
+    fn name(&self) -> &'static str {"TSC"}
+    fn frequency(&self) -> u64 {self.frequency}
+    fn width(&self) -> u32 {64}
+    fn counter(&mut self) -> u64 {rdtsc()}
+}
+
+//Test for an invariant TSC
+pub fn tsc_invariant() -> bool {
+    cpuid(CPUID_EXTENDED_MAX, 0).0 >= CPUID_ADVANCED_POWER && cpuid(CPUID_ADVANCED_POWER, 0).3 & CPUID_INVARIANT_TSC != 0
+}
+
+//Measure the frequency of a counter by busy waiting on a reference source
+pub fn calibrate<R: ClockSource, F: FnMut() -> u64>(reference: &mut R, nanoseconds: u64, mut counter: F) -> u64 {
+    let wait = nanoseconds_to_ticks(nanoseconds, reference.frequency()).max(1);
+    let reference_start = reference.counter();
+    let counter_start = counter();
+    let mut reference_end = reference_start;
+    while reference_end - reference_start < wait {reference_end = reference.counter();}
+    let counter_end = counter();
+    (counter_end.wrapping_sub(counter_start) as u128 * reference.frequency() as u128 / (reference_end - reference_start) as u128) as u64
+}
+
+//Conversions
+pub fn ticks_to_nanoseconds(ticks: u64, frequency: u64) -> u64 {
+    (ticks as u128 * NANOSECONDS as u128 / frequency as u128).min(u64::MAX as u128) as u64
+}
+pub fn nanoseconds_to_ticks(nanoseconds: u64, frequency: u64) -> u64 {
+    (nanoseconds as u128 * frequency as u128 / NANOSECONDS as u128).min(u64::MAX as u128) as u64
+}
+
+
+// MONOTONIC CLOCK
+//Available Clock
+#[derive(Clone, Copy, Debug)]
+pub enum Clock {
+    Tsc(TscClock),
+    Hpet(HpetClock),
+    Pit(PitClock),
+}
+impl Clock {
+    //Pick the best source: an invariant TSC, then the HPET, then the PIT
+    pub unsafe fn select(hpet: Option<HpetDevice>) -> Self {
+        let mut reference = match hpet {
+            Some(device) => Clock::Hpet(HpetClock::new(device)),
+            None         => Clock::Pit(PitClock::new()),
+        };
+        if tsc_invariant() {Clock::Tsc(TscClock::new(&mut reference, CALIBRATION_PERIOD))}
+        else {reference}
+    }
+}
+impl ClockSource for Clock {
+    fn name(&self) -> &'static str {
+        match self {
+            Clock::Tsc(clock)  => clock.name(),
+            Clock::Hpet(clock) => clock.name(),
+            Clock::Pit(clock)  => clock.name(),
+        }
+    }
+    fn frequency(&self) -> u64 {
+        match self {
+            Clock::Tsc(clock)  => clock.frequency(),
+            Clock::Hpet(clock) => clock.frequency(),
+            Clock::Pit(clock)  => clock.frequency(),
+        }
+    }
+    fn width(&self) -> u32 {
+        match self {
+            Clock::Tsc(clock)  => clock.width(),
+            Clock::Hpet(clock) => clock.width(),
+            Clock::Pit(clock)  => clock.width(),
+        }
+    }
+    fn counter(&mut self) -> u64 {
+        match self {
+            Clock::Tsc(clock)  => clock.counter(),
+            Clock::Hpet(clock) => clock.counter(),
+            Clock::Pit(clock)  => clock.counter(),
+        }
+    }
+}
+
+//Monotonic Clock
+#[derive(Clone, Copy, Debug)]
+pub struct MonotonicClock<C: ClockSource> {
+    pub source: C,
+    base:       u64, //Counter value at nanosecond zero
+    last:       u64, //Most recent time given, which is never gone back on
+}
+impl<C: ClockSource> MonotonicClock<C> {
+    //Constructor, starting from zero
+    pub fn new(mut source: C) -> Self {
+        let base = source.counter();
+        Self {source, base, last: 0}
+    }
+
+    //Nanoseconds since construction
+    pub fn nanoseconds(&mut self) -> u64 {
+        let ticks = self.source.counter().wrapping_sub(self.base);
+        self.last = self.last.max(ticks_to_nanoseconds(ticks, self.source.frequency()));
+        self.last
+    }
+
+    //Extended source counter value at a number of nanoseconds since construction
+    pub fn counter_at(&self, nanoseconds: u64) -> u64 {
+        self.base.saturating_add(nanoseconds_to_ticks(nanoseconds, self.source.frequency()))
+    }
+
+    //Longest time between reads for which a wrapping source stays extended, with a margin of half its wrap period
+    pub fn max_interval(&self) -> u64 {
+        match self.source.width() {
+            width if width >= 64 => u64::MAX,
+            width                => ticks_to_nanoseconds(1 << (width - 1), self.source.frequency()),
+        }
+    }
+}
+
+
+// TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    //Simulated wrapping source reading a shared time in nanoseconds, which each read advances
+    struct TestClock<'t> {
+        time:      &'t Cell<u64>,
+        step:      u64,
+        frequency: u64,
+        width:     u32,
+        extension: WrappingCounter,
+    }
+    impl<'t> TestClock<'t> {
+        fn new(time: &'t Cell<u64>, step: u64, frequency: u64, width: u32) -> Self {
+            let mut clock = Self {time, step, frequency, width, extension: WrappingCounter::new(64, 0)};
+            clock.extension = WrappingCounter::new(width, clock.raw());
+            clock
+        }
+        fn raw(&self) -> u64 {
+            nanoseconds_to_ticks(self.time.get(), self.frequency)
+        }
+    }
+    impl ClockSource for TestClock<'_> {
+        fn name(&self) -> &'static str {"TEST"}
+        fn frequency(&self) -> u64 {self.frequency}
+        fn width(&self) -> u32 {self.width}
+        fn counter(&mut self) -> u64 {
+            self.time.set(self.time.get() + self.step);
+            let raw = self.raw();
+            self.extension.update(raw)
+        }
+    }
+
+    #[test]
+    fn wrapping_counter_wraparound() {
+        //A 16 bit counter is extended across its wrap, ignoring bits above its width
+        let mut counter = WrappingCounter::new(16, 0xFFF0);
+        assert_eq!(counter.update(0x0010), 0x20);
+        assert_eq!(counter.update(0x0010), 0x20);
+        assert_eq!(counter.update(0xFFFF_0015), 0x25);
+        //Reads at least once per wrap period accumulate every wrap
+        let mut counter = WrappingCounter::new(16, 0);
+        for read in 1..=40u64 {assert_eq!(counter.update(read * 0x7000), read * 0x7000);}
+        //A full width counter wraps at 64 bits
+        let mut counter = WrappingCounter::new(64, u64::MAX - 1);
+        assert_eq!(counter.update(1), 3);
+        assert_eq!(WrappingCounter::new(64, 0).update(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(ticks_to_nanoseconds(1_193_182, 1_193_182), NANOSECONDS);
+        assert_eq!(nanoseconds_to_ticks(NANOSECONDS, 14_318_180), 14_318_180);
+        assert_eq!(nanoseconds_to_ticks(ticks_to_nanoseconds(3_000_000, 3_000_000_000), 3_000_000_000), 3_000_000);
+        //Products beyond 64 bits are computed exactly, and results beyond 64 bits saturate
+        assert_eq!(ticks_to_nanoseconds(u64::MAX / 2, 4_000_000_000), u64::MAX / 8);
+        assert_eq!(nanoseconds_to_ticks(u64::MAX / 2, 4_000_000_000), u64::MAX);
+        assert_eq!(ticks_to_nanoseconds(u64::MAX, 1), u64::MAX);
+        assert_eq!(nanoseconds_to_ticks(1, 1_193_182), 0);
+    }
+
+    #[test]
+    fn calibrate_across_wraps() {
+        //A 3 GHz counter, starting just short of its own wrap, measured against a 16 bit PIT rate reference which wraps during the measurement
+        let time = Cell::new(0);
+        let mut reference = TestClock::new(&time, 1_000, pit::PIT_FREQUENCY as u64, 16);
+        let start = u64::MAX - 1_000_000;
+        let frequency = calibrate(&mut reference, 100_000_000, || start.wrapping_add(nanoseconds_to_ticks(time.get(), 3_000_000_000)));
+        assert!(time.get() >= 100_000_000);
+        assert!(frequency.abs_diff(3_000_000_000) < 3_000_000, "{}", frequency);
+        //A waiting time shorter than a reference tick still waits for one tick
+        let before = time.get();
+        assert!(calibrate(&mut reference, 0, || nanoseconds_to_ticks(time.get(), 3_000_000_000)) > 0);
+        assert!(time.get() - before >= ticks_to_nanoseconds(1, pit::PIT_FREQUENCY as u64));
+    }
+
+    #[test]
+    fn monotonic_clock() {
+        let time = Cell::new(0);
+        let mut clock = MonotonicClock::new(TestClock::new(&time, 1_000, pit::PIT_FREQUENCY as u64, 16));
+        //Time keeps counting past the wrap of the source
+        let mut previous = 0;
+        for _ in 0..200_000 {
+            let now = clock.nanoseconds();
+            assert!(now >= previous);
+            previous = now;
+        }
+        assert!(previous.abs_diff(time.get()) < 2_000, "{} {}", previous, time.get());
+        assert_eq!(clock.max_interval(), ticks_to_nanoseconds(0x8000, pit::PIT_FREQUENCY as u64));
+        assert_eq!(clock.counter_at(NANOSECONDS) - clock.counter_at(0), pit::PIT_FREQUENCY as u64);
+    }
+}
@@ -11,6 +11,7 @@
 use crate::{numeric_enum, return_if_partial};
 use crate::noble::file_system::*;
 use crate::noble::return_code::ReturnCode;
+use crate::pc::rtc::DateTime;
 use core::convert::{TryFrom, TryInto};
 use core::str;
 
@@ -21,6 +22,7 @@ pub struct FATFileSystem<'s> {
     pub volume:     &'s dyn Volume,
     pub boot_sector:    FATBootSector,
     pub fat:            FATTable<'s>,
+    pub clock:          fn() -> Option<DateTime>, //Date and time stamped on new entries, left zeroed while unknown
 }
 impl<'s>                FATFileSystem<'s> {
     // CONSTRUCTOR
@@ -36,7 +38,7 @@ impl<'s>                FATFileSystem<'s> {
         fat.write_raw(0, 0xFFF0)?;
         fat.write_raw(1, 0xFFFF)?;
         //Return
-        Ok(Self {volume, boot_sector, fat})
+        Ok(Self {volume, boot_sector, fat, clock: no_clock})
     }
     pub fn from_existing_volume(volume: &'s dyn Volume) -> Result<Self, ReturnCode> {
         //Load Boot Sector
@@ -46,7 +48,7 @@ impl<'s>                FATFileSystem<'s> {
         //Load FAT
         let fat = FATTable::new(volume, boot_sector);
         //Return
-        Ok(Self {volume, boot_sector, fat})
+        Ok(Self {volume, boot_sector, fat, clock: no_clock})
     }
 }
 impl<'s> FileSystem for FATFileSystem<'s> {
@@ -114,6 +116,7 @@ impl<'s> FileSystem for FATFileSystem<'s> {
         //Allocate
         let start_cluster = self.fat.allocate_clusters((file_size / self.boot_sector.cluster_size()) as u16)?;
         //Create entry data
+        let (creation_time_ss, creation_time, creation_date) = (self.clock)().map(fat_timestamp).unwrap_or((0, 0, 0));
         let directory_entry = FATShortDirectoryEntry {
             file_name: if dir {format_short_directory_name(name)?} else {format_short_file_name(name)?},
             file_attributes: FATFileAttributes {
@@ -128,9 +131,9 @@ impl<'s> FileSystem for FATFileSystem<'s> {
             },
             start_cluster_high: (start_cluster >> 16) as u16,
             start_cluster_low: (start_cluster & 0xFFFF) as u16,
-            creation_time_ss: 0,
-            creation_time: 0,
-            creation_date: 0,
+            creation_time_ss,
+            creation_time,
+            creation_date,
             file_size,
         };
         //Write to directory
@@ -290,6 +293,21 @@ fn retrieve_short_directory_name(ascii_in: [u8;11], buffer: &mut[u8]) -> Result<
 }
 
 
+
+// TIMESTAMPS
+//Clock used until one is given, which leaves timestamps zeroed
+fn no_clock() -> Option<DateTime> {
+    None
+}
+
+//Encode a date and time as the 10ms count, time, and date fields of a directory entry (dates outside 1980-2107 cannot be held, and are zeroed)
+pub fn fat_timestamp(date_time: DateTime) -> (u8, u16, u16) {
+    if !(1980..=2107).contains(&date_time.year) {return (0, 0, 0)}
+    let time = (date_time.hour as u16) << 11 | (date_time.minute as u16) << 5 | ((date_time.second as u16) / 2);
+    let date = (date_time.year - 1980) << 9 | (date_time.month as u16) << 5 | date_time.day as u16;
+    ((date_time.second % 2) * 100, time, date)
+}
+
 // FAT16 BOOT SECTOR
 //Boot Sector
 #[derive(Debug)]
@@ -0,0 +1,93 @@
+// GLUON: PC HPET
+// Structs and functions related to the handling of the High Precision Event Timer
+
+
+// HEADER
+//Imports
+use crate::noble::return_code::ReturnCode;
+use core::ptr::{read_volatile, write_volatile};
+
+//Constants
+pub const FEMTOSECONDS:       u64   = 1_000_000_000_000_000; //FEMTOSECONDS IN A SECOND
+pub const HPET_PERIOD_MAX:    u64   = 100_000_000;           //LONGEST VALID COUNTER PERIOD IN FEMTOSECONDS (10MHZ)
+const REGISTER_CAPABILITIES:  usize = 0x000;                 //GENERAL CAPABILITIES AND ID REGISTER
+const REGISTER_CONFIGURATION: usize = 0x010;                 //GENERAL CONFIGURATION REGISTER
+const REGISTER_STATUS:        usize = 0x020;                 //GENERAL INTERRUPT STATUS REGISTER
+const REGISTER_COUNTER:       usize = 0x0F0;                 //MAIN COUNTER VALUE REGISTER
+const CONFIGURATION_ENABLE:   u64   = 1 << 0;                //MAIN COUNTER RUNS
+const CONFIGURATION_LEGACY:   u64   = 1 << 1;                //TIMERS 0 AND 1 REPLACE THE PIT AND RTC INTERRUPTS
+
+
+// HIGH PRECISION EVENT TIMER
+//HPET Device
+#[derive(Clone, Copy, Debug)]
+pub struct HpetDevice {
+    address:              *mut u8, //Linear address of the register block
+    pub period:           u64,     //Counter period in femtoseconds
+    pub comparator_count: u8,
+    pub counter_64:       bool,    //Main counter is 64 bits wide rather than 32
+    pub legacy_capable:   bool,
+    pub vendor_id:        u16,
+}
+impl HpetDevice {
+    //Constructor, taking the linear address at which the HPET's registers are mapped
+    pub unsafe fn new(address: *mut u8) -> Result<Self, ReturnCode> {
+        let capabilities = read_volatile(address.add(REGISTER_CAPABILITIES) as *const u64);
+        let period = capabilities >> 32;
+        if period == 0 || period > HPET_PERIOD_MAX {return Err(ReturnCode::InvalidData)}
+        Ok(Self {
+            address,
+            period,
+            comparator_count: (((capabilities >> 8) & 0x1F) + 1) as u8,
+            counter_64:       capabilities & (1 << 13) != 0,
+            legacy_capable:   capabilities & (1 << 15) != 0,
+            vendor_id:        (capabilities >> 16) as u16,
+        })
+    }
+
+    //Register access
+    pub unsafe fn read_register(&self, register: usize) -> u64 {
+        read_volatile(self.address.add(register) as *const u64)
+    }
+    pub unsafe fn write_register(&self, register: usize, data: u64) {
+        write_volatile(self.address.add(register) as *mut u64, data)
+    }
+
+    //Counter frequency in Hz
+    pub fn frequency(&self) -> u64 {
+        FEMTOSECONDS / self.period
+    }
+
+    //Start the main counter without legacy replacement routing
+    pub unsafe fn enable(&self) {
+        let configuration = self.read_register(REGISTER_CONFIGURATION);
+        self.write_register(REGISTER_CONFIGURATION, (configuration & !CONFIGURATION_LEGACY) | CONFIGURATION_ENABLE);
+    }
+
+    //Stop the main counter
+    pub unsafe fn disable(&self) {
+        let configuration = self.read_register(REGISTER_CONFIGURATION);
+        self.write_register(REGISTER_CONFIGURATION, configuration & !CONFIGURATION_ENABLE);
+    }
+
+    //Test if the main counter is running
+    pub unsafe fn enabled(&self) -> bool {
+        self.read_register(REGISTER_CONFIGURATION) & CONFIGURATION_ENABLE != 0
+    }
+
+    //Main counter access (only 32 bits are significant if the counter is not 64 bits wide)
+    pub unsafe fn counter(&self) -> u64 {
+        let counter = self.read_register(REGISTER_COUNTER);
+        if self.counter_64 {counter} else {counter & 0xFFFF_FFFF}
+    }
+    pub unsafe fn set_counter(&self, value: u64) -> Result<(), ReturnCode> {
+        if self.enabled() {return Err(ReturnCode::AlreadyStarted)}
+        self.write_register(REGISTER_COUNTER, value);
+        Ok(())
+    }
+
+    //Clear level triggered interrupt status bits
+    pub unsafe fn clear_status(&self, timers: u32) {
+        self.write_register(REGISTER_STATUS, timers as u64);
+    }
+}
@@ -1,18 +1,30 @@
 // GLUON: PC
 // Modules handling the PC de-facto standard system architecture:
-//   fat:   Structs and enums related to the contents and handling of the FAT16 file system
-//   ports: Functions and objects related to the handling of the PC architecture's standard port-space layout
-//   pci:   Structs and objects related to the handling of the PCI bus
-//   pic:   Functions related to the handling of the Programmable Interrupt Controller
-//   pit:   Consts, Functions, and Enums related to the handling of the 8253 and 8254 Programmable Interval Timer
-//   ps2:   Functions and objects related to the handling of the PS/2 controller and devices
+//   acpi:   Structs and functions related to the reading of Advanced Configuration and Power Interface tables
+//   clock:  Traits, structs, and functions related to clock sources and the calibrated monotonic clock built on them
+//   fat:    Structs and enums related to the contents and handling of the FAT16 file system
+//   hpet:   Structs and functions related to the handling of the High Precision Event Timer
+//   ports:  Functions and objects related to the handling of the PC architecture's standard port-space layout
+//   pci:    Structs and objects related to the handling of the PCI bus
+//   pic:    Functions related to the handling of the Programmable Interrupt Controller
+//   pit:    Consts, Functions, and Enums related to the handling of the 8253 and 8254 Programmable Interval Timer
+//   power:  Functions related to resetting and powering off the system through the 8042, the reset control port, and ACPI
+//   ps2:    Functions and objects related to the handling of the PS/2 controller and devices
+//   rtc:    Structs and functions related to the handling of the MC146818 CMOS real-time clock
+//   serial: Structs and functions related to the handling of 16550 UART serial ports
 
 
 // HEADER
 //Modules
+pub mod acpi;
+pub mod clock;
 pub mod fat;
+pub mod hpet;
 pub mod ports;
 pub mod pci;
 pub mod pic;
 pub mod pit;
+pub mod power;
 pub mod ps2;
+pub mod rtc;
+pub mod serial;
@@ -59,6 +59,11 @@ pub unsafe fn enable_irq(irq: u8)  -> Result<(), &'static str> {
     Ok(())
 }
 
+//Mask every IRQ, leaving the PIC unused once interrupts are routed through the IOAPIC
+pub unsafe fn disable() {
+    set_mask(0xFF, 0xFF);
+}
+
 //Send End IRQ Signal
 pub unsafe fn end_irq(irq: u8) -> Result<(), &'static str> {
     if irq < 16 {
@@ -21,12 +21,16 @@ pub static mut PIT_COMMAND:   PortB = PortB(0x0043);
 pub static mut PS2_DATA:      PortB = PortB(0x0060);
 pub static mut PS2_COMMAND:   PortB = PortB(0x0064);
 pub static mut PS2_STATUS:    PortB = PortB(0x0064);
+pub static mut CMOS_ADDRESS:  PortB = PortB(0x0070);
+pub static mut CMOS_DATA:     PortB = PortB(0x0071);
 pub static mut WAIT:          PortB = PortB(0x0080);
 pub static mut PIC2_COMMAND:  PortB = PortB(0x00A0);
 pub static mut PIC2_DATA:     PortB = PortB(0x00A1);
+pub static mut DEBUG_EXIT:    PortD = PortD(0x00F4);
 pub static mut SERIAL_4:      PortB = PortB(0x02E8);
 pub static mut SERIAL_2:      PortB = PortB(0x02F8);
 pub static mut SERIAL_3:      PortB = PortB(0x03E8);
 pub static mut SERIAL_1:      PortB = PortB(0x03F8);
 pub static mut PCI_INDEX:     PortD = PortD(0x0CF8);
+pub static mut RESET_CONTROL: PortB = PortB(0x0CF9);
 pub static mut PCI_DATA:      PortD = PortD(0x0CFC);
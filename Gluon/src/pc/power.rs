@@ -0,0 +1,103 @@
+// GLUON: PC POWER
+// Functions related to resetting and powering off the system through the 8042, the reset control port, and ACPI
+
+
+// HEADER
+//Imports
+use crate::noble::return_code::ReturnCode;
+use crate::pc::acpi::{AddressSpace, Fadt, SleepType, FADT_HW_REDUCED, FADT_RESET_REG_SUP};
+use crate::pc::ports::PS2_COMMAND;
+use crate::pc::ports::RESET_CONTROL;
+use crate::pc::ps2;
+use crate::x86_64::port::*;
+use core::convert::TryFrom;
+use core::ptr::write_volatile;
+
+//Constants
+const PS2_RESET:         u8    = 0xFE;      //8042 COMMAND PULSING THE CPU RESET LINE
+const RESET_CPU:         u8    = 0x04;      //RESET CONTROL: START A RESET ON A RISING EDGE
+const RESET_FULL:        u8    = 0x02;      //RESET CONTROL: HARD RATHER THAN SOFT RESET
+const PM1_SCI_EN:        u16   = 1 << 0;    //PM1 CONTROL: THE SYSTEM IS IN ACPI MODE
+const PM1_SLP_TYP_SHIFT: u16   = 10;        //PM1 CONTROL: SLEEP TYPE FIELD POSITION
+const PM1_SLP_TYP:       u16   = 0b111 << PM1_SLP_TYP_SHIFT;
+const PM1_SLP_EN:        u16   = 1 << 13;   //PM1 CONTROL: ENTER THE SLEEP STATE GIVEN BY SLP_TYP
+const ACPI_ENABLE_POLLS: usize = 1_000_000; //STATUS READS TRIED BEFORE GIVING UP ON THE FIRMWARE ENTERING ACPI MODE
+const SHUTDOWN_POLLS:    usize = 1_000_000; //SPINS WAITED FOR A RESET OR POWER OFF TO TAKE EFFECT
+
+
+// RESET
+//Reset through the 8042 PS/2 controller's reset line
+pub unsafe fn reset_8042() {
+    ps2::wait_for_input();
+    PS2_COMMAND.write(PS2_RESET);
+}
+
+//Reset through the reset control register of PCI host bridges
+pub unsafe fn reset_cf9() {
+    RESET_CONTROL.write(RESET_FULL);
+    RESET_CONTROL.write(RESET_FULL | RESET_CPU);
+}
+
+//Reset through the FADT reset register, taking the linear offset at which physical memory is mapped
+pub unsafe fn reset_acpi(fadt: &Fadt, memory_offset: u64) -> Result<(), ReturnCode> {
+    if fadt.flags & FADT_RESET_REG_SUP == 0 {return Err(ReturnCode::UnsupportedFeature)}
+    let register = fadt.reset_register.ok_or(ReturnCode::UnsupportedFeature)?;
+    match register.address_space {
+        AddressSpace::SystemIo     => PortB(u16::try_from(register.address).map_err(|_| ReturnCode::InvalidData)?).write(fadt.reset_value),
+        AddressSpace::SystemMemory => write_volatile((memory_offset + register.address) as *mut u8, fadt.reset_value),
+        _                          => return Err(ReturnCode::UnsupportedFeature),
+    }
+    Ok(())
+}
+
+//Reset by each available method in turn, returning only if all of them fail
+pub unsafe fn reset(fadt: Option<&Fadt>, memory_offset: u64) {
+    if let Some(fadt) = fadt {
+        if reset_acpi(fadt, memory_offset).is_ok() {settle()}
+    }
+    reset_cf9();
+    settle();
+    if fadt.map(|fadt| fadt.legacy_devices()).unwrap_or(true) {
+        reset_8042();
+        settle();
+    }
+}
+
+
+// POWER OFF
+//Switch the firmware into ACPI mode if it has not been already
+pub unsafe fn enable_acpi(fadt: &Fadt) -> Result<(), ReturnCode> {
+    let control = PortW(u16::try_from(fadt.pm1a_control_block).map_err(|_| ReturnCode::InvalidData)?);
+    if control.read() & PM1_SCI_EN != 0 {return Ok(())}
+    if fadt.smi_command == 0 || fadt.acpi_enable == 0 {return Err(ReturnCode::UnsupportedFeature)}
+    PortB(u16::try_from(fadt.smi_command).map_err(|_| ReturnCode::InvalidData)?).write(fadt.acpi_enable);
+    for _ in 0..ACPI_ENABLE_POLLS {
+        if control.read() & PM1_SCI_EN != 0 {return Ok(())}
+    }
+    Err(ReturnCode::TimeOut)
+}
+
+//Enter a sleep state (S5 to power off) through the PM1a and PM1b control registers
+pub unsafe fn sleep(fadt: &Fadt, sleep_type: SleepType) -> Result<(), ReturnCode> {
+    if fadt.flags & FADT_HW_REDUCED != 0 || fadt.pm1a_control_block == 0 {return Err(ReturnCode::UnsupportedFeature)}
+    enable_acpi(fadt)?;
+    for (block, value) in [(fadt.pm1a_control_block, sleep_type.pm1a), (fadt.pm1b_control_block, sleep_type.pm1b)] {
+        if block == 0 {continue}
+        let control = PortW(u16::try_from(block).map_err(|_| ReturnCode::InvalidData)?);
+        control.write((control.read() & !PM1_SLP_TYP) | (value as u16) << PM1_SLP_TYP_SHIFT | PM1_SLP_EN);
+    }
+    settle();
+    Err(ReturnCode::TimeOut)
+}
+
+//Power off through ACPI S5 (soft off)
+pub unsafe fn power_off(fadt: &Fadt, s5: SleepType) -> Result<(), ReturnCode> {
+    sleep(fadt, s5)
+}
+
+
+// FUNCTIONS
+//Give a reset or power off time to take effect before falling back
+fn settle() {
+    for _ in 0..SHUTDOWN_POLLS {core::hint::spin_loop();}
+}
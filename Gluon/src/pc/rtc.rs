@@ -0,0 +1,233 @@
+// GLUON: PC REAL-TIME CLOCK
+// Structs and functions related to the handling of the MC146818 CMOS real-time clock
+
+
+// HEADER
+//Imports
+use crate::noble::return_code::ReturnCode;
+use crate::pc::ports::CMOS_ADDRESS;
+use crate::pc::ports::CMOS_DATA;
+use crate::x86_64::port::*;
+use core::convert::TryFrom;
+
+//Constants
+pub const RTC_IRQ:           u8    = 0x08;      //ISA IRQ RAISED BY THE RTC
+const DEFAULT_CENTURY:       u16   = 2000;      //CENTURY ASSUMED WHEN THE FIRMWARE GIVES NO CENTURY REGISTER
+const READ_ATTEMPTS:         usize = 16;        //READS TRIED BEFORE GIVING UP ON A CONSISTENT TIME
+const UPDATE_POLLS:          usize = 1_000_000; //STATUS READS TRIED BEFORE GIVING UP ON AN UPDATE ENDING
+const REGISTER_SECONDS:      u8    = 0x00;
+const REGISTER_MINUTES:      u8    = 0x02;
+const REGISTER_HOURS:        u8    = 0x04;
+const REGISTER_DAY:          u8    = 0x07;
+const REGISTER_MONTH:        u8    = 0x08;
+const REGISTER_YEAR:         u8    = 0x09;
+const REGISTER_STATUS_A:     u8    = 0x0A;
+const REGISTER_STATUS_B:     u8    = 0x0B;
+const REGISTER_STATUS_C:     u8    = 0x0C;
+const NMI_DISABLE:           u8    = 0x80;      //SET IN THE ADDRESS PORT TO MASK NMIS WHILE A REGISTER IS SELECTED
+const STATUS_A_UPDATING:     u8    = 0x80;      //AN UPDATE IS IN PROGRESS AND THE TIME REGISTERS ARE UNSTABLE
+const STATUS_B_UPDATE_INT:   u8    = 0x10;      //INTERRUPT WHEN AN UPDATE ENDS
+const STATUS_B_BINARY:       u8    = 0x04;      //REGISTERS ARE BINARY RATHER THAN BCD
+const STATUS_B_24_HOUR:      u8    = 0x02;      //HOURS ARE 0-23 RATHER THAN 1-12 WITH A PM FLAG
+const STATUS_C_UPDATE_ENDED: u8    = 0x10;      //AN UPDATE HAS ENDED SINCE STATUS C WAS LAST READ
+const HOUR_PM:               u8    = 0x80;      //PM FLAG IN 12 HOUR FORMAT
+
+
+// REGISTER ACCESS
+//Read a CMOS register
+pub unsafe fn read_register(register: u8) -> u8 {
+    CMOS_ADDRESS.write(NMI_DISABLE | register);
+    let data = CMOS_DATA.read();
+    CMOS_ADDRESS.write(0x00);
+    data
+}
+
+//Write a CMOS register
+pub unsafe fn write_register(register: u8, data: u8) {
+    CMOS_ADDRESS.write(NMI_DISABLE | register);
+    CMOS_DATA.write(data);
+    CMOS_ADDRESS.write(0x00);
+}
+
+//Test if an update is in progress
+pub unsafe fn updating() -> bool {
+    read_register(REGISTER_STATUS_A) & STATUS_A_UPDATING != 0
+}
+
+
+// UPDATE INTERRUPTS
+//Enable or disable the interrupt raised once a second when an update ends
+pub unsafe fn set_update_interrupt(enable: bool) {
+    let status = read_register(REGISTER_STATUS_B);
+    write_register(REGISTER_STATUS_B, if enable {status | STATUS_B_UPDATE_INT} else {status & !STATUS_B_UPDATE_INT});
+    acknowledge();
+}
+
+//Acknowledge an interrupt (the RTC raises no more until status C is read), returning whether an update has ended
+pub unsafe fn acknowledge() -> bool {
+    read_register(REGISTER_STATUS_C) & STATUS_C_UPDATE_ENDED != 0
+}
+
+//Busy wait while an update is in progress, giving up if one never ends
+pub unsafe fn wait_while_updating() -> Result<(), ReturnCode> {
+    for _ in 0..UPDATE_POLLS {
+        if !updating() {return Ok(())}
+        core::hint::spin_loop();
+    }
+    Err(ReturnCode::TimeOut)
+}
+
+//Busy wait until the next update ends, so that the time read afterwards has only just started its second
+pub unsafe fn wait_for_update() -> Result<(), ReturnCode> {
+    acknowledge();
+    for _ in 0..UPDATE_POLLS {
+        if acknowledge() {return Ok(())}
+    }
+    Err(ReturnCode::TimeOut)
+}
+
+
+// DATE AND TIME
+//Date and Time (UTC, as the RTC is assumed to hold)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DateTime {
+    pub year:   u16,
+    pub month:  u8,  //1-12
+    pub day:    u8,  //1-31
+    pub hour:   u8,  //0-23
+    pub minute: u8,  //0-59
+    pub second: u8,  //0-59
+}
+impl DateTime {
+    //Read from the RTC, taking the CMOS index of the century register if the firmware reports one
+    pub unsafe fn read(century_register: Option<u8>) -> Result<Self, ReturnCode> {
+        //Read until two reads made outside of updates agree
+        let mut previous: Option<RawTime> = None;
+        for _ in 0..READ_ATTEMPTS {
+            wait_while_updating()?;
+            let current = RawTime::read(century_register);
+            if previous == Some(current) {return current.decode(read_register(REGISTER_STATUS_B))}
+            previous = Some(current);
+        }
+        Err(ReturnCode::TimeOut)
+    }
+
+    //Validate fields
+    pub fn validate(&self) -> Result<(), ReturnCode> {
+        if self.month == 0 || self.month > 12
+        || self.day == 0 || self.day > days_in_month(self.year, self.month)
+        || self.hour > 23 || self.minute > 59 || self.second > 59 {return Err(ReturnCode::InvalidData)}
+        Ok(())
+    }
+
+    //Seconds since 1970-01-01 00:00:00 UTC
+    pub fn unix_time(&self) -> i64 {
+        days_from_civil(self.year as i64, self.month as i64, self.day as i64) * 86400
+        + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+    }
+
+    //Conversion from seconds since 1970-01-01 00:00:00 UTC
+    pub fn from_unix_time(time: i64) -> Result<Self, ReturnCode> {
+        let days = time.div_euclid(86400);
+        let seconds = time.rem_euclid(86400);
+        let (year, month, day) = civil_from_days(days);
+        Ok(Self {
+            year:   u16::try_from(year).map_err(|_| ReturnCode::ConversionError)?,
+            month:  month as u8,
+            day:    day as u8,
+            hour:   (seconds / 3600) as u8,
+            minute: (seconds / 60 % 60) as u8,
+            second: (seconds % 60) as u8,
+        })
+    }
+}
+
+//Raw RTC Registers
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+struct RawTime {
+    second:  u8,
+    minute:  u8,
+    hour:    u8,
+    day:     u8,
+    month:   u8,
+    year:    u8,
+    century: Option<u8>,
+}
+impl RawTime {
+    //Read every time register
+    unsafe fn read(century_register: Option<u8>) -> Self {
+        Self {
+            second:  read_register(REGISTER_SECONDS),
+            minute:  read_register(REGISTER_MINUTES),
+            hour:    read_register(REGISTER_HOURS),
+            day:     read_register(REGISTER_DAY),
+            month:   read_register(REGISTER_MONTH),
+            year:    read_register(REGISTER_YEAR),
+            century: century_register.map(|register| read_register(register)),
+        }
+    }
+
+    //Decode according to the format given in status B
+    fn decode(&self, status_b: u8) -> Result<DateTime, ReturnCode> {
+        let binary = status_b & STATUS_B_BINARY != 0;
+        let field = |value: u8| if binary {Ok(value)} else {bcd_to_binary(value)};
+        //Hours carry the PM flag outside of their number in 12 hour format
+        let hour = if status_b & STATUS_B_24_HOUR != 0 {field(self.hour)?} else {
+            let hour = field(self.hour & !HOUR_PM)?;
+            if hour == 0 || hour > 12 {return Err(ReturnCode::InvalidData)}
+            hour % 12 + if self.hour & HOUR_PM != 0 {12} else {0}
+        };
+        let year = field(self.year)? as u16 + match self.century {
+            Some(century) => field(century)? as u16 * 100,
+            None          => DEFAULT_CENTURY,
+        };
+        let date_time = DateTime {year, month: field(self.month)?, day: field(self.day)?, hour, minute: field(self.minute)?, second: field(self.second)?};
+        date_time.validate()?;
+        Ok(date_time)
+    }
+}
+
+
+// CONVERSIONS
+//Binary coded decimal to binary
+pub fn bcd_to_binary(value: u8) -> Result<u8, ReturnCode> {
+    if value & 0x0F > 9 || value >> 4 > 9 {return Err(ReturnCode::InvalidData)}
+    Ok((value >> 4) * 10 + (value & 0x0F))
+}
+
+//Leap years of the Gregorian calendar
+pub fn leap_year(year: u16) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+//Days in a month (1-12)
+pub fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2              => if leap_year(year) {29} else {28},
+        4 | 6 | 9 | 11 => 30,
+        _              => 31,
+    }
+}
+
+//Days since 1970-01-01 of a Gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 {year - 1} else {year};
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 {-3} else {9}) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+//Gregorian date of a number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {month_index + 3} else {month_index - 9};
+    (year_of_era + era * 400 + if month <= 2 {1} else {0}, month, day)
+}
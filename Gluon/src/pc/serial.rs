@@ -0,0 +1,69 @@
+// GLUON: PC SERIAL
+// Structs and functions related to the handling of 16550 UART serial ports
+
+
+// HEADER
+//Imports
+use crate::x86_64::port::*;
+use core::fmt::Write;
+
+//Constants
+pub const UART_FREQUENCY: u32 = 115200; //BAUD RATE AT A DIVISOR OF 1
+
+
+// SERIAL PORT
+//Serial Port
+pub struct SerialPort {
+    data:          PortB, //+0 : Transmit and receive buffer (divisor low byte while DLAB is set)
+    interrupts:    PortB, //+1 : Interrupt enable (divisor high byte while DLAB is set)
+    fifo:          PortB, //+2 : FIFO control
+    line_control:  PortB, //+3 : Data bits, stop bits, parity, and DLAB
+    modem_control: PortB, //+4 : DTR, RTS, and loopback
+    line_status:   PortB, //+5 : Transmit and receive readiness
+}
+impl SerialPort {
+    //Constructor
+    pub const fn new(base: u16) -> Self {
+        Self {
+            data:          PortB(base),
+            interrupts:    PortB(base + 1),
+            fifo:          PortB(base + 2),
+            line_control:  PortB(base + 3),
+            modem_control: PortB(base + 4),
+            line_status:   PortB(base + 5),
+        }
+    }
+
+    //Initialize as 8 data bits, no parity, 1 stop bit, with interrupts disabled
+    pub fn init(&self, baud_rate: u32) {
+        let divisor = (UART_FREQUENCY / baud_rate.clamp(1, UART_FREQUENCY)) as u16;
+        self.interrupts.write(0x00);
+        self.line_control.write(0x80);
+        self.data.write(divisor.to_le_bytes()[0]);
+        self.interrupts.write(divisor.to_le_bytes()[1]);
+        self.line_control.write(0x03);
+        self.fifo.write(0xC7);
+        self.modem_control.write(0x03);
+    }
+
+    //Write a byte once the transmit buffer is empty
+    pub fn write_byte(&self, byte: u8) {
+        while self.line_status.read() & 0x20 == 0 {}
+        self.data.write(byte);
+    }
+
+    //Read a byte if one has been received
+    pub fn read_byte(&self) -> Option<u8> {
+        if self.line_status.read() & 0x01 == 0 {None}
+        else {Some(self.data.read())}
+    }
+}
+impl Write for SerialPort {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for byte in s.bytes() {
+            if byte == b'\n' {self.write_byte(b'\r');}
+            self.write_byte(byte);
+        }
+        Ok(())
+    }
+}
@@ -398,6 +398,11 @@ impl Program {
             }, 
         }
     }
+
+    // FLAGS
+    pub fn readable  (&self) -> bool {self.flags & PROGRAM_FLAG_READ    != 0}
+    pub fn writeable (&self) -> bool {self.flags & PROGRAM_FLAG_WRITE   != 0}
+    pub fn executable(&self) -> bool {self.flags & PROGRAM_FLAG_EXECUTE != 0}
 }
 
 //Program Header Iterator
@@ -460,15 +465,23 @@ numeric_enum! {
     #[derive(Clone, Copy)]
     #[derive(Debug)]
     pub enum ProgramType {
-        Null                 = 0x00_00_00_00,
-        Loadable             = 0x00_00_00_01,
-        Dynamic              = 0x00_00_00_02,
-        Interpreter          = 0x00_00_00_03,
-        Note                 = 0x00_00_00_04,
-        ProgramHeader        = 0x00_00_00_06,
-        ThreadLocalStorage   = 0x00_00_00_07,
-    }
-}
+        Null                  = 0x00_00_00_00,
+        Loadable              = 0x00_00_00_01,
+        Dynamic               = 0x00_00_00_02,
+        Interpreter           = 0x00_00_00_03,
+        Note                  = 0x00_00_00_04,
+        ProgramHeader         = 0x00_00_00_06,
+        ThreadLocalStorage    = 0x00_00_00_07,
+        GnuEhFrame            = 0x64_74_E5_50,
+        GnuStack              = 0x64_74_E5_51,
+        GnuRelocationReadOnly = 0x64_74_E5_52,
+    }
+}
+
+//Program Flags
+pub const PROGRAM_FLAG_EXECUTE: u32 = 0x01;
+pub const PROGRAM_FLAG_WRITE:   u32 = 0x02;
+pub const PROGRAM_FLAG_READ:    u32 = 0x04;
 
 
 // ELF SECTION HEADER
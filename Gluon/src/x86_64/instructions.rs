@@ -85,6 +85,28 @@ pub fn in_d(port: u16) -> u32 {
     result
 }
 
+//INVLPG: Invalidate TLB Entries for Page
+#[inline]
+pub fn invlpg(address: usize) {
+    unsafe {asm!(
+        "INVLPG [{}]",
+        in(reg) address,
+        options(nostack, preserves_flags)
+    )}
+}
+
+//INVPCID: Invalidate TLB Entries by Process-Context Identifier
+#[inline]
+pub fn invpcid(invalidation_type: u64, pcid: u16, address: usize) {
+    let descriptor: [u64; 2] = [pcid as u64, address as u64];
+    unsafe {asm!(
+        "INVPCID {}, [{}]",
+        in(reg) invalidation_type,
+        in(reg) &descriptor,
+        options(nostack, preserves_flags)
+    )}
+}
+
 //LGDT: Load Global Descriptor Table Register
 #[inline]
 pub fn lgdt(gdtr: &[u8;10]) {
@@ -0,0 +1,235 @@
+// GLUON: x86-64 IOAPIC
+// Structs and functions related to the handling of the I/O Advanced Programmable Interrupt Controller
+
+
+// HEADER
+//Imports
+use crate::numeric_enum;
+use crate::noble::return_code::ReturnCode;
+use crate::pc::acpi::InterruptFlags;
+use crate::pc::acpi::Polarity;
+use crate::pc::acpi::TriggerMode;
+use core::convert::TryFrom;
+use core::ptr::{read_volatile, write_volatile};
+
+//Constants
+pub const IOAPIC_ADDRESS: usize = 0xFEC0_0000; //USUAL PHYSICAL ADDRESS OF THE FIRST IOAPIC
+pub const MAX_IOAPICS:    usize = 8;           //MAXIMUM NUMBER OF IOAPICS ROUTED BETWEEN
+pub const ISA_IRQS:       usize = 16;          //NUMBER OF LEGACY ISA IRQS
+const REGISTER_SELECT:    usize = 0x00;        //OFFSET OF THE REGISTER SELECT WINDOW
+const REGISTER_DATA:      usize = 0x10;        //OFFSET OF THE REGISTER DATA WINDOW
+const REGISTER_ID:        u8    = 0x00;        //IOAPIC ID REGISTER
+const REGISTER_VERSION:   u8    = 0x01;        //IOAPIC VERSION AND ENTRY COUNT REGISTER
+const REGISTER_TABLE:     u8    = 0x10;        //FIRST REDIRECTION TABLE REGISTER
+
+
+// REDIRECTION ENTRIES
+//Delivery Mode
+numeric_enum! {
+    #[repr(u64)]
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum DeliveryMode {
+        Fixed            = 0b000,
+        LowestPriority   = 0b001,
+        SystemManagement = 0b010,
+        NonMaskable      = 0b100,
+        Init             = 0b101,
+        External         = 0b111,
+    }
+}
+
+//Redirection Entry
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RedirectionEntry {
+    pub vector:          u8,           //Bits  0-7  : Interrupt vector delivered to the destination
+    pub delivery_mode:   DeliveryMode, //Bits  8-10 : How the interrupt is delivered
+    pub logical:         bool,         //Bit  11    : Destination is a logical rather than a physical APIC ID
+    pub active_low:      bool,         //Bit  13    : Pin polarity
+    pub level_triggered: bool,         //Bit  15    : Trigger mode
+    pub masked:          bool,         //Bit  16    : Interrupt is not delivered
+    pub destination:     u8,           //Bits 56-63 : Destination APIC ID or logical set
+}
+impl RedirectionEntry {
+    //Masked entry
+    pub const MASKED: Self = Self {
+        vector: 0, delivery_mode: DeliveryMode::Fixed, logical: false, active_low: false, level_triggered: false, masked: true, destination: 0,
+    };
+
+    //Fixed delivery to a single CPU by its LAPIC ID
+    pub fn new(vector: u8, destination: u8, active_low: bool, level_triggered: bool) -> Self {
+        Self {vector, delivery_mode: DeliveryMode::Fixed, logical: false, active_low, level_triggered, masked: false, destination}
+    }
+
+    //Conversions
+    pub fn to_u64(&self) -> u64 {
+        self.vector as u64
+        | (self.delivery_mode as u64) << 8
+        | (self.logical as u64) << 11
+        | (self.active_low as u64) << 13
+        | (self.level_triggered as u64) << 15
+        | (self.masked as u64) << 16
+        | (self.destination as u64) << 56
+    }
+    pub fn from_u64(data: u64) -> Result<Self, ReturnCode> {
+        Ok(Self {
+            vector:          data as u8,
+            delivery_mode:   DeliveryMode::try_from((data >> 8) & 0b111).map_err(|_| ReturnCode::InvalidData)?,
+            logical:         data & (1 << 11) != 0,
+            active_low:      data & (1 << 13) != 0,
+            level_triggered: data & (1 << 15) != 0,
+            masked:          data & (1 << 16) != 0,
+            destination:     (data >> 56) as u8,
+        })
+    }
+}
+
+
+// I/O ADVANCED PROGRAMMABLE INTERRUPT CONTROLLER
+//IOAPIC
+#[derive(Clone, Copy, Debug)]
+pub struct IoApic {
+    address:            *mut u8, //Linear address of the register windows
+    pub id:             u8,
+    pub interrupt_base: u32,     //First global system interrupt handled
+    pub entry_count:    u32,     //Number of redirection entries
+}
+impl IoApic {
+    //Constructor, taking the linear address at which the IOAPIC's registers are mapped
+    pub unsafe fn new(address: *mut u8, id: u8, interrupt_base: u32) -> Self {
+        let mut ioapic = Self {address, id, interrupt_base, entry_count: 0};
+        ioapic.entry_count = ((ioapic.read_register(REGISTER_VERSION) >> 16) & 0xFF) + 1;
+        ioapic
+    }
+
+    //Register access
+    pub unsafe fn read_register(&self, register: u8) -> u32 {
+        write_volatile(self.address.add(REGISTER_SELECT) as *mut u32, register as u32);
+        read_volatile(self.address.add(REGISTER_DATA) as *mut u32)
+    }
+    pub unsafe fn write_register(&self, register: u8, data: u32) {
+        write_volatile(self.address.add(REGISTER_SELECT) as *mut u32, register as u32);
+        write_volatile(self.address.add(REGISTER_DATA) as *mut u32, data);
+    }
+
+    //Read the ID assigned in hardware
+    pub unsafe fn hardware_id(&self) -> u8 {
+        ((self.read_register(REGISTER_ID) >> 24) & 0x0F) as u8
+    }
+
+    //Test if a global system interrupt is handled by this IOAPIC
+    pub fn handles(&self, interrupt: u32) -> bool {
+        interrupt >= self.interrupt_base && interrupt - self.interrupt_base < self.entry_count
+    }
+
+    //Redirection entry access by global system interrupt
+    pub unsafe fn read_entry(&self, interrupt: u32) -> Result<RedirectionEntry, ReturnCode> {
+        let register = self.entry_register(interrupt)?;
+        let low = self.read_register(register) as u64;
+        let high = self.read_register(register + 1) as u64;
+        RedirectionEntry::from_u64(low | high << 32)
+    }
+    pub unsafe fn write_entry(&self, interrupt: u32, entry: RedirectionEntry) -> Result<(), ReturnCode> {
+        let register = self.entry_register(interrupt)?;
+        let data = entry.to_u64();
+        //Mask while the entry is inconsistent
+        self.write_register(register, RedirectionEntry::MASKED.to_u64() as u32);
+        self.write_register(register + 1, (data >> 32) as u32);
+        self.write_register(register, data as u32);
+        Ok(())
+    }
+
+    //Mask every entry
+    pub unsafe fn mask_all(&self) -> Result<(), ReturnCode> {
+        for index in 0..self.entry_count {self.write_entry(self.interrupt_base + index, RedirectionEntry::MASKED)?;}
+        Ok(())
+    }
+
+    //Find the register holding the low half of an entry
+    fn entry_register(&self, interrupt: u32) -> Result<u8, ReturnCode> {
+        if !self.handles(interrupt) {return Err(ReturnCode::IndexOutOfBounds)}
+        u8::try_from(REGISTER_TABLE as u32 + 2 * (interrupt - self.interrupt_base)).map_err(|_| ReturnCode::IndexOutOfBounds)
+    }
+}
+
+
+// INTERRUPT ROUTING
+//ISA IRQ Route
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct IsaRoute {
+    pub interrupt:       u32, //Global system interrupt the IRQ arrives on
+    pub active_low:      bool,
+    pub level_triggered: bool,
+}
+impl IsaRoute {
+    //Route from an interrupt source override, resolving bus conforming flags to ISA's active high and edge triggered
+    pub fn new(interrupt: u32, flags: InterruptFlags) -> Self {
+        Self {
+            interrupt,
+            active_low: flags.polarity == Polarity::ActiveLow,
+            level_triggered: flags.trigger == TriggerMode::Level,
+        }
+    }
+}
+
+//Interrupt Router
+pub struct InterruptRouter {
+    ioapics: [Option<IoApic>; MAX_IOAPICS],
+    isa:     [IsaRoute; ISA_IRQS],
+}
+impl InterruptRouter {
+    //Constructor, with ISA IRQs identity mapped until overridden
+    pub const fn new() -> Self {
+        let mut isa = [IsaRoute {interrupt: 0, active_low: false, level_triggered: false}; ISA_IRQS];
+        let mut irq = 0;
+        while irq < ISA_IRQS {isa[irq].interrupt = irq as u32; irq += 1;}
+        Self {ioapics: [None; MAX_IOAPICS], isa}
+    }
+
+    //Add an IOAPIC, masking all of its entries
+    pub unsafe fn add_ioapic(&mut self, ioapic: IoApic) -> Result<(), ReturnCode> {
+        let slot = self.ioapics.iter_mut().find(|slot| slot.is_none()).ok_or(ReturnCode::OutOfResources)?;
+        ioapic.mask_all()?;
+        *slot = Some(ioapic);
+        Ok(())
+    }
+
+    //Apply an ACPI interrupt source override
+    pub fn add_override(&mut self, source: u8, interrupt: u32, flags: InterruptFlags) -> Result<(), ReturnCode> {
+        let route = self.isa.get_mut(source as usize).ok_or(ReturnCode::IndexOutOfBounds)?;
+        *route = IsaRoute::new(interrupt, flags);
+        Ok(())
+    }
+
+    //Find the global system interrupt an ISA IRQ arrives on
+    pub fn isa_route(&self, irq: u8) -> Result<IsaRoute, ReturnCode> {
+        self.isa.get(irq as usize).copied().ok_or(ReturnCode::IndexOutOfBounds)
+    }
+
+    //Find the IOAPIC handling a global system interrupt
+    pub fn ioapic(&self, interrupt: u32) -> Result<&IoApic, ReturnCode> {
+        self.ioapics.iter().flatten().find(|ioapic| ioapic.handles(interrupt)).ok_or(ReturnCode::NotFound)
+    }
+
+    //Route a global system interrupt to a vector on a CPU
+    pub unsafe fn route(&self, interrupt: u32, entry: RedirectionEntry) -> Result<(), ReturnCode> {
+        self.ioapic(interrupt)?.write_entry(interrupt, entry)
+    }
+
+    //Route an ISA IRQ to a vector on a CPU, returning the global system interrupt used
+    pub unsafe fn route_isa(&self, irq: u8, vector: u8, destination: u8) -> Result<u32, ReturnCode> {
+        let route = self.isa_route(irq)?;
+        self.route(route.interrupt, RedirectionEntry::new(vector, destination, route.active_low, route.level_triggered))?;
+        Ok(route.interrupt)
+    }
+
+    //Mask or unmask a global system interrupt
+    pub unsafe fn set_masked(&self, interrupt: u32, masked: bool) -> Result<(), ReturnCode> {
+        let ioapic = self.ioapic(interrupt)?;
+        let mut entry = ioapic.read_entry(interrupt)?;
+        entry.masked = masked;
+        ioapic.write_entry(interrupt, entry)
+    }
+}
+impl Default for InterruptRouter {
+    fn default() -> Self {Self::new()}
+}
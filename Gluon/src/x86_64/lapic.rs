@@ -16,6 +16,9 @@ pub static mut LAPIC_ADDRESS: *mut u8 = 0xFEE00000 as *mut u8;
 pub unsafe fn apic_check() -> bool {
     cpuid(0x0001, 0).3 & (1<<9) > 0
 }
+pub unsafe fn tsc_deadline_check() -> bool {
+    cpuid(0x0001, 0).2 & (1<<24) > 0
+}
 
 //Model Specific Register Operations
 pub unsafe fn set_base(base: u64) -> Result<(), &'static str> {
@@ -60,16 +63,20 @@ pub unsafe fn spurious(int: u8) {
 pub unsafe fn timer(vector: u8, mask: bool, mode: TimerMode) {
     write_register(0x0320, vector as u32 | (if mask {1u32} else {0u32} << 16) | ((mode as u32) << 17)).unwrap();
 }
-#[repr(u8)] pub enum TimerMode {
+#[repr(u8)] #[derive(Clone, Copy, PartialEq, Eq, Debug)] pub enum TimerMode {
     OneShot     = 0b00,
     Periodic    = 0b01,
     TSCDeadline = 0b10,
 }
 
-//Reg 0x0380: Initial Count
+//Reg 0x0380: Initial Count (in one-shot mode, writing a count arms the timer and writing 0 disarms it)
 pub unsafe fn initial_count(count: u32) {
     write_register(0x0380, count).unwrap();
 }
+pub unsafe fn one_shot(nanoseconds: u64, frequency: u64) {
+    let count = (nanoseconds as u128 * frequency as u128 / 1_000_000_000).clamp(1, u32::MAX as u128);
+    initial_count(count as u32);
+}
 
 //Reg 0x0390: Current Count
 pub unsafe fn current_count() -> u32 {
@@ -90,3 +97,8 @@ pub unsafe fn divide_config(div: Divide) {
     Divide_64  = 0b1001,
     Divide_128 = 0b1010,
 }
+
+//MSR 0x06E0: TSC Deadline (in TSC-deadline mode, the timer fires once the TSC reaches the deadline, and writing 0 disarms it)
+pub unsafe fn tsc_deadline(deadline: u64) {
+    msr::IA32_TSC_DEADLINE.write(deadline);
+}
@@ -1,8 +1,10 @@
 // GLUON: x86-64
 // Modules handling the x86-64 instruction set architecture:
 //   instructions: Functions that shortcut intrinsic instructions from the x86-64 instruction set architecture
+//   ioapic:       Structs and functions related to the handling of the I/O Advanced Programmable Interrupt Controller
 //   lapic:        Functions and objects related to the handling of the Local Advanced Programmable Interrupt Controller
 //   msr:          Structs and objects handling Model Specific Registers
+//   page_operations: Traits, structs, and functions which edit x86-64 page tables through address translation and physical page allocation
 //   paging:       Structs, enums, and traits related to the contents and handling of x86-64 page tables
 //   port:         Structs, functions, and traits related to the handling of ports
 //   segmentation: Structs and enums related to the contents and handling of x86-64 GDT, IDT, and other segmentation structures
@@ -12,8 +14,10 @@
 // HEADER
 //Modules
 pub mod instructions;
+pub mod ioapic;
 pub mod lapic;
 pub mod msr;
+pub mod page_operations;
 pub mod paging;
 pub mod port;
 pub mod registers;
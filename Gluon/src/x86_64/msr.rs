@@ -275,6 +275,7 @@ pub static IA32_VMX_PROCBASED_CTLS:       MSR = MSR(0x0000_0482);
 pub static IA32_VMX_EXIT_CTLS:            MSR = MSR(0x0000_0483);
 pub static IA32_VMX_ENTRY_CTLS:           MSR = MSR(0x0000_0484);
 pub static IA32_VMX_MISC:                 MSR = MSR(0x0000_0485);
+pub static IA32_TSC_DEADLINE:             MSR = MSR(0x0000_06E0);
 //Cutoff at Page 2-33, may finish at a later time
 pub static IA32_EFER:                     MSR = MSR(0xC000_0080);
 pub static IA32_STAR:                     MSR = MSR(0xC000_0081);
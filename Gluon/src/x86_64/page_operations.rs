@@ -0,0 +1,733 @@
+// GLUON: x86-64 PAGE OPERATIONS
+// Traits, structs, and functions which edit x86-64 page tables through a physical to linear address translator and a physical page allocator
+
+
+// HEADER
+//Imports
+use crate::noble::return_code::ReturnCode;
+use crate::x86_64::paging::*;
+use crate::x86_64::instructions::invlpg;
+
+
+// ADDRESS TRANSLATION
+//Address Translator Trait
+pub trait AddressTranslator {
+    fn translate(&self, physical: PhysicalAddress) -> Result<LinearAddress, ReturnCode>;
+}
+
+//Offset Identity Address Translator
+pub struct OffsetIdentity {
+    pub offset: usize,
+    pub limit: usize,
+}
+impl AddressTranslator for OffsetIdentity {
+    fn translate(&self, physical: PhysicalAddress) -> Result<LinearAddress, ReturnCode> {
+        if physical.0 > self.limit {return Err(ReturnCode::MemoryOutOfBounds)}
+        Ok(LinearAddress(physical.0 + self.offset))
+    }
+}
+
+
+// PHYSICAL ADDRESS ALLOCATION
+//Physical Allocator Trait
+pub trait PhysicalAddressAllocator {
+    fn take(&self, pages: &mut [PhysicalAddress]) -> Result<(), ReturnCode>;
+    fn give(&self, pages: &[PhysicalAddress]) -> Result<(), ReturnCode>;
+    fn take_one(&self) -> Result<PhysicalAddress, ReturnCode> {
+        let mut buffer: [PhysicalAddress; 1] = [PhysicalAddress(0)];
+        self.take(&mut buffer)?;
+        Ok(buffer[0])
+    }
+    fn give_one(&self, page: PhysicalAddress) -> Result<(), ReturnCode> {
+        let array: [PhysicalAddress; 1] = [page];
+        self.give(&array)
+    }
+    //Take a physically contiguous block aligned to the size of a large page at the given level
+    fn take_block(&self, _level: PageMapLevel) -> Result<PhysicalAddress, ReturnCode> {
+        Err(ReturnCode::UnsupportedFeature)
+    }
+    //Give back a block taken for a large page, as individual pages
+    fn give_block(&self, block: PhysicalAddress, level: PageMapLevel) -> Result<(), ReturnCode> {
+        for page in 0..page_size(level) / PAGE_SIZE_4KIB {self.give_one(block.add(page * PAGE_SIZE_4KIB))?;}
+        Ok(())
+    }
+}
+
+
+// TLB INVALIDATION
+//TLB Invalidator Trait (called for each page whose entry was changed or removed by an operation editing a page map which may be loaded)
+pub trait TlbInvalidator {
+    fn invalidate(&self, address: LinearAddress);
+}
+
+//Page Invalidator (INVLPG for the active page map, or INVPCID given the PCID of a page map which is not the active one, which INVLPG cannot reach)
+pub struct PageInvalidator {
+    pub pcid: Option<u16>,
+}
+impl PageInvalidator {
+    pub const ACTIVE: Self = Self {pcid: None};
+}
+impl TlbInvalidator for PageInvalidator {
+    fn invalidate(&self, address: LinearAddress) {
+        match self.pcid {
+            Some(pcid) => invalidate_pcid(pcid, address),
+            None       => invlpg(address.0),
+        }
+    }
+}
+
+
+// PAGE OPERATIONS
+//Page Operation Trait
+pub trait PageOperation {
+    fn op(&mut self, entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode>;
+}
+
+//Map Memory
+pub struct MapMemory<'s> {
+    pub allocator: &'s dyn PhysicalAddressAllocator,
+    pub translator: &'s dyn AddressTranslator,
+    pub write: bool,
+    pub user: bool,
+    pub execute_disable: bool,
+    pub largest_page: PageMapLevel, //Largest level at which memory entries may be made (L1 to map only 4KiB pages)
+}
+impl<'i> MapMemory<'i> {
+    //Test if a whole entry is being mapped at a level which may hold a large page
+    fn large_page_fits(&self, level: PageMapLevel, start: LinearAddress, end: LinearAddress) -> bool {
+        level != PageMapLevel::L1 && level as usize <= self.largest_page as usize && end.0 - start.0 == page_size(level)
+    }
+
+    //Allocate a single page as a table and recurse
+    fn map_table(&mut self, entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode> {
+        let physical = self.allocator.take_one()?;
+        let linear = self.translator.translate(physical)?;
+        let map = PageMap::new(linear, entry.entry_level.sub()?)?;
+        page_map_editor(map, self, start, end)?;
+        //tables are left writeable and executable so that permissions are decided by the memory entries beneath them
+        PageMapEntry::new(entry.entry_level, PageMapEntryType::Table, physical, true, true, self.user, false)
+    }
+}
+impl<'i> PageOperation for MapMemory<'i> {
+    fn op(&mut self, entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode> {
+        match (entry.in_use, entry.entry_level, entry.entry_type) {
+            (true,  _, PageMapEntryType::Memory) => {Err(ReturnCode::Test03)}, //throw error due to previously allocated memory
+            (true,  _, PageMapEntryType::Table)  => {
+                //use existing table and recurse
+                let mut entry = entry;
+                let map = PageMap::new(self.translator.translate(entry.physical)?, entry.entry_level.sub()?)?;
+                page_map_editor(map, self, start, end)?;
+                //writeln!(self.printer, "tlb: {:?}", entry);
+                //widen table so that it does not restrict the new memory
+                if self.write            {entry.write = true}
+                if self.user             {entry.user = true}
+                if !self.execute_disable {entry.execute_disable = false}
+                Ok(entry)
+            },
+            (false, PageMapLevel::L1, _) => {
+                //allocate a single page as memory
+                let address = self.allocator.take_one()?;
+                let value = PageMapEntry::new(PageMapLevel::L1, PageMapEntryType::Memory, address, true, self.write, self.user, self.execute_disable);
+                //writeln!(self.printer, "nwm: {:?}", value);
+                value
+            },
+            (false, level, _) if self.large_page_fits(level, start, end) => {
+                //allocate a contiguous block as a large page, or fall back to a table if there is none
+                match self.allocator.take_block(level) {
+                    Ok(address) => PageMapEntry::new(level, PageMapEntryType::Memory, address, true, self.write, self.user, self.execute_disable),
+                    Err(_)      => self.map_table(entry, start, end),
+                }
+            },
+            (false, _, _) => self.map_table(entry, start, end),
+        }
+    }
+}
+
+//Unmap Memory
+pub struct UnmapMemory<'s> {
+    pub allocator: &'s dyn PhysicalAddressAllocator,
+    pub translator: &'s dyn AddressTranslator,
+    pub invalidator: &'s dyn TlbInvalidator,
+}
+impl<'i> PageOperation for UnmapMemory<'i> {
+    fn op(&mut self, entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode> {
+        match (entry.in_use, entry.entry_type, entry.entry_level) {
+            (false, _, _) => Err(ReturnCode::Test05), //throw error due to deallocating area not in use
+            (true, PageMapEntryType::Table, _) => {
+                //recurse through existing table
+                //writeln!(self.printer, "traverse: {:?}", entry.physical);
+                let map = PageMap::new(self.translator.translate(entry.physical)?, entry.entry_level.sub()?)?;
+                page_map_editor(map, self, start, end)?;
+                //test if map is empty
+                let mut map_empty: bool = true;
+                for position in 0usize..512 {
+                    let map_entry = map.read_entry(position)?;
+                    if map_entry.in_use {
+                        map_empty = false;
+                        break
+                    }
+                }
+                //finish
+                //return Ok(entry); // PROBLEM SOMEWHERE HERE
+                if map_empty {
+                    let physical = entry.physical;
+                    self.allocator.give(&[physical])?;
+                    PageMapEntry::from_u64(0, entry.entry_level)
+                }
+                else {
+                    Ok(entry)
+                }
+            },
+            (true, PageMapEntryType::Memory, PageMapLevel::L1) => {
+                //deallocate 4KB memory block
+                let physical = entry.physical;
+                //writeln!(self.printer, "dealloc: {:?}", physical);
+                self.allocator.give(&[physical])?;
+                self.invalidator.invalidate(start);
+                PageMapEntry::from_u64(0, PageMapLevel::L1)
+            },
+            (true, PageMapEntryType::Memory, level) if end.0 - start.0 == page_size(level) => {
+                //deallocate large memory block
+                self.allocator.give_block(entry.physical, level)?;
+                self.invalidator.invalidate(start);
+                PageMapEntry::from_u64(0, level)
+            },
+            (true, PageMapEntryType::Memory, _) => {
+                //split large memory block so that part of it can be deallocated
+                let table = split_page(entry, self.allocator, self.translator)?;
+                self.op(table, start, end)
+            },
+        }
+    }
+}
+
+//Mark in use
+pub struct MarkInUse<'s> {
+    pub translator: &'s dyn AddressTranslator,
+}
+impl<'i> PageOperation for MarkInUse<'i> {
+    fn op(&mut self, mut entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode> {
+        match (entry.in_use, entry.present, entry.entry_type) {
+            (true,  _,     _) => {},
+            (_,     false, _) => {},
+            (false, true,  PageMapEntryType::Memory) => {
+                entry.in_use = true;
+            },
+            (false, true,  PageMapEntryType::Table) => {
+                let map = PageMap::new(self.translator.translate(entry.physical)?, entry.entry_level.sub()?)?;
+                page_map_editor(map, self, start, end)?;
+                entry.in_use = true;
+            },
+        }
+        Ok(entry)
+    }
+}
+
+//Deprivilege
+pub struct DePrivilege<'s> {
+    pub translator: &'s dyn AddressTranslator,
+}
+impl<'i> PageOperation for DePrivilege<'i> {
+    fn op(&mut self, mut entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode> {
+        match (entry.in_use, entry.entry_type) {
+            (true, PageMapEntryType::Memory) => {
+                entry.user = true;
+            },
+            (true, PageMapEntryType::Table) => {
+                let map = PageMap::new(self.translator.translate(entry.physical)?, entry.entry_level.sub()?)?;
+                page_map_editor(map, self, start, end)?;
+                entry.user = true;
+            },
+            (_, _) => {},
+        }
+        Ok(entry)
+    }
+}
+
+//Protect Memory
+pub struct ProtectMemory<'s> {
+    pub allocator: &'s dyn PhysicalAddressAllocator,
+    pub translator: &'s dyn AddressTranslator,
+    pub invalidator: &'s dyn TlbInvalidator,
+    pub write: bool,
+    pub user: bool,
+    pub execute_disable: bool,
+}
+impl<'i> PageOperation for ProtectMemory<'i> {
+    fn op(&mut self, mut entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode> {
+        match (entry.in_use, entry.entry_type, entry.entry_level) {
+            (false, _, _) => Err(ReturnCode::NoMapping), //throw error due to protecting area not in use
+            (true, PageMapEntryType::Table, _) => {
+                //recurse through existing table
+                let map = PageMap::new(self.translator.translate(entry.physical)?, entry.entry_level.sub()?)?;
+                page_map_editor(map, self, start, end)?;
+                //widen table so that it does not restrict the new permissions
+                if self.write            {entry.write = true}
+                if self.user             {entry.user = true}
+                if !self.execute_disable {entry.execute_disable = false}
+                Ok(entry)
+            },
+            (true, PageMapEntryType::Memory, level) if level == PageMapLevel::L1 || end.0 - start.0 == page_size(level) => {
+                //change permissions of whole memory block
+                entry.write = self.write;
+                entry.user = self.user;
+                entry.execute_disable = self.execute_disable;
+                self.invalidator.invalidate(start);
+                Ok(entry)
+            },
+            (true, PageMapEntryType::Memory, _) => {
+                //split large memory block so that part of it can be protected
+                let table = split_page(entry, self.allocator, self.translator)?;
+                self.op(table, start, end)
+            },
+        }
+    }
+}
+
+//Check User Access
+pub struct CheckUserAccess<'s> {
+    pub translator: &'s dyn AddressTranslator,
+    pub write: bool,
+}
+impl<'i> PageOperation for CheckUserAccess<'i> {
+    fn op(&mut self, entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode> {
+        if !entry.present               {return Err(ReturnCode::NoMapping)}    //throw error due to area not mapped
+        if !entry.user                  {return Err(ReturnCode::AccessDenied)} //throw error due to area restricted to supervisor
+        if self.write && !entry.write   {return Err(ReturnCode::AccessDenied)} //throw error due to area not writeable
+        if entry.entry_type == PageMapEntryType::Table {
+            let map = PageMap::new(self.translator.translate(entry.physical)?, entry.entry_level.sub()?)?;
+            page_map_editor(map, self, start, end)?;
+        }
+        Ok(entry)
+    }
+}
+
+//Split a large page into a table of pages one level smaller covering the same memory with the same permissions and caching (held by the pages, as tables are left writeable and executable)
+fn split_page(entry: PageMapEntry, allocator: &dyn PhysicalAddressAllocator, translator: &dyn AddressTranslator) -> Result<PageMapEntry, ReturnCode> {
+    let level = entry.entry_level.sub()?;
+    let physical = allocator.take_one()?;
+    let map = PageMap::new(translator.translate(physical)?, level)?;
+    for position in 0usize..512 {
+        let mut page = PageMapEntry::new(level, PageMapEntryType::Memory, entry.physical.add(position * page_size(level)), entry.present, entry.write, entry.user, entry.execute_disable)?;
+        page.write_through   = entry.write_through;
+        page.cache_disable   = entry.cache_disable;
+        page.attribute_table = entry.attribute_table;
+        page.global          = entry.global;
+        map.write_entry(position, page)?;
+    }
+    PageMapEntry::new(entry.entry_level, PageMapEntryType::Table, physical, true, true, entry.user, false)
+}
+
+// PAGE OPERATION
+//Virtual Memory Editor (the map is a root page map, whose level decides the canonical form of linear addresses, a PML5 when 5-level paging is in use)
+pub fn virtual_memory_editor(map: PageMap, operation: &mut dyn PageOperation, start: LinearAddress, end: LinearAddress) -> Result<(), ReturnCode> {
+    canonical(start, map.map_level)?; canonical(end, map.map_level)?;
+    page_map_editor(map, operation, start, end)
+}
+
+//Page Map Editor (for operations recursing into the table of an entry, with addresses already checked against the root page map)
+pub fn page_map_editor(map: PageMap, operation: &mut dyn PageOperation, start: LinearAddress, end: LinearAddress) -> Result<(), ReturnCode> {
+    //
+    if end.0 <= start.0 {return Err(ReturnCode::Test01)}
+    //
+    let index_start: usize = extract_index(start, map.map_level);
+    let index_end: usize = extract_index(end.sub(1), map.map_level);
+    let mut start_current: LinearAddress = start;
+    let p = page_size(map.map_level);
+    //
+    for index_current in index_start..index_end+1 {
+        //
+        let end_current: LinearAddress =
+            if index_current == index_end {end}
+            else {LinearAddress(((start_current.0 + p)/p) * p)};
+        //
+        let entry_read: PageMapEntry = map.read_entry(index_current)?;
+        let entry_write: PageMapEntry = operation.op(entry_read, start_current, end_current)?;
+        map.write_entry(index_current, entry_write)?;
+        //
+        start_current = end_current;
+    }
+    //
+    Ok(())
+}
+
+
+// TESTS
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::{Cell, RefCell};
+    use std::collections::BTreeSet;
+
+    //Simulated physical memory (frame 0 holds the PML4, the rest are handed out by the allocator)
+    #[repr(C, align(4096))]
+    #[derive(Clone, Copy)]
+    struct Frame([u64; 512]);
+    struct TestMemory {
+        base:   *mut Frame,
+        frames: usize,
+    }
+    impl TestMemory {
+        fn new(frames: usize) -> Self {
+            let memory: &'static mut [Frame] = vec![Frame([0; 512]); frames].leak();
+            Self {base: memory.as_mut_ptr(), frames}
+        }
+        fn root(&self) -> PageMap {
+            PageMap::new(self.translate(PhysicalAddress(0)).unwrap(), PageMapLevel::L4).unwrap()
+        }
+        fn root_empty(&self) -> bool {
+            (0..PAGE_NUMBER_1).all(|position| !self.root().read_entry(position).unwrap().in_use)
+        }
+    }
+    impl AddressTranslator for TestMemory {
+        fn translate(&self, physical: PhysicalAddress) -> Result<LinearAddress, ReturnCode> {
+            if physical.0 >= self.frames * PAGE_SIZE_4KIB {return Err(ReturnCode::MemoryOutOfBounds)}
+            Ok(LinearAddress(self.base as usize + physical.0))
+        }
+    }
+
+    //Allocator handing out frames of the simulated memory, and large page blocks above it which are never dereferenced
+    struct TestAllocator<'m> {
+        memory:     &'m TestMemory,
+        free:       RefCell<Vec<PhysicalAddress>>,
+        next_block: Cell<usize>,
+        blocks:     Cell<usize>, //Large page blocks taken and not given back whole
+    }
+    impl<'m> TestAllocator<'m> {
+        fn new(memory: &'m TestMemory) -> Self {
+            let free = (1..memory.frames).rev().map(|frame| PhysicalAddress(frame * PAGE_SIZE_4KIB)).collect();
+            Self {memory, free: RefCell::new(free), next_block: Cell::new(64 * GIB), blocks: Cell::new(0)}
+        }
+        fn free(&self) -> usize {
+            self.free.borrow().len()
+        }
+    }
+    impl PhysicalAddressAllocator for TestAllocator<'_> {
+        fn take(&self, pages: &mut [PhysicalAddress]) -> Result<(), ReturnCode> {
+            for page in pages {
+                *page = self.free.borrow_mut().pop().ok_or(ReturnCode::OutOfResources)?;
+                unsafe {core::ptr::write_bytes(self.memory.translate(*page)?.0 as *mut u8, 0, PAGE_SIZE_4KIB)}
+            }
+            Ok(())
+        }
+        fn give(&self, pages: &[PhysicalAddress]) -> Result<(), ReturnCode> {
+            for page in pages {
+                //pages of split large page blocks lie above the simulated memory and are dropped
+                if page.0 < self.memory.frames * PAGE_SIZE_4KIB {
+                    assert!(!self.free.borrow().iter().any(|free| free.0 == page.0), "page given back twice");
+                    self.free.borrow_mut().push(*page);
+                }
+            }
+            Ok(())
+        }
+        fn take_block(&self, level: PageMapLevel) -> Result<PhysicalAddress, ReturnCode> {
+            let size = page_size(level);
+            let block = self.next_block.get().div_ceil(size) * size;
+            self.next_block.set(block + size);
+            self.blocks.set(self.blocks.get() + 1);
+            Ok(PhysicalAddress(block))
+        }
+        fn give_block(&self, _block: PhysicalAddress, _level: PageMapLevel) -> Result<(), ReturnCode> {
+            self.blocks.set(self.blocks.get() - 1);
+            Ok(())
+        }
+    }
+
+    //Invalidator counting the pages whose entries were changed or removed (the simulated page tables are never loaded, so there is nothing to invalidate)
+    struct TestInvalidator {
+        invalidations: Cell<usize>,
+    }
+    impl TestInvalidator {
+        fn new() -> Self {
+            Self {invalidations: Cell::new(0)}
+        }
+    }
+    impl TlbInvalidator for TestInvalidator {
+        fn invalidate(&self, _address: LinearAddress) {
+            self.invalidations.set(self.invalidations.get() + 1);
+        }
+    }
+
+    fn map_memory<'s>(memory: &'s TestMemory, allocator: &'s TestAllocator, write: bool, execute_disable: bool, largest_page: PageMapLevel) -> MapMemory<'s> {
+        MapMemory {allocator, translator: memory, write, user: true, execute_disable, largest_page}
+    }
+    fn unmap_memory<'s>(memory: &'s TestMemory, allocator: &'s TestAllocator, invalidator: &'s TestInvalidator) -> UnmapMemory<'s> {
+        UnmapMemory {allocator, translator: memory, invalidator}
+    }
+    fn translate(memory: &TestMemory, address: usize) -> Result<PageMapping, ReturnCode> {
+        translate_address(memory.root(), LinearAddress(address), &|physical| memory.translate(physical))
+    }
+    fn mapped_pages(memory: &TestMemory) -> BTreeSet<usize> {
+        PageWalker::new(memory.root(), &|physical| memory.translate(physical))
+            .flat_map(|page| (0..page.size() / PAGE_SIZE_4KIB).map(move |offset| page.linear.0 + offset * PAGE_SIZE_4KIB))
+            .collect()
+    }
+
+    #[test]
+    fn entry_encoding() {
+        let page = PageMapEntry::new(PageMapLevel::L1, PageMapEntryType::Memory, PhysicalAddress(0x0012_3000), true, true, false, true).unwrap();
+        assert_eq!(page.to_u64().unwrap(), 0x8010_0000_0012_3003);
+        let large = PageMapEntry::new(PageMapLevel::L2, PageMapEntryType::Memory, PhysicalAddress(0x0040_0000), true, false, true, false).unwrap();
+        assert_eq!(large.to_u64().unwrap(), 0x0010_0000_0040_0085);
+        let table = PageMapEntry::new(PageMapLevel::L4, PageMapEntryType::Table, PhysicalAddress(0x0000_5000), true, true, true, false).unwrap();
+        assert_eq!(table.to_u64().unwrap(), 0x0010_0000_0000_5007);
+        //The attribute table bit moves to bit 12 in large pages, where bit 7 marks the entry as memory
+        let mut pat = page;
+        pat.attribute_table = Some(true);
+        assert_eq!(pat.to_u64().unwrap() & (1<<7), 1<<7);
+        let mut pat = large;
+        pat.attribute_table = Some(true);
+        let read = PageMapEntry::from_u64(pat.to_u64().unwrap(), PageMapLevel::L2).unwrap();
+        assert_eq!((read.attribute_table, read.physical.0), (Some(true), 0x0040_0000));
+        //Large pages must be aligned to their size
+        assert_eq!(PageMapEntry::new(PageMapLevel::L3, PageMapEntryType::Memory, PhysicalAddress(0x0040_0000), true, true, true, false).err(), Some(ReturnCode::UnalignedAddress));
+        assert_eq!(PageMapEntry::new(PageMapLevel::L1, PageMapEntryType::Table, PhysicalAddress(0), true, true, true, false).err(), Some(ReturnCode::InvalidData));
+    }
+
+    #[test]
+    fn entry_round_trip() {
+        //Every combination of flags survives conversion to and from the raw form
+        let flags: [u64; 8] = [1<<0, 1<<1, 1<<2, 1<<3, 1<<4, 1<<5, 1<<52, 1<<63];
+        for (level, raw_type, physical) in [
+            (PageMapLevel::L4, 0,    0x0007_FFFF_FFFF_F000_u64),
+            (PageMapLevel::L3, 1<<7, 0x0007_FFFF_C000_0000_u64),
+            (PageMapLevel::L2, 1<<7, 0x0007_FFFF_FFE0_0000_u64),
+            (PageMapLevel::L2, 0,    0x0000_0000_1234_5000_u64),
+            (PageMapLevel::L1, 0,    0x0000_0000_1234_5000_u64),
+        ] {
+            for combination in 0..1u64<<flags.len() {
+                let mut raw = physical | raw_type;
+                for (bit, flag) in flags.iter().enumerate() {if combination & (1<<bit) != 0 {raw |= flag}}
+                let entry = PageMapEntry::from_u64(raw, level).unwrap();
+                assert_eq!(entry.to_u64().unwrap(), raw, "{:?} {:016X}", level, raw);
+                assert_eq!(entry.physical.0 as u64, physical);
+            }
+        }
+    }
+
+    #[test]
+    fn map_unmap_round_trip() {
+        let memory = TestMemory::new(64);
+        let allocator = TestAllocator::new(&memory);
+        let initial = allocator.free();
+        //A low range and a higher half range crossing the boundaries of every table level
+        let low = (0x1000, 0x9000);
+        let high_start = oct_to_usize_4(0o400, 0o777, 0o777, 0o774, 0).unwrap();
+        let high = (high_start, high_start + 8 * PAGE_SIZE_4KIB);
+        let mut map = map_memory(&memory, &allocator, true, true, PageMapLevel::L1);
+        for (start, end) in [low, high] {virtual_memory_editor(memory.root(), &mut map, LinearAddress(start), LinearAddress(end)).unwrap();}
+        assert_eq!(mapped_pages(&memory).len(), 16);
+        for (start, end) in [low, high] {
+            for address in (start..end).step_by(PAGE_SIZE_4KIB) {
+                let page = translate(&memory, address + 0x123).unwrap();
+                assert_eq!(page.level, PageMapLevel::L1);
+                assert_eq!(page.physical.0 % PAGE_SIZE_4KIB, 0x123);
+                assert!(page.flags.write && page.flags.user && page.flags.execute_disable);
+            }
+        }
+        assert_eq!(translate(&memory, 0).err(), Some(ReturnCode::NotPresent));
+        //Unmapping gives back every page and every table emptied by it
+        let invalidator = TestInvalidator::new();
+        let mut unmap = unmap_memory(&memory, &allocator, &invalidator);
+        for (start, end) in [low, high] {virtual_memory_editor(memory.root(), &mut unmap, LinearAddress(start), LinearAddress(end)).unwrap();}
+        assert!(mapped_pages(&memory).is_empty());
+        assert_eq!(invalidator.invalidations.get(), 16);
+        assert_eq!(allocator.free(), initial);
+        assert!(memory.root_empty());
+    }
+
+    #[test]
+    fn map_unmap_57_bit() {
+        let memory = TestMemory::new(64);
+        let allocator = TestAllocator::new(&memory);
+        let initial = allocator.free();
+        let root = PageMap::new(memory.translate(PhysicalAddress(0)).unwrap(), PageMapLevel::L5).unwrap();
+        //A higher half address only canonical with 57 bits, mapped through a PML5 root
+        let start = oct_to_usize_5(0o400, 0o001, 0, 0, 0, 0).unwrap();
+        let end = start + 2 * PAGE_SIZE_4KIB;
+        let mut map = map_memory(&memory, &allocator, true, true, PageMapLevel::L1);
+        assert_eq!(virtual_memory_editor(memory.root(), &mut map, LinearAddress(start), LinearAddress(end)), Err(ReturnCode::NonCanonicalAddress));
+        virtual_memory_editor(root, &mut map, LinearAddress(start), LinearAddress(end)).unwrap();
+        let page = translate_address(root, LinearAddress(start + PAGE_SIZE_4KIB + 0x123), &|physical| memory.translate(physical)).unwrap();
+        assert_eq!((page.level, page.physical.0 % PAGE_SIZE_4KIB), (PageMapLevel::L1, 0x123));
+        assert_eq!(root.read_entry(0o400).unwrap().entry_type, PageMapEntryType::Table);
+        assert!(virtual_memory_editor(root, &mut map, LinearAddress(0x0100_0000_0000_0000), LinearAddress(0x0100_0000_0000_1000)).is_err());
+        //Unmapping through the PML5 gives back every page and table
+        let invalidator = TestInvalidator::new();
+        let mut unmap = unmap_memory(&memory, &allocator, &invalidator);
+        virtual_memory_editor(root, &mut unmap, LinearAddress(start), LinearAddress(end)).unwrap();
+        assert_eq!(allocator.free(), initial);
+        assert!(memory.root_empty());
+    }
+
+    #[test]
+    fn tables_kept_while_in_use() {
+        let memory = TestMemory::new(64);
+        let allocator = TestAllocator::new(&memory);
+        let initial = allocator.free();
+        let mut map = map_memory(&memory, &allocator, true, true, PageMapLevel::L1);
+        virtual_memory_editor(memory.root(), &mut map, LinearAddress(0x1000), LinearAddress(0x3000)).unwrap();
+        //Unmapping one of two pages leaves the tables which still hold the other, 3 tables and 1 page
+        let invalidator = TestInvalidator::new();
+        let mut unmap = unmap_memory(&memory, &allocator, &invalidator);
+        virtual_memory_editor(memory.root(), &mut unmap, LinearAddress(0x1000), LinearAddress(0x2000)).unwrap();
+        assert_eq!(allocator.free(), initial - 4);
+        assert!(translate(&memory, 0x2000).is_ok());
+        virtual_memory_editor(memory.root(), &mut unmap, LinearAddress(0x2000), LinearAddress(0x3000)).unwrap();
+        assert_eq!(allocator.free(), initial);
+    }
+
+    #[test]
+    fn overlap_rejected() {
+        let memory = TestMemory::new(64);
+        let allocator = TestAllocator::new(&memory);
+        let mut map = map_memory(&memory, &allocator, true, true, PageMapLevel::L1);
+        virtual_memory_editor(memory.root(), &mut map, LinearAddress(0x4000), LinearAddress(0x6000)).unwrap();
+        assert_eq!(virtual_memory_editor(memory.root(), &mut map, LinearAddress(0x5000), LinearAddress(0x7000)), Err(ReturnCode::Test03));
+        let invalidator = TestInvalidator::new();
+        let mut unmap = unmap_memory(&memory, &allocator, &invalidator);
+        assert_eq!(virtual_memory_editor(memory.root(), &mut unmap, LinearAddress(0x8000), LinearAddress(0x9000)), Err(ReturnCode::Test05));
+        assert!(virtual_memory_editor(memory.root(), &mut map, LinearAddress(0x9000), LinearAddress(0x8000)).is_err());
+        assert_eq!(virtual_memory_editor(memory.root(), &mut map, LinearAddress(SIGN_BIT_48), LinearAddress(SIGN_BIT_48 + 0x1000)), Err(ReturnCode::NonCanonicalAddress));
+    }
+
+    #[test]
+    fn large_pages() {
+        let memory = TestMemory::new(64);
+        let allocator = TestAllocator::new(&memory);
+        let initial = allocator.free();
+        let (start, end) = (2 * MIB, 6 * MIB + PAGE_SIZE_4KIB);
+        let mut map = map_memory(&memory, &allocator, true, false, PageMapLevel::L2);
+        virtual_memory_editor(memory.root(), &mut map, LinearAddress(start), LinearAddress(end)).unwrap();
+        assert_eq!(allocator.blocks.get(), 2);
+        let page = translate(&memory, 2 * MIB + 0x1234).unwrap();
+        assert_eq!(page.level, PageMapLevel::L2);
+        assert_eq!(page.physical.0 % PAGE_SIZE_2MIB, 0x1234);
+        assert_eq!(translate(&memory, 6 * MIB).unwrap().level, PageMapLevel::L1);
+        //Unmapping part of a large page splits it
+        let invalidator = TestInvalidator::new();
+        let mut unmap = unmap_memory(&memory, &allocator, &invalidator);
+        virtual_memory_editor(memory.root(), &mut unmap, LinearAddress(3 * MIB), LinearAddress(3 * MIB + PAGE_SIZE_4KIB)).unwrap();
+        assert_eq!(translate(&memory, 3 * MIB).err(), Some(ReturnCode::NotPresent));
+        let split = translate(&memory, 3 * MIB + PAGE_SIZE_4KIB).unwrap();
+        assert_eq!(split.level, PageMapLevel::L1);
+        assert_eq!(split.physical.0, page.physical.0 - 0x1234 + MIB + PAGE_SIZE_4KIB);
+        assert_eq!(mapped_pages(&memory).len(), (end - start) / PAGE_SIZE_4KIB - 1);
+        //Unmapping the rest gives back the intact block whole and every table
+        virtual_memory_editor(memory.root(), &mut unmap, LinearAddress(start), LinearAddress(3 * MIB)).unwrap();
+        virtual_memory_editor(memory.root(), &mut unmap, LinearAddress(3 * MIB + PAGE_SIZE_4KIB), LinearAddress(end)).unwrap();
+        assert_eq!(allocator.blocks.get(), 1);
+        assert_eq!(allocator.free(), initial);
+        assert!(memory.root_empty());
+    }
+
+    #[test]
+    fn split_tables_permissive() {
+        //A read-only, non-executable large page split by unmapping part of it keeps its permissions on the pages, not the new table
+        let memory = TestMemory::new(64);
+        let allocator = TestAllocator::new(&memory);
+        let mut map = map_memory(&memory, &allocator, false, true, PageMapLevel::L2);
+        virtual_memory_editor(memory.root(), &mut map, LinearAddress(2 * MIB), LinearAddress(4 * MIB)).unwrap();
+        let invalidator = TestInvalidator::new();
+        let mut unmap = unmap_memory(&memory, &allocator, &invalidator);
+        virtual_memory_editor(memory.root(), &mut unmap, LinearAddress(3 * MIB), LinearAddress(3 * MIB + PAGE_SIZE_4KIB)).unwrap();
+        let l3 = PageMap::new(memory.translate(memory.root().read_entry(0).unwrap().physical).unwrap(), PageMapLevel::L3).unwrap();
+        let l2 = PageMap::new(memory.translate(l3.read_entry(0).unwrap().physical).unwrap(), PageMapLevel::L2).unwrap();
+        let table = l2.read_entry(1).unwrap();
+        assert_eq!(table.entry_type, PageMapEntryType::Table);
+        assert!(table.write && !table.execute_disable);
+        let page = translate(&memory, 3 * MIB + PAGE_SIZE_4KIB).unwrap();
+        assert!(!page.flags.write && page.flags.execute_disable);
+    }
+
+    #[test]
+    fn protect_and_check() {
+        let memory = TestMemory::new(64);
+        let allocator = TestAllocator::new(&memory);
+        let mut map = map_memory(&memory, &allocator, true, true, PageMapLevel::L1);
+        virtual_memory_editor(memory.root(), &mut map, LinearAddress(0x10000), LinearAddress(0x20000)).unwrap();
+        let mut protect = ProtectMemory {allocator: &allocator, translator: &memory, invalidator: &TestInvalidator::new(), write: false, user: true, execute_disable: false};
+        virtual_memory_editor(memory.root(), &mut protect, LinearAddress(0x14000), LinearAddress(0x18000)).unwrap();
+        let regions: Vec<(usize, usize, bool, bool)> = PageWalker::new(memory.root(), &|physical| memory.translate(physical)).regions()
+            .map(|region| (region.linear.0, region.size, region.flags.write, region.flags.execute_disable))
+            .collect();
+        assert_eq!(regions, [(0x10000, 0x4000, true, true), (0x14000, 0x4000, false, false), (0x18000, 0x8000, true, true)]);
+        let mut check = CheckUserAccess {translator: &memory, write: true};
+        assert!(virtual_memory_editor(memory.root(), &mut check, LinearAddress(0x10000), LinearAddress(0x14000)).is_ok());
+        assert_eq!(virtual_memory_editor(memory.root(), &mut check, LinearAddress(0x13000), LinearAddress(0x15000)), Err(ReturnCode::AccessDenied));
+        assert_eq!(virtual_memory_editor(memory.root(), &mut check, LinearAddress(0x1F000), LinearAddress(0x21000)), Err(ReturnCode::NoMapping));
+        assert_eq!(virtual_memory_editor(memory.root(), &mut protect, LinearAddress(0x20000), LinearAddress(0x21000)), Err(ReturnCode::NoMapping));
+    }
+
+    #[test]
+    fn protect_large_page() {
+        let memory = TestMemory::new(64);
+        let allocator = TestAllocator::new(&memory);
+        let mut map = map_memory(&memory, &allocator, true, false, PageMapLevel::L2);
+        virtual_memory_editor(memory.root(), &mut map, LinearAddress(2 * MIB), LinearAddress(4 * MIB)).unwrap();
+        let page = translate(&memory, 2 * MIB).unwrap();
+        //Protecting part of a large page splits it, keeping the same physical memory
+        let mut protect = ProtectMemory {allocator: &allocator, translator: &memory, invalidator: &TestInvalidator::new(), write: false, user: true, execute_disable: true};
+        virtual_memory_editor(memory.root(), &mut protect, LinearAddress(3 * MIB), LinearAddress(3 * MIB + 2 * PAGE_SIZE_4KIB)).unwrap();
+        let protected = translate(&memory, 3 * MIB + PAGE_SIZE_4KIB).unwrap();
+        assert_eq!(protected.level, PageMapLevel::L1);
+        assert_eq!(protected.physical.0, page.physical.0 + MIB + PAGE_SIZE_4KIB);
+        let regions: Vec<(usize, usize, bool)> = PageWalker::new(memory.root(), &|physical| memory.translate(physical)).regions()
+            .map(|region| (region.linear.0, region.size, region.flags.write))
+            .collect();
+        assert_eq!(regions, [(2 * MIB, MIB, true), (3 * MIB, 2 * PAGE_SIZE_4KIB, false), (3 * MIB + 2 * PAGE_SIZE_4KIB, MIB - 2 * PAGE_SIZE_4KIB, true)]);
+        //Protecting a whole large page leaves it whole
+        virtual_memory_editor(memory.root(), &mut map, LinearAddress(4 * MIB), LinearAddress(6 * MIB)).unwrap();
+        virtual_memory_editor(memory.root(), &mut protect, LinearAddress(4 * MIB), LinearAddress(6 * MIB)).unwrap();
+        let whole = translate(&memory, 5 * MIB).unwrap();
+        assert_eq!(whole.level, PageMapLevel::L2);
+        assert!(!whole.flags.write);
+    }
+
+    #[test]
+    fn random_map_unmap() {
+        //Random ranges are mapped and unmapped, and the page map must always hold exactly the pages a simple model says it does
+        const WINDOW: usize = 4096;
+        let base = GIB - 8 * MIB;
+        let memory = TestMemory::new(WINDOW + 64);
+        let allocator = TestAllocator::new(&memory);
+        let initial = allocator.free();
+        let mut map = map_memory(&memory, &allocator, true, true, PageMapLevel::L2);
+        let invalidator = TestInvalidator::new();
+        let mut unmap = unmap_memory(&memory, &allocator, &invalidator);
+        let mut model: BTreeSet<usize> = BTreeSet::new();
+        let mut seed: u64 = 0x9E37_79B9_7F4A_7C15;
+        let mut random = |limit: usize| {seed ^= seed << 13; seed ^= seed >> 7; seed ^= seed << 17; seed as usize % limit};
+        for _ in 0..2000 {
+            //Some ranges start on a 2MiB boundary and are long enough to be mapped with large pages
+            let (first, length) = match random(8) {
+                0 => (random(WINDOW / PAGE_NUMBER_1 - 1) * PAGE_NUMBER_1, PAGE_NUMBER_1 + random(PAGE_NUMBER_1)),
+                1 => {let length = 1 + random(1024); (random(WINDOW - length + 1), length)},
+                _ => {let length = 1 + random(16);   (random(WINDOW - length + 1), length)},
+            };
+            let pages: Vec<usize> = (first..first + length).map(|page| base + page * PAGE_SIZE_4KIB).collect();
+            let (start, end) = (LinearAddress(pages[0]), LinearAddress(pages[length - 1] + PAGE_SIZE_4KIB));
+            if pages.iter().all(|page| !model.contains(page)) {
+                virtual_memory_editor(memory.root(), &mut map, start, end).unwrap();
+                model.extend(pages);
+            }
+            else if pages.iter().all(|page| model.contains(page)) {
+                virtual_memory_editor(memory.root(), &mut unmap, start, end).unwrap();
+                for page in pages {model.remove(&page);}
+            }
+            else {
+                //Unmap the mapped pages of a partly mapped range one at a time
+                for page in pages {
+                    if model.remove(&page) {virtual_memory_editor(memory.root(), &mut unmap, LinearAddress(page), LinearAddress(page + PAGE_SIZE_4KIB)).unwrap();}
+                }
+            }
+            assert_eq!(mapped_pages(&memory), model);
+        }
+        //Unmapping everything left gives back every page, block and table
+        for page in model.iter() {
+            virtual_memory_editor(memory.root(), &mut unmap, LinearAddress(*page), LinearAddress(*page + PAGE_SIZE_4KIB)).unwrap();
+        }
+        assert_eq!(allocator.free(), initial);
+        assert!(memory.root_empty());
+    }
+}
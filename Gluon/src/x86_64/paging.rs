@@ -36,6 +36,10 @@ pub const PIB:              usize = 0o_000_004_000_000_000_000_0000_usize; //ONE
 
 //Imports
 use crate::noble::return_code::ReturnCode;
+use crate::x86_64::instructions::cpuid;
+use crate::x86_64::instructions::invpcid;
+use crate::x86_64::msr::IA32_PAT;
+use crate::x86_64::registers::{read_cr3, read_cr4, write_cr3, write_cr4};
 
 
 // ADDRESSES
@@ -72,7 +76,8 @@ pub fn oct_to_usize_5(pml5: usize, pml4: usize, pml3: usize, pml2: usize, pml1:
     if pml2   >= PAGE_NUMBER_1  {return Err("O5 to Pointer: PML2 oct out of bounds.")}
     if pml1   >= PAGE_NUMBER_1  {return Err("O5 to Pointer: PML1 oct out of bounds.")}
     if offset >= PAGE_SIZE_4KIB {return Err("O5 to Pointer: Offset out of bounds.")}
-    let mut result: usize = if pml4 >= 0o400 {HIGHER_HALF_48} else {0};
+    let mut result: usize = if pml5 >= 0o400 {HIGHER_HALF_57} else {0};
+    result |= pml5 << (0o14 + 0o11 + 0o11 + 0o11 + 0o11);
     result |= pml4 << (0o14 + 0o11 + 0o11 + 0o11);
     result |= pml3 << (0o14 + 0o11 + 0o11);
     result |= pml2 << (0o14 + 0o11);
@@ -99,6 +104,20 @@ pub fn canonical_48(address: LinearAddress) -> Result<(), ReturnCode> {
         false => Err(ReturnCode::NonCanonicalAddress),
     }
 }
+pub fn canonical_57(address: LinearAddress) -> Result<(), ReturnCode> {
+    let mask: usize = SIGN_BIT_57 | HIGHER_HALF_57;
+    let masked: usize = address.0 & mask;
+    match masked == mask || masked == 0 {
+        true => Ok(()),
+        false => Err(ReturnCode::NonCanonicalAddress),
+    }
+}
+pub fn canonical(address: LinearAddress, root: PageMapLevel) -> Result<(), ReturnCode> {
+    match root {
+        PageMapLevel::L5 => canonical_57(address),
+        _                => canonical_48(address),
+    }
+}
 
 //Extract an index
 pub fn extract_index(address: LinearAddress, level: PageMapLevel) -> usize {
@@ -131,6 +150,24 @@ pub fn align_lvl(size: usize) -> PageMapLevel {
     else                           {PageMapLevel::L5}
 }
 
+//Test for 5-level paging (57-bit linear addresses) support and use
+pub fn la57_check() -> bool {
+    cpuid(0x0007, 0).2 & (1<<16) > 0
+}
+pub fn la57_enabled() -> bool {
+    read_cr4() & (1<<12) > 0
+}
+
+//Get the level of the root page map (CR3 points to a PML5 when 5-level paging is in use)
+pub fn root_level() -> PageMapLevel {
+    if la57_enabled() {PageMapLevel::L5} else {PageMapLevel::L4}
+}
+
+//Get the largest level at which memory entries may be made (1GiB pages are optional)
+pub fn largest_page_level() -> PageMapLevel {
+    if cpuid(0x8000_0001, 0).3 & (1<<26) > 0 {PageMapLevel::L3} else {PageMapLevel::L2}
+}
+
 
 // PAGING
 //Page Allocator
@@ -332,6 +369,24 @@ impl PageMapEntry {
             execute_disable,
         })
     }
+
+    /// Cache mode selected by the PAT, PCD, and PWT bits
+    pub fn cache_mode(&self) -> CacheMode {
+        CacheMode::from_pat_index(if self.attribute_table == Some(true) {4} else {0} + if self.cache_disable {2} else {0} + if self.write_through {1} else {0})
+    }
+
+    /// Select a cache mode (tables have no PAT bit, so are limited to the first four entries of the attribute table)
+    pub fn set_cache_mode(&mut self, mode: CacheMode) -> Result<(), ReturnCode> {
+        let index = mode.pat_index();
+        match self.attribute_table {
+            Some(_)                => {self.attribute_table = Some(index & 4 != 0)},
+            None if index & 4 != 0 => {return Err(ReturnCode::InvalidData)},
+            None                   => {},
+        }
+        self.cache_disable = index & 2 != 0;
+        self.write_through = index & 1 != 0;
+        Ok(())
+    }
 }
 
 //Page Map Level
@@ -367,3 +422,291 @@ pub enum PageMapEntryType {
     Table,
 }
 
+
+// PAGE ATTRIBUTE TABLE
+//Cache Mode (memory type of a page, selected through the page attribute table)
+#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub enum CacheMode {
+    WriteBack,      //Reads and writes are cached, for normal memory
+    WriteCombining, //Writes are buffered and combined without being cached, for framebuffers
+    WriteThrough,   //Reads are cached and writes go straight to memory
+    Uncached,       //Every access goes to memory in program order, for memory mapped I/O
+    UncachedMinus,  //Uncached unless the MTRRs make the memory write-combining
+    WriteProtect,   //Reads are cached and writes go to memory, invalidating cached lines
+}
+impl CacheMode {
+    //Memory type encoding in IA32_PAT
+    pub fn memory_type(self) -> u64 {
+        match self {
+            CacheMode::Uncached       => 0x00,
+            CacheMode::WriteCombining => 0x01,
+            CacheMode::WriteThrough   => 0x04,
+            CacheMode::WriteProtect   => 0x05,
+            CacheMode::WriteBack      => 0x06,
+            CacheMode::UncachedMinus  => 0x07,
+        }
+    }
+
+    //Index of the attribute table entry programmed with the mode (bit 2 is the PAT bit, bit 1 PCD, and bit 0 PWT)
+    pub fn pat_index(self) -> usize {
+        match self {
+            CacheMode::WriteBack      => 0,
+            CacheMode::WriteThrough   => 1,
+            CacheMode::UncachedMinus  => 2,
+            CacheMode::Uncached       => 3,
+            CacheMode::WriteProtect   => 4,
+            CacheMode::WriteCombining => 5,
+        }
+    }
+    pub fn from_pat_index(index: usize) -> Self {
+        PAT_LAYOUT[index % 8]
+    }
+}
+
+//Attribute table programmed at boot (the first four entries keep their power-on modes, so page tables without PAT bits are unaffected)
+pub const PAT_LAYOUT: [CacheMode; 8] = [
+    CacheMode::WriteBack,    CacheMode::WriteThrough,   CacheMode::UncachedMinus, CacheMode::Uncached,
+    CacheMode::WriteProtect, CacheMode::WriteCombining, CacheMode::UncachedMinus, CacheMode::Uncached,
+];
+
+//IA32_PAT value holding the layout
+pub fn pat_value() -> u64 {
+    PAT_LAYOUT.iter().enumerate().fold(0, |value, (index, mode)| value | mode.memory_type() << (index * 8))
+}
+
+//Test for page attribute table support and program it
+pub fn pat_check() -> bool {
+    cpuid(0x0001, 0).3 & (1<<16) > 0
+}
+pub fn write_pat() {
+    IA32_PAT.write(pat_value())
+}
+
+
+
+
+// PROCESS-CONTEXT IDENTIFIERS
+//Constants
+pub const PCID_MASK:     u64 = 0x0000_0000_0000_0FFF; //CR3 BITS HOLDING THE PCID WHEN PCIDS ARE ENABLED
+pub const CR3_NO_FLUSH:  u64 = 0x8000_0000_0000_0000; //CR3 WRITE BIT WHICH KEEPS TLB ENTRIES TAGGED WITH THE NEW PCID
+pub const CR4_PCIDE:     u64 = 0x0000_0000_0002_0000; //CR4 BIT ENABLING PCIDS
+
+//INVPCID Invalidation Type
+#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub enum Invalidation {
+    Address       = 0x00, //One linear address in one PCID
+    Context       = 0x01, //Every address in one PCID, except global pages
+    AllGlobal     = 0x02, //Every address in every PCID, including global pages
+    AllNonGlobal  = 0x03, //Every address in every PCID, except global pages
+}
+
+//Test for PCID and INVPCID support and use
+pub fn pcid_check() -> bool {
+    cpuid(0x0001, 0).2 & (1<<17) > 0
+}
+pub fn invpcid_check() -> bool {
+    cpuid(0x0007, 0).1 & (1<<10) > 0
+}
+pub fn pcid_enabled() -> bool {
+    read_cr4() & CR4_PCIDE > 0
+}
+
+//Enable PCIDs (CR3 must select PCID 0 as the feature is turned on, so the current page map is reloaded with it)
+pub fn enable_pcid() {
+    let cr3 = read_cr3();
+    if cr3 & PCID_MASK != 0 {write_cr3(cr3 & !PCID_MASK)}
+    write_cr4(read_cr4() | CR4_PCIDE);
+}
+
+//Load a root page map under a PCID, flushing the TLB entries tagged with the PCID only if asked to (PCID 0 and a flush when PCIDs are disabled)
+pub fn load_page_map(root: PhysicalAddress, pcid: u16, flush: bool) {
+    write_cr3(root.0 as u64 | (pcid as u64 & PCID_MASK) | if flush {0} else {CR3_NO_FLUSH});
+}
+
+//Invalidate the TLB entries of a PCID which need not be the current one
+pub fn invalidate_pcid(pcid: u16, address: LinearAddress) {
+    invpcid(Invalidation::Address as u64, pcid, address.0);
+}
+pub fn invalidate_pcid_all(pcid: u16) {
+    invpcid(Invalidation::Context as u64, pcid, 0);
+}
+
+
+// WALKING
+//Physical to linear address translation used to reach page tables while walking
+pub type TableTranslator<'t> = &'t dyn Fn(PhysicalAddress) -> Result<LinearAddress, ReturnCode>;
+
+//Effective Page Flags (permissions are combined over every level of the walk, caching flags come from the final entry)
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub struct PageFlags {
+    pub write:           bool, //Every level allows writes
+    pub user:            bool, //Every level allows ring 3 access
+    pub execute_disable: bool, //Any level disables execution
+    pub cache_mode:      CacheMode,
+    pub global:          bool,
+}
+impl PageFlags {
+    //Flags before any entry has restricted access
+    pub const PERMISSIVE: Self = Self {write: true, user: true, execute_disable: false, cache_mode: CacheMode::WriteBack, global: false};
+
+    //Restrict by an entry one level further down
+    pub fn restrict(self, entry: PageMapEntry) -> Self {
+        Self {
+            write:           self.write && entry.write,
+            user:            self.user && entry.user,
+            execute_disable: self.execute_disable || entry.execute_disable,
+            cache_mode:      entry.cache_mode(),
+            global:          entry.global.unwrap_or(false),
+        }
+    }
+}
+
+//Single Mapped Page (of any size)
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct PageMapping {
+    pub linear:   LinearAddress,
+    pub physical: PhysicalAddress,
+    pub level:    PageMapLevel,
+    pub flags:    PageFlags,
+}
+impl PageMapping {
+    pub fn size(&self) -> usize {
+        page_size(self.level)
+    }
+}
+
+//Translate a linear address to the physical address it maps to, with its effective flags
+pub fn translate_address(map: PageMap, address: LinearAddress, translator: TableTranslator) -> Result<PageMapping, ReturnCode> {
+    canonical(address, map.map_level)?;
+    let mut table = map;
+    let mut flags = PageFlags::PERMISSIVE;
+    loop {
+        let entry = table.read_entry(extract_index(address, table.map_level))?;
+        if !entry.present {return Err(ReturnCode::NotPresent)}
+        flags = flags.restrict(entry);
+        match entry.entry_type {
+            PageMapEntryType::Memory => {
+                let offset = address.0 & (page_size(table.map_level) - 1);
+                return Ok(PageMapping {linear: address, physical: entry.physical.add(offset), level: table.map_level, flags})
+            },
+            PageMapEntryType::Table => {table = PageMap::new(translator(entry.physical)?, table.map_level.sub()?)?;},
+        }
+    }
+}
+
+//Page Walker (iterates over every present page in ascending linear address order)
+pub struct PageWalker<'t> {
+    translator: TableTranslator<'t>,
+    root:       PageMapLevel,
+    depth:      usize,                     //Index into the arrays below of the table being read, 0 being the root
+    tables:     [Option<PageMap>; 5],
+    indices:    [usize; 5],
+    bases:      [usize; 5],                //Linear address of the first entry of each table, without sign extension
+    flags:      [PageFlags; 5],            //Flags inherited by the entries of each table
+}
+impl<'t> PageWalker<'t> {
+    //Constructor
+    pub fn new(map: PageMap, translator: TableTranslator<'t>) -> Self {
+        let mut tables = [None; 5];
+        tables[0] = Some(map);
+        Self {translator, root: map.map_level, depth: 0, tables, indices: [0; 5], bases: [0; 5], flags: [PageFlags::PERMISSIVE; 5]}
+    }
+
+    //Coalesce the walk into regions
+    pub fn regions(self) -> MappedRegions<'t> {
+        MappedRegions {walker: self, pending: None}
+    }
+
+    //Sign extend an address built from table indices
+    fn extend(&self, address: usize) -> LinearAddress {
+        match self.root {
+            PageMapLevel::L5 if address & SIGN_BIT_57 != 0 => LinearAddress(address | HIGHER_HALF_57),
+            PageMapLevel::L5                               => LinearAddress(address),
+            _                if address & SIGN_BIT_48 != 0 => LinearAddress(address | HIGHER_HALF_48),
+            _                                              => LinearAddress(address),
+        }
+    }
+}
+impl Iterator for PageWalker<'_> {
+    type Item = PageMapping;
+    fn next(&mut self) -> Option<PageMapping> {
+        loop {
+            let depth = self.depth;
+            let table = self.tables[depth]?;
+            //Return to the parent table once this one is finished
+            if self.indices[depth] >= PAGE_NUMBER_1 {
+                self.tables[depth] = None;
+                if depth == 0 {return None}
+                self.depth -= 1;
+                self.indices[depth - 1] += 1;
+                continue;
+            }
+            let index = self.indices[depth];
+            let linear = self.bases[depth] + index * page_size(table.map_level);
+            let entry = match table.read_entry(index) {
+                Ok(entry) if entry.present => entry,
+                _ => {self.indices[depth] += 1; continue},
+            };
+            let flags = self.flags[depth].restrict(entry);
+            match entry.entry_type {
+                PageMapEntryType::Memory => {
+                    self.indices[depth] += 1;
+                    return Some(PageMapping {linear: self.extend(linear), physical: entry.physical, level: table.map_level, flags})
+                },
+                PageMapEntryType::Table => {
+                    let child = table.map_level.sub().ok()
+                        .and_then(|level| (self.translator)(entry.physical).ok().and_then(|address| PageMap::new(address, level).ok()));
+                    match child {
+                        Some(child) => {
+                            self.depth += 1;
+                            self.tables[depth + 1] = Some(child);
+                            self.indices[depth + 1] = 0;
+                            self.bases[depth + 1] = linear;
+                            self.flags[depth + 1] = flags;
+                        },
+                        None => {self.indices[depth] += 1;},
+                    }
+                },
+            }
+        }
+    }
+}
+
+//Mapped Region (linearly contiguous pages with identical flags)
+#[derive(Clone, Copy)]
+#[derive(Debug)]
+pub struct MappedRegion {
+    pub linear:   LinearAddress,
+    pub size:     usize,
+    pub physical: Option<PhysicalAddress>, //None unless the region is physically contiguous as well
+    pub flags:    PageFlags,
+}
+
+//Mapped Regions (coalesces the pages from a walker)
+pub struct MappedRegions<'t> {
+    walker:  PageWalker<'t>,
+    pending: Option<PageMapping>,
+}
+impl Iterator for MappedRegions<'_> {
+    type Item = MappedRegion;
+    fn next(&mut self) -> Option<MappedRegion> {
+        let first = self.pending.take().or_else(|| self.walker.next())?;
+        let mut region = MappedRegion {linear: first.linear, size: first.size(), physical: Some(first.physical), flags: first.flags};
+        for page in self.walker.by_ref() {
+            if page.linear.0 != region.linear.0 + region.size || page.flags != region.flags {
+                self.pending = Some(page);
+                break;
+            }
+            if region.physical.is_some_and(|physical| physical.0 + region.size != page.physical.0) {region.physical = None}
+            region.size += page.size();
+        }
+        Some(region)
+    }
+}
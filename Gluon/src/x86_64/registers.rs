@@ -18,8 +18,22 @@ pub fn read_cr3() -> u64 {
     value
 }
 
+pub fn write_cr3(value: u64) {
+    unsafe{asm!("MOV CR3, {}", in(reg) value, options(nostack, preserves_flags));}
+}
+
 pub fn read_cr3_address() -> PhysicalAddress {
     let value: u64;
     unsafe{asm!("MOV {}, CR3", out(reg) value, options(nomem, nostack, preserves_flags));}
     PhysicalAddress((value & 0xFFFF_FFFF_FFFF_F000) as usize)
 }
+
+pub fn read_cr4() -> u64 {
+    let value: u64;
+    unsafe{asm!("MOV {}, CR4", out(reg) value, options(nomem, nostack, preserves_flags));}
+    value
+}
+
+pub fn write_cr4(value: u64) {
+    unsafe{asm!("MOV CR4, {}", in(reg) value, options(nostack, preserves_flags));}
+}
@@ -245,6 +245,54 @@ pub struct TaskStateSegment {
     pub iomba: u16,
 }
 
+//I/O Permission Bitmap (a set bit denies ring 3 access to a port, the CPU reads one byte past the last port)
+pub const IO_PORT_COUNT: usize = 0x1_0000;
+#[repr(C)]
+#[repr(packed)]
+#[derive(Clone, Copy)]
+pub struct IoPermissionBitmap {
+    pub bits:       [u8; IO_PORT_COUNT / 8],
+    pub terminator: u8,
+}
+impl IoPermissionBitmap {
+    //Constructor, denying every port
+    pub const fn new() -> Self {
+        Self {bits: [0xFF; IO_PORT_COUNT / 8], terminator: 0xFF}
+    }
+
+    //Allow or deny a range of ports
+    pub fn set(&mut self, first: u16, count: u32, allow: bool) -> Result<(), &'static str> {
+        let end = first as usize + count as usize;
+        if end > IO_PORT_COUNT {return Err("I/O Permission Bitmap: Port range out of bounds")}
+        for port in first as usize..end {
+            if allow {self.bits[port / 8] &= !(1 << (port % 8))}
+            else     {self.bits[port / 8] |=   1 << (port % 8)}
+        }
+        Ok(())
+    }
+
+    //Deny every port
+    pub fn clear(&mut self) {
+        self.bits = [0xFF; IO_PORT_COUNT / 8];
+    }
+}
+impl Default for IoPermissionBitmap {
+    fn default() -> Self {Self::new()}
+}
+
+//TSS followed by an I/O permission bitmap, which its iomba field should point to
+#[repr(C)]
+#[repr(packed)]
+#[derive(Clone, Copy)]
+pub struct TaskStateSegmentIo {
+    pub tss:    TaskStateSegment,
+    pub bitmap: IoPermissionBitmap,
+}
+impl TaskStateSegmentIo {
+    pub const IOMBA: u16 = core::mem::size_of::<TaskStateSegment>() as u16;
+    pub const LIMIT: u32 = core::mem::size_of::<TaskStateSegmentIo>() as u32 - 1;
+}
+
 //Load Task Register
 pub fn load_task_register(selector: SegmentSelector) {
     ltr(u16::from(selector))
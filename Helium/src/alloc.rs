@@ -3,7 +3,8 @@ use core::{alloc::{GlobalAlloc, Layout}, ptr::{write_volatile, read_volatile}};
 
 use gluon::{x86_64::paging::{PageMap, LinearAddress, PageMapLevel, PAGE_SIZE_1GIB, PAGE_SIZE_4KIB}, noble::return_code::ReturnCode};
 
-use crate::pmm::{PhysicalAddressAllocator, MemoryStack, virtual_memory_editor, PageOperation};
+use crate::pmm::MemoryStack;
+use gluon::x86_64::page_operations::{PhysicalAddressAllocator, virtual_memory_editor, PageOperation};
 
 #[repr(usize)]
 #[derive(Clone, Copy, Debug)]
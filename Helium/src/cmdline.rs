@@ -0,0 +1,99 @@
+// HELIUM: KERNEL COMMAND LINE
+// Structs and functions for reading boot settings from the kernel command line given in limine.cfg
+//
+// The command line is a whitespace separated list of key=value settings:
+//   log=<quiet|error|info|debug>  Amount of kernel output printed (program output is always printed)
+//   serial=<on|off>               Mirror kernel output to the first serial port
+//   tick=<hz>                     Scheduler tick rate
+//   selftest=<on|off>             Run kernel self-tests during boot
+//   init=<module path>            Module started as the init program, overriding the boot manifest
+
+
+// HEADER
+//Imports
+use gluon::noble::return_code::ReturnCode;
+
+//Constants
+pub const TICK_RATE_MIN: u32 = 100;    //LOWEST SCHEDULER TICK RATE IN HZ
+pub const TICK_RATE_MAX: u32 = 10_000; //HIGHEST SCHEDULER TICK RATE IN HZ
+
+
+// SETTINGS
+//Log Level
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum LogLevel {
+    Quiet = 0x00, //Print nothing
+    Error = 0x01, //Print errors only
+    Info  = 0x02, //Print boot progress
+    Debug = 0x03, //Print everything
+}
+
+//Boot Settings
+#[derive(Clone, Copy, Debug)]
+pub struct Settings<'s> {
+    pub log_level:  LogLevel,
+    pub serial:     bool,
+    pub tick_rate:  u32,
+    pub self_tests: bool,
+    pub init_path:  Option<&'s str>,
+}
+impl<'s> Settings<'s> {
+    pub const DEFAULT: Self = Self {
+        log_level:  LogLevel::Info,
+        serial:     false,
+        tick_rate:  1000,
+        self_tests: false,
+        init_path:  None,
+    };
+
+    //Read settings from a command line, ignoring settings which cannot be applied
+    pub fn parse(command_line: &'s str) -> Self {
+        let mut settings = Self::DEFAULT;
+        for setting in command_line.split_whitespace() {let _ = settings.apply(setting);}
+        settings
+    }
+
+    //Find settings on a command line which cannot be applied
+    pub fn errors(command_line: &'s str) -> impl Iterator<Item = (&'s str, ReturnCode)> {
+        let mut settings = Self::DEFAULT;
+        command_line.split_whitespace().filter_map(move |setting| settings.apply(setting).err().map(|error| (setting, error)))
+    }
+
+    //Apply a single key=value setting
+    pub fn apply(&mut self, setting: &'s str) -> Result<(), ReturnCode> {
+        let (key, value) = setting.split_once('=').ok_or(ReturnCode::InvalidData)?;
+        match key {
+            "log" => {
+                self.log_level = match value {
+                    "quiet" => LogLevel::Quiet,
+                    "error" => LogLevel::Error,
+                    "info"  => LogLevel::Info,
+                    "debug" => LogLevel::Debug,
+                    _       => return Err(ReturnCode::InvalidData),
+                }
+            },
+            "serial"   => {self.serial = parse_switch(value)?},
+            "selftest" => {self.self_tests = parse_switch(value)?},
+            "tick" => {
+                let tick_rate: u32 = value.parse().map_err(|_| ReturnCode::InvalidData)?;
+                if !(TICK_RATE_MIN..=TICK_RATE_MAX).contains(&tick_rate) {return Err(ReturnCode::InvalidData)}
+                self.tick_rate = tick_rate;
+            },
+            "init" => {
+                if value.is_empty() {return Err(ReturnCode::InvalidData)}
+                self.init_path = Some(value);
+            },
+            _ => return Err(ReturnCode::NotFound),
+        }
+        Ok(())
+    }
+}
+
+//Parse an on or off value
+fn parse_switch(value: &str) -> Result<bool, ReturnCode> {
+    match value {
+        "on"  | "true"  | "1" => Ok(true),
+        "off" | "false" | "0" => Ok(false),
+        _                     => Err(ReturnCode::InvalidData),
+    }
+}
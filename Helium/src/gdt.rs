@@ -11,7 +11,7 @@ use gluon::x86_64::segmentation::*;
 pub const TASK_STATE_SEGMENT_POSITION: u16 = 0x01;
 
 pub static mut TASK_STATE_SEGMENT_ENTRY: SystemSegmentDescriptor = SystemSegmentDescriptor {
-    limit:           0x00068,
+    limit:           TaskStateSegmentIo::LIMIT,
     base:            0,
     segment_type:    DescriptorType::TaskStateSegmentAvailable,
     privilege_level: PrivilegeLevel::Supervisor,
@@ -4,73 +4,355 @@
 
 // HEADER
 //Imports
-use gluon::{x86_64::paging::{PhysicalAddress, PageMap, PageMapLevel, PageMapEntryType}, noble::data_type::DataType};
+use gluon::{x86_64::paging::{CacheMode, PhysicalAddress, LinearAddress, PageMap, PageMapLevel, PageMapEntryType}, noble::{data_type::DataType, director::*, handle::*, return_code::ReturnCode, signal::*}};
+use gluon::x86_64::page_operations::{PageInvalidator, PageOperation, virtual_memory_editor};
+use gluon::x86_64::paging::{invpcid_check, pcid_enabled};
+use gluon::x86_64::registers::read_cr3_address;
+
+//Constants
+pub const MAX_PROCESSES:     usize = 16; //MAXIMUM NUMBER OF PROCESSES
+pub const MAX_THREADS:       usize = 16; //MAXIMUM NUMBER OF THREADS
+pub const MAX_PORTS:         usize = 32; //MAXIMUM NUMBER OF MESSAGE PORTS
+pub const MAX_MEMORY_PORTS:  usize = 32; //MAXIMUM NUMBER OF MEMORY PORTS
+pub const MAX_TIMERS:        usize = 16; //MAXIMUM NUMBER OF TIMERS
+pub const MAX_HANDLES:       usize = 64; //MAXIMUM NUMBER OF HANDLES HELD BY ONE PROCESS
+pub const MAX_MEMORY_AREAS:  usize = 64; //MAXIMUM NUMBER OF MEMORY AREAS OWNED BY ALL PROCESSES
+pub const MAX_PORT_MAPPINGS: usize = 32; //MAXIMUM NUMBER OF MEMORY PORT MAPPINGS MADE BY ALL PROCESSES
+pub const PORT_QUEUE_LENGTH: usize = 16; //MAXIMUM NUMBER OF MESSAGES WAITING IN ONE PORT
+pub const MAX_IO_GRANTS:     usize = 32; //MAXIMUM NUMBER OF I/O PORT RANGES GRANTED TO ALL PROCESSES
+pub const ISA_IRQS:          usize = 16; //NUMBER OF ISA IRQ LINES WHICH MAY BE BOUND TO PORTS
+pub const MAX_PCIDS:         usize = 8;  //NUMBER OF PCIDS HANDED TO ADDRESS SPACES (PCID 0 IS LEFT FOR BOOT)
+pub const KERNEL_PROCESS: ProcessID = ProcessID(0); //PROCESS WHICH OWNS KERNEL THREADS AND ADOPTS ORPHANED PROCESSES
+
+
+// TABLES
+//Fixed Capacity Table
+pub struct Table<T, const SIZE: usize> {
+    entries: [Option<T>; SIZE],
+}
+impl<T, const SIZE: usize> Table<T, SIZE> {
+    const EMPTY: Option<T> = None;
+
+    //Constructor
+    pub const fn new() -> Self {
+        Self {entries: [Self::EMPTY; SIZE]}
+    }
+
+    //Insert an entry into the first free slot
+    pub fn insert(&mut self, entry: T) -> Result<usize, ReturnCode> {
+        let index = self.entries.iter().position(|slot| slot.is_none()).ok_or(ReturnCode::OutOfResources)?;
+        self.entries[index] = Some(entry);
+        Ok(index)
+    }
+
+    //Insert an entry into a specific free slot
+    pub fn insert_at(&mut self, index: usize, entry: T) -> Result<(), ReturnCode> {
+        let slot = self.entries.get_mut(index).ok_or(ReturnCode::IndexOutOfBounds)?;
+        if slot.is_some() {return Err(ReturnCode::AddressConflict)}
+        *slot = Some(entry);
+        Ok(())
+    }
+
+    //Access an entry
+    pub fn get(&self, index: usize) -> Result<&T, ReturnCode> {
+        self.entries.get(index).and_then(|slot| slot.as_ref()).ok_or(ReturnCode::InvalidIdentifier)
+    }
+    pub fn get_mut(&mut self, index: usize) -> Result<&mut T, ReturnCode> {
+        self.entries.get_mut(index).and_then(|slot| slot.as_mut()).ok_or(ReturnCode::InvalidIdentifier)
+    }
+
+    //Remove an entry
+    pub fn remove(&mut self, index: usize) -> Result<T, ReturnCode> {
+        self.entries.get_mut(index).and_then(|slot| slot.take()).ok_or(ReturnCode::InvalidIdentifier)
+    }
+
+    //Iterate over present entries
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.entries.iter().enumerate().filter_map(|(index, slot)| slot.as_ref().map(|entry| (index, entry)))
+    }
+}
+
+
+//PCID Cache (assigns PCIDs to page maps, recycling the least recently used one when all are taken)
+pub struct PcidCache<const SIZE: usize> {
+    owners:    [Option<PhysicalAddress>; SIZE],
+    last_used: [u64; SIZE],
+    clock:     u64,
+}
+impl<const SIZE: usize> PcidCache<SIZE> {
+    //Constructor
+    pub const fn new() -> Self {
+        Self {owners: [None; SIZE], last_used: [0; SIZE], clock: 0}
+    }
+
+    //Assign a PCID to a page map, returning it and whether its TLB entries must be flushed because it was not already the page map's
+    pub fn assign(&mut self, page_map: PhysicalAddress) -> (u16, bool) {
+        self.clock += 1;
+        let (slot, fresh) = match self.slot(page_map) {
+            Some(slot) => (slot, false),
+            None => match self.owners.iter().position(|owner| owner.is_none()) {
+                Some(slot) => (slot, true),
+                None => ((0..SIZE).min_by_key(|slot| self.last_used[*slot]).unwrap_or(0), true),
+            },
+        };
+        self.owners[slot] = Some(page_map);
+        self.last_used[slot] = self.clock;
+        (slot as u16 + 1, fresh)
+    }
+
+    //Find the PCID held by a page map
+    pub fn find(&self, page_map: PhysicalAddress) -> Option<u16> {
+        self.slot(page_map).map(|slot| slot as u16 + 1)
+    }
+
+    //Release the PCID held by a page map, so it is flushed when next assigned
+    pub fn release(&mut self, page_map: PhysicalAddress) {
+        if let Some(slot) = self.slot(page_map) {self.owners[slot] = None;}
+    }
+
+    //Slot held by a page map
+    fn slot(&self, page_map: PhysicalAddress) -> Option<usize> {
+        self.owners.iter().position(|owner| owner.map(|owner| owner.0) == Some(page_map.0))
+    }
+}
 
 
 // IDENTIFIER STRUCTS
 //IDs
-struct ProcessID (u64);
-struct ThreadID  (u64);
-struct PortID    (u64);
-struct TimerID   (u64);
+#[derive(Clone, Copy, PartialEq, Eq, Debug)] pub struct ProcessID    (pub u64);
+#[derive(Clone, Copy, PartialEq, Eq, Debug)] pub struct ThreadID     (pub u64);
+#[derive(Clone, Copy, PartialEq, Eq, Debug)] pub struct PortID       (pub u64);
+#[derive(Clone, Copy, PartialEq, Eq, Debug)] pub struct MemoryPortID (pub u64);
+#[derive(Clone, Copy, PartialEq, Eq, Debug)] pub struct TimerID      (pub u64);
 
 
 // BASE STRUCTS
 //Process
 #[repr(C)]
-struct Process {
-    page_map_address: PhysicalAddress,
+pub struct Process {
+    pub page_map_address: PhysicalAddress,
+    pub handles: HandleTable,
+    pub exit_code: Option<u64>,
+    pub signals_pending: u64,
+    pub signals_mask: u64,
 }
 
 //Thread
 #[repr(C)]
-struct Thread {
-    kernel_stack: usize,
+pub struct Thread {
+    pub kernel_stack_start: LinearAddress,
+    pub kernel_stack_end: LinearAddress,
+    pub state: ThreadState,
+    pub priority: u8,
+    pub user_cycles: u64,   //TSC cycles spent running in ring 3
+    pub kernel_cycles: u64, //TSC cycles spent running in ring 0
+    pub switches: u64,      //Times the scheduler switched to the thread
+    pub wakeups: u64,       //Times the thread left a waiting state
+}
+impl Thread {
+    //Make a waiting thread ready
+    pub fn wake(&mut self) {
+        self.state = ThreadState::Ready;
+        self.wakeups += 1;
+    }
 }
 
-//Port
+//Thread State
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ThreadState {
+    Ready,                         //Thread may be scheduled
+    WaitProcess(ProcessID),        //Thread is waiting for a process to exit
+    WaitSignal,                    //Thread is waiting for an unmasked signal to be posted to its process
+    WaitFutex(usize, Option<u64>), //Thread is waiting to be woken through a futex address, or until a tick deadline
+    Exited,                        //Thread will never be scheduled again
+}
+
+//Message Port
 #[repr(C)]
+pub struct MessagePort {
+    pub queue: [Option<KernelMessage>; PORT_QUEUE_LENGTH],
+    pub read_head: usize,
+    pub write_head: usize,
+}
+impl MessagePort {
+    pub const fn new() -> Self {
+        Self {queue: [None; PORT_QUEUE_LENGTH], read_head: 0, write_head: 0}
+    }
+    pub fn push(&mut self, message: KernelMessage) -> Result<(), ReturnCode> {
+        if self.queue[self.write_head].is_some() {return Err(ReturnCode::NotReady)}
+        self.queue[self.write_head] = Some(message);
+        self.write_head = (self.write_head + 1) % PORT_QUEUE_LENGTH;
+        Ok(())
+    }
+    pub fn peek(&self) -> Result<&KernelMessage, ReturnCode> {
+        self.queue[self.read_head].as_ref().ok_or(ReturnCode::NotReady)
+    }
+    pub fn pop(&mut self) -> Result<KernelMessage, ReturnCode> {
+        let message = self.queue[self.read_head].take().ok_or(ReturnCode::NotReady)?;
+        self.read_head = (self.read_head + 1) % PORT_QUEUE_LENGTH;
+        Ok(message)
+    }
+}
+
+//Message held in a port, with any transferred handle removed from its sender
+#[derive(Clone, Copy)]
+pub struct KernelMessage {
+    pub data: [u64; MESSAGE_DATA_SIZE],
+    pub handle: Option<HandleEntry>,
+}
+
+//Memory Port
+#[repr(C)]
+#[derive(Clone, Copy)]
 pub struct MemPort {
     pub address: PhysicalAddress,
     pub level: PageMapLevel,
+    pub pages: usize,           //Consecutive pages of the given level covered by the port
+    pub cache_mode: CacheMode,  //Memory type the port's pages are mapped with (write-combining for framebuffers, uncached for device registers)
     pub data_type: DataType,
 }
 
+//Entry type of a memory port's pages, which are memory at every level that can hold pages
 pub fn port_type(level: PageMapLevel) -> PageMapEntryType {
     match level {
-        PageMapLevel::L1 => PageMapEntryType::Table,
-        _                => PageMapEntryType::Table,
+        PageMapLevel::L1 | PageMapLevel::L2 | PageMapLevel::L3 => PageMapEntryType::Memory,
+        _                                                      => PageMapEntryType::Table,
     }
 }
 
 //Timer
 #[repr(C)]
-struct Timer {
-    divisor: u64,
-    remainder: u64,
+pub struct Timer {
+    pub divisor: u64,
+    pub remainder: u64,
+    pub process: ProcessID, //Process signaled when the timer expires
+}
+impl Timer {
+    //First tick at or after a given time at which the timer expires
+    pub fn next_expiry(&self, from: u64) -> u64 {
+        next_occurrence(from, self.divisor, self.remainder)
+    }
+}
+
+
+// HANDLES
+//Kernel Object
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KernelObject {
+    Port       (PortID),
+    MemoryPort (MemoryPortID),
+    Thread     (ThreadID),
+    Timer      (TimerID),
+    Process    (ProcessID),
+}
+impl KernelObject {
+    pub fn object_type(&self) -> KernelObjectType {
+        match self {
+            KernelObject::Port(_)       => KernelObjectType::Port,
+            KernelObject::MemoryPort(_) => KernelObjectType::MemoryPort,
+            KernelObject::Thread(_)     => KernelObjectType::Thread,
+            KernelObject::Timer(_)      => KernelObjectType::Timer,
+            KernelObject::Process(_)    => KernelObjectType::Process,
+        }
+    }
+}
+
+//Handle Entry
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HandleEntry {
+    pub object: KernelObject,
+    pub rights: HandleRights,
+}
+
+//Handle Table
+pub type HandleTable = Table<HandleEntry, MAX_HANDLES>;
+impl HandleTable {
+    //Open a handle
+    pub fn open(&mut self, object: KernelObject, rights: HandleRights) -> Result<Handle, ReturnCode> {
+        Ok(Handle(self.insert(HandleEntry {object, rights})? as u64))
+    }
+
+    //Open a handle with a well known value
+    pub fn open_at(&mut self, handle: Handle, object: KernelObject, rights: HandleRights) -> Result<(), ReturnCode> {
+        self.insert_at(handle.0 as usize, HandleEntry {object, rights})
+    }
+
+    //Look up a handle, failing if it does not carry the required rights
+    pub fn check(&self, handle: Handle, rights: HandleRights) -> Result<HandleEntry, ReturnCode> {
+        let entry = *self.get(handle.0 as usize)?;
+        if !entry.rights.contains(rights) {return Err(ReturnCode::AccessDenied)}
+        Ok(entry)
+    }
+
+    //Look up a handle to a specific type of object
+    pub fn check_port(&self, handle: Handle, rights: HandleRights) -> Result<PortID, ReturnCode> {
+        match self.check(handle, rights)?.object {KernelObject::Port(id) => Ok(id), _ => Err(ReturnCode::InvalidIdentifier)}
+    }
+    pub fn check_memory_port(&self, handle: Handle, rights: HandleRights) -> Result<MemoryPortID, ReturnCode> {
+        match self.check(handle, rights)?.object {KernelObject::MemoryPort(id) => Ok(id), _ => Err(ReturnCode::InvalidIdentifier)}
+    }
+    pub fn check_thread(&self, handle: Handle, rights: HandleRights) -> Result<ThreadID, ReturnCode> {
+        match self.check(handle, rights)?.object {KernelObject::Thread(id) => Ok(id), _ => Err(ReturnCode::InvalidIdentifier)}
+    }
+    pub fn check_timer(&self, handle: Handle, rights: HandleRights) -> Result<TimerID, ReturnCode> {
+        match self.check(handle, rights)?.object {KernelObject::Timer(id) => Ok(id), _ => Err(ReturnCode::InvalidIdentifier)}
+    }
+    pub fn check_process(&self, handle: Handle, rights: HandleRights) -> Result<ProcessID, ReturnCode> {
+        match self.check(handle, rights)?.object {KernelObject::Process(id) => Ok(id), _ => Err(ReturnCode::InvalidIdentifier)}
+    }
+
+    //Close a handle
+    pub fn close(&mut self, handle: Handle) -> Result<HandleEntry, ReturnCode> {
+        self.remove(handle.0 as usize)
+    }
 }
 
 
 //RELATIONAL
 //Child Process
 #[repr(C)]
-struct ChildProcess {
-    parent: ProcessID,
-    child: ProcessID,
+pub struct ChildProcess {
+    pub parent: ProcessID,
+    pub child: ProcessID,
 }
 
 //Child Thread
 #[repr(C)]
-struct ChildThread {
-    process: ProcessID,
-    thread: ThreadID,
+pub struct ChildThread {
+    pub process: ProcessID,
+    pub thread: ThreadID,
+}
+
+//Process Memory
+#[repr(C)]
+pub struct ProcessMemory {
+    pub process: ProcessID,
+    pub start: LinearAddress,
+    pub end: LinearAddress,
+}
+
+//Port Mapping (an area where a process has mapped a memory port)
+#[repr(C)]
+pub struct PortMapping {
+    pub process: ProcessID,
+    pub port: MemoryPortID,
+    pub start: LinearAddress,
+    pub end: LinearAddress,
 }
 
 //Signaling Thread
 #[repr(C)]
-struct SignalingThread {
-    process: ProcessID,
-    thread: ThreadID,
+pub struct SignalingThread {
+    pub process: ProcessID,
+    pub thread: ThreadID,
+}
+
+//I/O Port Grant (a range of ports a process may access from ring 3)
+#[repr(C)]
+pub struct IoPortGrant {
+    pub process: ProcessID,
+    pub first: u16,
+    pub count: u32,
 }
 
 //Attached Ports (Read)
@@ -87,11 +369,18 @@ struct AttachedPortWrite {
     port: PortID,
 }
 
-//Director Process
+//Director Process (the role stays reserved once the director exits, so no later process takes over its port or privileges)
 #[repr(C)]
-struct DirectorProcess {
-    port: PortID,
-    process: ProcessID,
+pub struct DirectorProcess {
+    pub port: PortID,
+    pub process: ProcessID,
+    pub exited: bool,
+}
+impl DirectorProcess {
+    //Test whether a process is the running director
+    pub fn is(&self, process: ProcessID) -> bool {
+        !self.exited && self.process == process
+    }
 }
 
 //EXECUTION CONTEXTS
@@ -112,4 +401,372 @@ struct ExecutionLock {
 //Interrupt
 struct ExecutionInterrupt {
     thread: ThreadID,
-}
\ No newline at end of file
+}
+
+
+// KERNEL TABLES
+//Base structures
+pub static mut PROCESSES:    Table<Process,     MAX_PROCESSES>    = Table::new();
+pub static mut THREADS:      Table<Thread,      MAX_THREADS>      = Table::new();
+pub static mut PORTS:        Table<MessagePort, MAX_PORTS>        = Table::new();
+pub static mut MEMORY_PORTS: Table<MemPort,     MAX_MEMORY_PORTS> = Table::new();
+pub static mut TIMERS:       Table<Timer,       MAX_TIMERS>       = Table::new();
+
+//Relations
+pub static mut CHILD_PROCESSES: Table<ChildProcess, MAX_PROCESSES> = Table::new();
+pub static mut CHILD_THREADS:   Table<ChildThread,  MAX_THREADS>   = Table::new();
+pub static mut PROCESS_MEMORY:  Table<ProcessMemory, MAX_MEMORY_AREAS> = Table::new();
+pub static mut PORT_MAPPINGS:   Table<PortMapping,   MAX_PORT_MAPPINGS> = Table::new();
+pub static mut SIGNALING_THREADS: Table<SignalingThread, MAX_PROCESSES> = Table::new();
+pub static mut DIRECTOR: Option<DirectorProcess> = None;
+pub static mut IO_PORT_GRANTS: Table<IoPortGrant, MAX_IO_GRANTS> = Table::new();
+pub static mut IRQ_BINDINGS: [Option<PortID>; ISA_IRQS] = [None; ISA_IRQS];
+pub static mut IRQ_RELEASED: Option<unsafe fn(u8)> = None; //CALLED WITH EACH IRQ LINE WHOSE BINDING IS RELEASED
+
+//Address spaces
+pub static mut PCIDS: PcidCache<MAX_PCIDS> = PcidCache::new();
+
+//Create a process, recording it as a child of its parent if it has one
+pub unsafe fn create_process(page_map_address: PhysicalAddress, parent: Option<ProcessID>) -> Result<ProcessID, ReturnCode> {
+    let process = ProcessID(PROCESSES.insert(Process {page_map_address, handles: HandleTable::new(), exit_code: None, signals_pending: 0, signals_mask: 0})? as u64);
+    if let Some(parent) = parent {
+        if let Err(error) = CHILD_PROCESSES.insert(ChildProcess {parent, child: process}) {
+            PROCESSES.remove(process.0 as usize);
+            return Err(error);
+        }
+        if let Err(error) = PROCESSES.get_mut(parent.0 as usize)?.handles.open(KernelObject::Process(process), HandleRights::ALL) {
+            let relation = CHILD_PROCESSES.iter().find(|(_, relation)| relation.child == process).map(|(index, _)| index);
+            if let Some(index) = relation {CHILD_PROCESSES.remove(index);}
+            PROCESSES.remove(process.0 as usize);
+            return Err(error);
+        }
+        //The first process to be started by another becomes the director, later processes are given a port to it
+        if let Err(error) = open_bootstrap(process) {
+            exit_process_handles(process);
+            let relation = CHILD_PROCESSES.iter().find(|(_, relation)| relation.child == process).map(|(index, _)| index);
+            if let Some(index) = relation {CHILD_PROCESSES.remove(index);}
+            PROCESSES.remove(process.0 as usize);
+            revoke_object(KernelObject::Process(process));
+            return Err(error);
+        }
+    }
+    Ok(process)
+}
+
+//Give a new process its bootstrap handle, making it the director if there is none (and failing once the director has exited)
+unsafe fn open_bootstrap(process: ProcessID) -> Result<(), ReturnCode> {
+    match &DIRECTOR {
+        Some(director) if director.exited => Err(ReturnCode::NotReady),
+        Some(director) => {
+            let port = director.port;
+            PROCESSES.get_mut(process.0 as usize)?.handles.open_at(BOOTSTRAP_HANDLE, KernelObject::Port(port), SERVICE_RIGHTS)
+        },
+        None => {
+            let port = PortID(PORTS.insert(MessagePort::new())? as u64);
+            if let Err(error) = PROCESSES.get_mut(process.0 as usize)?.handles.open_at(BOOTSTRAP_HANDLE, KernelObject::Port(port), HandleRights::ALL) {
+                PORTS.remove(port.0 as usize);
+                return Err(error);
+            }
+            DIRECTOR = Some(DirectorProcess {port, process, exited: false});
+            Ok(())
+        },
+    }
+}
+
+//Attach a thread to the process that owns it, attaching nothing if it fails
+pub unsafe fn attach_thread(process: ProcessID, thread: ThreadID, kernel_stack_start: LinearAddress, kernel_stack_end: LinearAddress, priority: u8) -> Result<(), ReturnCode> {
+    PROCESSES.get(process.0 as usize)?;
+    THREADS.insert_at(thread.0 as usize, Thread {kernel_stack_start, kernel_stack_end, state: ThreadState::Ready, priority, user_cycles: 0, kernel_cycles: 0, switches: 0, wakeups: 0})?;
+    if let Err(error) = CHILD_THREADS.insert(ChildThread {process, thread}) {
+        THREADS.remove(thread.0 as usize);
+        return Err(error);
+    }
+    if let Err(error) = PROCESSES.get_mut(process.0 as usize)?.handles.open(KernelObject::Thread(thread), HandleRights::ALL) {
+        let relation = CHILD_THREADS.iter().find(|(_, relation)| relation.thread == thread).map(|(index, _)| index);
+        if let Some(index) = relation {CHILD_THREADS.remove(index);}
+        THREADS.remove(thread.0 as usize);
+        return Err(error);
+    }
+    Ok(())
+}
+
+//Find the process which owns a thread
+pub unsafe fn thread_process(thread: ThreadID) -> Result<ProcessID, ReturnCode> {
+    CHILD_THREADS.iter().find(|(_, relation)| relation.thread == thread).map(|(_, relation)| relation.process).ok_or(ReturnCode::InvalidIdentifier)
+}
+
+//Access a process
+pub unsafe fn process(process: ProcessID) -> Result<&'static mut Process, ReturnCode> {
+    PROCESSES.get_mut(process.0 as usize)
+}
+
+//Record an area of memory as owned by a process, so that it is unmapped when the process exits
+pub unsafe fn attach_memory(process: ProcessID, start: LinearAddress, end: LinearAddress) -> Result<(), ReturnCode> {
+    PROCESSES.get(process.0 as usize)?;
+    PROCESS_MEMORY.insert(ProcessMemory {process, start, end})?;
+    Ok(())
+}
+
+//Record an area where a process has mapped a memory port, so that it is unmapped when the process exits and the port outlives it
+pub unsafe fn attach_port_mapping(process: ProcessID, port: MemoryPortID, start: LinearAddress, end: LinearAddress) -> Result<(), ReturnCode> {
+    PROCESSES.get(process.0 as usize)?;
+    MEMORY_PORTS.get(port.0 as usize)?;
+    PORT_MAPPINGS.insert(PortMapping {process, port, start, end})?;
+    Ok(())
+}
+
+//Test whether a range of memory lies wholly within areas owned by a process
+pub unsafe fn owns_memory(process: ProcessID, start: LinearAddress, end: LinearAddress) -> bool {
+    let mut covered = start.0;
+    while covered < end.0 {
+        match PROCESS_MEMORY.iter().find(|(_, area)| area.process == process && area.start.0 <= covered && covered < area.end.0) {
+            Some((_, area)) => covered = area.end.0,
+            None => return false,
+        }
+    }
+    true
+}
+
+
+// PROCESS LIFETIME
+//Invalidator for TLB entries of a process's page map, which may not be the active one (INVLPG only reaches the active PCID, so without INVPCID its PCID is given up instead)
+pub unsafe fn target_page_map(process: ProcessID) -> Result<PageInvalidator, ReturnCode> {
+    let page_map = PROCESSES.get(process.0 as usize)?.page_map_address;
+    if page_map.0 == read_cr3_address().0 || !pcid_enabled() {return Ok(PageInvalidator::ACTIVE)}
+    match PCIDS.find(page_map) {
+        Some(pcid) if invpcid_check() => Ok(PageInvalidator {pcid: Some(pcid)}),
+        Some(_) => {PCIDS.release(page_map); Ok(PageInvalidator::ACTIVE)},
+        None => Ok(PageInvalidator::ACTIVE),
+    }
+}
+
+//Exit a process, releasing everything it owns except the kernel stacks of its threads, which are released when it is reaped
+pub unsafe fn exit_process(process: ProcessID, code: u64, map: PageMap, unmap: &mut dyn PageOperation, unmap_port: &mut dyn PageOperation) -> Result<(), ReturnCode> {
+    end_process(process, code, SIGNAL_CHILD_EXIT, map, unmap, unmap_port)
+}
+
+//Terminate a process after a processor fault, notifying its parent
+pub unsafe fn fault_process(process: ProcessID, map: PageMap, unmap: &mut dyn PageOperation, unmap_port: &mut dyn PageOperation) -> Result<(), ReturnCode> {
+    end_process(process, EXIT_CODE_FAULT, SIGNAL_CHILD_FAULT, map, unmap, unmap_port)
+}
+
+//End a process with an exit code, posting one signal to its parent to say how it ended (memory is unmapped with one operation, memory ports with another that leaves their frames alone, both invalidating through the process's page map)
+unsafe fn end_process(process: ProcessID, code: u64, signal: u64, map: PageMap, unmap: &mut dyn PageOperation, unmap_port: &mut dyn PageOperation) -> Result<(), ReturnCode> {
+    if PROCESSES.get(process.0 as usize)?.exit_code.is_some() {return Err(ReturnCode::InvalidIdentifier)}
+    //Stop threads
+    for (_, relation) in CHILD_THREADS.iter() {
+        if relation.process == process {THREADS.get_mut(relation.thread.0 as usize)?.state = ThreadState::Exited;}
+    }
+    //Unmap memory
+    for index in 0..MAX_MEMORY_AREAS {
+        if PROCESS_MEMORY.get(index).map(|area| area.process == process) == Ok(true) {
+            let area = PROCESS_MEMORY.remove(index)?;
+            virtual_memory_editor(map, unmap, area.start, area.end)?;
+        }
+    }
+    //Unmap memory ports, destroying any whose last handle has already been closed
+    for index in 0..MAX_PORT_MAPPINGS {
+        if PORT_MAPPINGS.get(index).map(|mapping| mapping.process == process) == Ok(true) {
+            let mapping = PORT_MAPPINGS.remove(index)?;
+            virtual_memory_editor(map, unmap_port, mapping.start, mapping.end)?;
+            release_object(KernelObject::MemoryPort(mapping.port));
+        }
+    }
+    //Close handles
+    exit_process_handles(process);
+    //Give children to the kernel process
+    for index in 0..MAX_PROCESSES {
+        if let Ok(relation) = CHILD_PROCESSES.get_mut(index) {
+            if relation.parent == process {relation.parent = KERNEL_PROCESS;}
+        }
+    }
+    //Stop directing, leaving the bootstrap port held by other processes without a receiver and new processes without a director
+    if let Some(director) = DIRECTOR.as_mut().filter(|director| director.is(process)) {director.exited = true;}
+    //Give up I/O ports
+    for index in 0..MAX_IO_GRANTS {
+        if IO_PORT_GRANTS.get(index).map(|grant| grant.process == process) == Ok(true) {IO_PORT_GRANTS.remove(index)?;}
+    }
+    //Stop receiving signals
+    for index in 0..MAX_PROCESSES {
+        if SIGNALING_THREADS.get(index).map(|relation| relation.process == process) == Ok(true) {SIGNALING_THREADS.remove(index)?;}
+    }
+    //Record exit code and wake threads waiting on the process
+    PROCESSES.get_mut(process.0 as usize)?.exit_code = Some(code);
+    for index in 0..MAX_THREADS {
+        if let Ok(thread) = THREADS.get_mut(index) {
+            if thread.state == ThreadState::WaitProcess(process) {thread.wake();}
+        }
+    }
+    //Notify parent
+    if let Ok(parent) = process_parent(process) {post_signal(parent, signal);}
+    Ok(())
+}
+
+//Close every handle held by a process
+unsafe fn exit_process_handles(process: ProcessID) {
+    for index in 0..MAX_HANDLES {
+        let entry = PROCESSES.get_mut(process.0 as usize).and_then(|target| target.handles.remove(index));
+        if let Ok(entry) = entry {release_object(entry.object);}
+    }
+}
+
+//Remove an exited process, freeing the kernel stacks of its threads and returning its exit code
+pub unsafe fn reap_process(process: ProcessID, map: PageMap, unmap: &mut dyn PageOperation) -> Result<u64, ReturnCode> {
+    let code = PROCESSES.get(process.0 as usize)?.exit_code.ok_or(ReturnCode::NotReady)?;
+    //Free threads
+    for index in 0..MAX_THREADS {
+        if let Ok(relation) = CHILD_THREADS.get(index) {
+            if relation.process == process {
+                let thread = relation.thread;
+                CHILD_THREADS.remove(index)?;
+                revoke_object(KernelObject::Thread(thread));
+                let thread = THREADS.remove(thread.0 as usize)?;
+                virtual_memory_editor(map, unmap, thread.kernel_stack_start, thread.kernel_stack_end)?;
+            }
+        }
+    }
+    //Remove process
+    for index in 0..MAX_PROCESSES {
+        if CHILD_PROCESSES.get(index).map(|relation| relation.child == process) == Ok(true) {CHILD_PROCESSES.remove(index)?;}
+    }
+    let page_map = PROCESSES.remove(process.0 as usize)?.page_map_address;
+    revoke_object(KernelObject::Process(process));
+    //Give up the PCID of a page map no other process uses
+    if !PROCESSES.iter().any(|(_, other)| other.page_map_address.0 == page_map.0) {PCIDS.release(page_map);}
+    Ok(code)
+}
+
+//Find the parent of a process
+pub unsafe fn process_parent(process: ProcessID) -> Result<ProcessID, ReturnCode> {
+    CHILD_PROCESSES.iter().find(|(_, relation)| relation.child == process).map(|(_, relation)| relation.parent).ok_or(ReturnCode::NotFound)
+}
+
+//Remove every handle to a kernel object which no longer exists
+pub unsafe fn revoke_object(object: KernelObject) {
+    for process in 0..MAX_PROCESSES {
+        if let Ok(process) = PROCESSES.get_mut(process) {
+            for index in 0..MAX_HANDLES {
+                if process.handles.get(index).map(|entry| entry.object == object) == Ok(true) {process.handles.remove(index);}
+            }
+        }
+    }
+    for port in 0..MAX_PORTS {
+        if let Ok(port) = PORTS.get_mut(port) {
+            for message in port.queue.iter_mut().flatten() {
+                if message.handle.map(|entry| entry.object) == Some(object) {message.handle = None;}
+            }
+        }
+    }
+}
+
+//Destroy a kernel object if no handle to it remains in any process or port, and no process has it mapped
+pub unsafe fn release_object(object: KernelObject) {
+    let held = PROCESSES.iter().any(|(_, process)| process.handles.iter().any(|(_, entry)| entry.object == object));
+    let queued = PORTS.iter().any(|(_, port)| port.queue.iter().flatten().any(|message| message.handle.map(|entry| entry.object) == Some(object)));
+    let mapped = PORT_MAPPINGS.iter().any(|(_, mapping)| KernelObject::MemoryPort(mapping.port) == object);
+    if held || queued || mapped {return}
+    match object {
+        KernelObject::Port(id) => {
+            //IRQ lines bound to a destroyed port are handed back to the kernel
+            for (irq, binding) in IRQ_BINDINGS.iter_mut().enumerate() {
+                if *binding == Some(id) {
+                    *binding = None;
+                    if let Some(released) = IRQ_RELEASED {released(irq as u8);}
+                }
+            }
+            //handles waiting in a destroyed port are released with it
+            if let Ok(mut port) = PORTS.remove(id.0 as usize) {
+                while let Ok(message) = port.pop() {
+                    if let Some(entry) = message.handle {release_object(entry.object);}
+                }
+            }
+        },
+        KernelObject::MemoryPort(id) => {MEMORY_PORTS.remove(id.0 as usize);},
+        KernelObject::Timer(id)      => {TIMERS.remove(id.0 as usize);},
+        KernelObject::Thread(_)      => {},
+        KernelObject::Process(_)     => {},
+    }
+}
+
+
+// SIGNALS
+//Post a signal to a process, waking its signal thread if the signal is not masked
+pub unsafe fn post_signal(process: ProcessID, signal: u64) -> Result<(), ReturnCode> {
+    if signal >= SIGNAL_COUNT {return Err(ReturnCode::InvalidIdentifier)}
+    let target = PROCESSES.get_mut(process.0 as usize)?;
+    if target.exit_code.is_some() {return Err(ReturnCode::InvalidIdentifier)}
+    target.signals_pending |= signal_bit(signal);
+    if target.signals_mask & signal_bit(signal) == 0 {
+        if let Ok(thread) = signal_thread(process) {
+            let thread = THREADS.get_mut(thread.0 as usize)?;
+            if thread.state == ThreadState::WaitSignal {thread.wake();}
+        }
+    }
+    Ok(())
+}
+
+//Take the unmasked signals pending for a process
+pub unsafe fn take_signals(process: ProcessID) -> Result<u64, ReturnCode> {
+    let target = PROCESSES.get_mut(process.0 as usize)?;
+    let signals = target.signals_pending & !target.signals_mask;
+    target.signals_pending &= !signals;
+    Ok(signals)
+}
+
+//Find the thread designated to receive signals for a process
+pub unsafe fn signal_thread(process: ProcessID) -> Result<ThreadID, ReturnCode> {
+    SIGNALING_THREADS.iter().find(|(_, relation)| relation.process == process).map(|(_, relation)| relation.thread).ok_or(ReturnCode::NotFound)
+}
+
+//Designate the thread which receives signals for a process
+pub unsafe fn set_signal_thread(process: ProcessID, thread: ThreadID) -> Result<(), ReturnCode> {
+    if thread_process(thread)? != process {return Err(ReturnCode::AccessDenied)}
+    for index in 0..MAX_PROCESSES {
+        if SIGNALING_THREADS.get(index).map(|relation| relation.process == process) == Ok(true) {SIGNALING_THREADS.remove(index)?;}
+    }
+    SIGNALING_THREADS.insert(SignalingThread {process, thread})?;
+    Ok(())
+}
+
+//Post timer signals for every timer expiring between two times, inclusive
+pub unsafe fn expire_timers(from: u64, until: u64) {
+    for index in 0..MAX_TIMERS {
+        if let Ok(timer) = TIMERS.get(index) {
+            if timer.next_expiry(from) <= until {
+                let process = timer.process;
+                post_signal(process, SIGNAL_TIMER);
+            }
+        }
+    }
+}
+
+//Find the first tick at or after a given time at which any timer expires
+pub unsafe fn next_timer_expiry(from: u64) -> Option<u64> {
+    TIMERS.iter().map(|(_, timer)| timer.next_expiry(from)).min()
+}
+
+//Find the first tick at or after a given time which leaves a remainder when divided
+pub fn next_occurrence(from: u64, divisor: u64, remainder: u64) -> u64 {
+    let phase = from % divisor;
+    if phase <= remainder {from.saturating_add(remainder - phase)}
+    else {from.saturating_add(divisor - phase).saturating_add(remainder)}
+}
+
+
+// FUTEXES
+//Wake up to a number of threads waiting on a futex address, returning how many were woken
+pub unsafe fn wake_futex(address: usize, count: u64) -> u64 {
+    let mut woken = 0;
+    for index in 0..MAX_THREADS {
+        if woken >= count {break}
+        if let Ok(thread) = THREADS.get_mut(index) {
+            if matches!(thread.state, ThreadState::WaitFutex(waiting, _) if waiting == address) {thread.wake(); woken += 1;}
+        }
+    }
+    woken
+}
+
+//Find the first tick at or after a given time at which any futex wait times out (waits already timed out resume on their thread's next turn)
+pub unsafe fn next_futex_deadline(from: u64) -> Option<u64> {
+    THREADS.iter().filter_map(|(_, thread)| match thread.state {ThreadState::WaitFutex(_, deadline) => deadline, _ => None}).min().map(|deadline| deadline.max(from))
+}
@@ -0,0 +1,381 @@
+// HELIUM: KERNEL TESTS
+// Test runner for tests which must run inside the kernel, reporting over serial and exiting QEMU through isa-debug-exit
+//
+// QEMU must be started with: -device isa-debug-exit,iobase=0xF4,iosize=0x04 -serial stdio
+
+
+// HEADER
+//Imports
+use crate::cmdline::*;
+use crate::gdt;
+use crate::kstruct::*;
+use crate::manifest::*;
+use gluon::noble::address_space::*;
+use gluon::noble::data_type::DataType;
+use gluon::noble::handle::*;
+use gluon::noble::return_code::ReturnCode;
+use gluon::pc::fat::fat_timestamp;
+use gluon::pc::pic;
+use gluon::pc::ports::*;
+use gluon::pc::rtc;
+use gluon::pc::rtc::DateTime;
+use gluon::pc::serial::*;
+use gluon::x86_64::lapic;
+use gluon::x86_64::msr::IA32_PAT;
+use gluon::x86_64::page_operations::*;
+use gluon::x86_64::paging::*;
+use gluon::x86_64::port::*;
+use gluon::x86_64::registers::*;
+use gluon::x86_64::segmentation::*;
+use core::arch::asm;
+use core::fmt::Write;
+use core::panic::PanicInfo;
+
+
+// QEMU EXIT
+//Exit Codes (QEMU exits with status (code << 1) | 1)
+#[derive(Clone, Copy, Debug)]
+#[repr(u32)]
+pub enum QemuExitCode {
+    Success = 0x10, //Status 0x21
+    Failed  = 0x11, //Status 0x23
+}
+
+//Exit QEMU, halting if the debug exit device is not present
+pub fn exit_qemu(code: QemuExitCode) -> ! {
+    unsafe {DEBUG_EXIT.write(code as u32);}
+    loop {gluon::x86_64::instructions::hlt();}
+}
+
+
+// TEST RUNNER
+//Serial port used for test results
+static mut TEST_SERIAL: SerialPort = SerialPort::new(0x03F8);
+
+//Test
+pub trait Testable {
+    fn run(&self);
+}
+impl<T> Testable for T where T: Fn() {
+    fn run(&self) {
+        unsafe {
+            write!(TEST_SERIAL, "{}... ", core::any::type_name::<T>());
+            self();
+            writeln!(TEST_SERIAL, "[ok]");
+        }
+    }
+}
+
+//Run all tests and exit QEMU
+pub fn runner(tests: &[&dyn Testable]) {
+    unsafe {
+        TEST_SERIAL.init(UART_FREQUENCY);
+        writeln!(TEST_SERIAL, "\nRunning {} kernel tests", tests.len());
+    }
+    for test in tests {test.run();}
+    unsafe {writeln!(TEST_SERIAL, "All kernel tests passed");}
+    exit_qemu(QemuExitCode::Success);
+}
+
+//Report a failed test and exit QEMU
+pub fn panic(panic_info: &PanicInfo) -> ! {
+    unsafe {
+        writeln!(TEST_SERIAL, "[failed]");
+        writeln!(TEST_SERIAL, "{}", panic_info);
+    }
+    exit_qemu(QemuExitCode::Failed);
+}
+
+
+// GLUON TESTS
+//Paging: entries survive conversion to and from their raw form
+#[test_case]
+fn page_map_entry_round_trip() {
+    let entry = PageMapEntry::new(PageMapLevel::L1, PageMapEntryType::Memory, PhysicalAddress(0x0012_3000), true, true, false, true).unwrap();
+    let raw = entry.to_u64().unwrap();
+    let read = PageMapEntry::from_u64(raw, PageMapLevel::L1).unwrap();
+    assert_eq!(read.to_u64().unwrap(), raw);
+    assert_eq!(read.physical.0, 0x0012_3000);
+}
+
+//Paging: large pages are read back as memory and must be aligned to their size
+#[test_case]
+fn page_map_entry_large() {
+    let entry = PageMapEntry::new(PageMapLevel::L2, PageMapEntryType::Memory, PhysicalAddress(0x0040_0000), true, true, false, true).unwrap();
+    let read = PageMapEntry::from_u64(entry.to_u64().unwrap(), PageMapLevel::L2).unwrap();
+    assert_eq!(read.entry_type, PageMapEntryType::Memory);
+    assert_eq!(read.physical.0, 0x0040_0000);
+    assert!(PageMapEntry::new(PageMapLevel::L2, PageMapEntryType::Memory, PhysicalAddress(0x0012_3000), true, true, false, true).is_err());
+}
+
+//Paging: addresses are split into table indices
+#[test_case]
+fn page_map_indices() {
+    let address = LinearAddress(oct_to_usize_4(0o123, 0o456, 0o701, 0o234, 0o5670).unwrap());
+    assert_eq!(extract_index(address, PageMapLevel::L4), 0o123);
+    assert_eq!(extract_index(address, PageMapLevel::L3), 0o456);
+    assert_eq!(extract_index(address, PageMapLevel::L2), 0o701);
+    assert_eq!(extract_index(address, PageMapLevel::L1), 0o234);
+    assert_eq!(canonical_48(LinearAddress(0x0000_8000_0000_0000)), Err(ReturnCode::NonCanonicalAddress));
+}
+
+//Paging: 5-level addresses are built and checked with 57-bit sign extension
+#[test_case]
+fn page_map_la57_addresses() {
+    let address = LinearAddress(oct_to_usize_5(0o400, 0o001, 0, 0, 0, 0).unwrap());
+    assert_eq!(address.0, 0xFF00_0080_0000_0000);
+    assert_eq!(extract_index(address, PageMapLevel::L5), 0o400);
+    assert_eq!(extract_index(address, PageMapLevel::L4), 0o001);
+    assert_eq!(canonical(address, PageMapLevel::L5), Ok(()));
+    assert_eq!(canonical(address, PageMapLevel::L4), Err(ReturnCode::NonCanonicalAddress));
+    assert_eq!(canonical_57(LinearAddress(0x0100_0000_0000_0000)), Err(ReturnCode::NonCanonicalAddress));
+    if la57_enabled() {assert!(la57_check());}
+    //The active page map is edited and walked at a 57-bit address (the physical memory mapping when 5-level paging is in use), which a 4-level root refuses
+    let translator = |physical: PhysicalAddress| Ok::<_, ReturnCode>(LinearAddress(physical.0 + physical_memory_ptr(root_level())));
+    let map = PageMap::new(translator(read_cr3_address()).unwrap(), root_level()).unwrap();
+    let start = LinearAddress(PHYSICAL_MEMORY_57 + 0x1000);
+    let mut check = CheckUserAccess {translator: &OffsetIdentity {offset: physical_memory_ptr(root_level()), limit: PAGE_SIZE_512G}, write: false};
+    match root_level() {
+        PageMapLevel::L5 => {
+            let mapping = translate_address(map, start, &translator).unwrap();
+            assert_eq!(mapping.physical.0, 0x1000);
+            assert_eq!(virtual_memory_editor(map, &mut check, start, start.add(PAGE_SIZE_4KIB)).is_ok(), mapping.flags.user);
+        },
+        _ => {
+            assert_eq!(translate_address(map, start, &translator).err(), Some(ReturnCode::NonCanonicalAddress));
+            assert_eq!(virtual_memory_editor(map, &mut check, start, start.add(PAGE_SIZE_4KIB)), Err(ReturnCode::NonCanonicalAddress));
+        },
+    }
+}
+
+//Paging: cache modes survive encoding at every level which holds pages, and the attribute table is programmed at boot where supported
+#[test_case]
+fn page_cache_modes() {
+    for mode in PAT_LAYOUT {
+        for level in [PageMapLevel::L1, PageMapLevel::L2, PageMapLevel::L3] {
+            let mut entry = PageMapEntry::new(level, PageMapEntryType::Memory, PhysicalAddress(0), true, true, false, true).unwrap();
+            entry.set_cache_mode(mode).unwrap();
+            assert_eq!(PageMapEntry::from_u64(entry.to_u64().unwrap(), level).unwrap().cache_mode(), mode);
+        }
+    }
+    let mut table = PageMapEntry::new(PageMapLevel::L4, PageMapEntryType::Table, PhysicalAddress(0), true, true, false, false).unwrap();
+    assert_eq!(table.set_cache_mode(CacheMode::WriteCombining), Err(ReturnCode::InvalidData));
+    if pat_check() {assert_eq!(IA32_PAT.read(), pat_value());}
+}
+
+//Paging: the active page map is readable
+#[test_case]
+fn page_map_active() {
+    let pml4_physical = read_cr3_address();
+    assert_eq!(pml4_physical.0 % PAGE_SIZE_4KIB, 0);
+}
+
+//Paging: the walker agrees with the translation of a mapped address
+#[test_case]
+fn page_map_walker() {
+    let translator = |physical: PhysicalAddress| Ok::<_, ReturnCode>(LinearAddress(physical.0 + physical_memory_ptr(root_level())));
+    let map = PageMap::new(translator(read_cr3_address()).unwrap(), root_level()).unwrap();
+    let address = LinearAddress(page_map_walker as fn() as usize);
+    let mapping = translate_address(map, address, &translator).unwrap();
+    assert!(!mapping.flags.execute_disable);
+    let region = PageWalker::new(map, &translator).regions().find(|region| region.linear.0 <= address.0 && address.0 < region.linear.0 + region.size).unwrap();
+    assert_eq!(region.flags, mapping.flags);
+    assert_eq!(translate_address(map, LinearAddress(0), &translator).err(), Some(ReturnCode::NotPresent));
+}
+
+//Segmentation: the kernel runs in the supervisor code segment
+#[test_case]
+fn segment_selectors() {
+    let cs: u16;
+    unsafe {asm!("MOV {:x}, CS", out(reg) cs);}
+    assert_eq!(cs, u16::from(gdt::SUPERVISOR_CODE));
+    let selector = SegmentSelector::from(cs);
+    assert!(matches!(selector.requested_privilege_level, PrivilegeLevel::Supervisor));
+}
+
+//PIC: masks can be written and read back
+#[test_case]
+fn pic_mask() {
+    unsafe {
+        let mask_1 = PIC1_DATA.read();
+        let mask_2 = PIC2_DATA.read();
+        pic::set_mask(0xA5, 0x5A);
+        assert_eq!((PIC1_DATA.read(), PIC2_DATA.read()), (0xA5, 0x5A));
+        pic::set_mask(mask_1, mask_2);
+    }
+}
+
+//LAPIC: the timer counts down
+#[test_case]
+fn lapic_timer() {
+    unsafe {
+        assert!(lapic::apic_check());
+        lapic::timer(0x30, true, lapic::TimerMode::OneShot);
+        lapic::initial_count(u32::MAX);
+        for _ in 0..1000 {core::hint::spin_loop();}
+        assert!(lapic::current_count() < u32::MAX);
+        lapic::initial_count(0);
+        lapic::timer(0x30, false, lapic::TimerMode::Periodic);
+    }
+}
+
+
+//RTC: dates convert to and from Unix time, and to FAT timestamps
+#[test_case]
+fn rtc_unix_time() {
+    let date_time = DateTime {year: 2024, month: 2, day: 29, hour: 13, minute: 5, second: 9};
+    assert_eq!(date_time.unix_time(), 1_709_211_909);
+    assert_eq!(DateTime::from_unix_time(1_709_211_909), Ok(date_time));
+    assert_eq!(DateTime::from_unix_time(0).unwrap().year, 1970);
+    assert_eq!(rtc::bcd_to_binary(0x59), Ok(59));
+    assert!(rtc::bcd_to_binary(0x5A).is_err());
+    assert!(DateTime {year: 2023, month: 2, day: 29, hour: 0, minute: 0, second: 0}.validate().is_err());
+    assert_eq!(fat_timestamp(date_time), (100, 13 << 11 | 5 << 5 | 4, 44 << 9 | 2 << 5 | 29));
+    assert_eq!(fat_timestamp(DateTime::from_unix_time(0).unwrap()), (0, 0, 0));
+}
+
+// HELIUM TESTS
+//Kernel structures: table slots are reused after removal
+#[test_case]
+fn table_slots() {
+    let mut table: Table<u64, 2> = Table::new();
+    assert_eq!(table.insert(10), Ok(0));
+    assert_eq!(table.insert(11), Ok(1));
+    assert_eq!(table.insert(12), Err(ReturnCode::OutOfResources));
+    assert_eq!(table.remove(0), Ok(10));
+    assert_eq!(table.get(0), Err(ReturnCode::InvalidIdentifier));
+    assert_eq!(table.insert(13), Ok(0));
+    assert_eq!(table.iter().count(), 2);
+}
+
+//Kernel structures: PCIDs are kept by their page map and recycled least recently used first
+#[test_case]
+fn pcid_cache_lru() {
+    let mut pcids: PcidCache<2> = PcidCache::new();
+    assert_eq!(pcids.assign(PhysicalAddress(0x1000)), (1, true));
+    assert_eq!(pcids.assign(PhysicalAddress(0x2000)), (2, true));
+    assert_eq!(pcids.assign(PhysicalAddress(0x1000)), (1, false));
+    assert_eq!(pcids.assign(PhysicalAddress(0x3000)), (2, true));
+    assert_eq!(pcids.find(PhysicalAddress(0x2000)), None);
+    pcids.release(PhysicalAddress(0x1000));
+    assert_eq!(pcids.assign(PhysicalAddress(0x1000)), (1, true));
+}
+
+//Kernel structures: handles are checked against their rights and object type
+#[test_case]
+fn handle_rights() {
+    let mut handles = HandleTable::new();
+    let handle = handles.open(KernelObject::Port(PortID(3)), HandleRights::READ | HandleRights::WRITE).unwrap();
+    assert_eq!(handles.check_port(handle, HandleRights::WRITE), Ok(PortID(3)));
+    assert_eq!(handles.check_port(handle, HandleRights::TRANSFER), Err(ReturnCode::AccessDenied));
+    assert!(handles.check_timer(handle, HandleRights::NONE).is_err());
+    handles.close(handle).unwrap();
+    assert!(handles.check_port(handle, HandleRights::NONE).is_err());
+}
+
+//Kernel structures: timers expire on the ticks matching their divisor and remainder
+#[test_case]
+fn timer_expiry() {
+    let timer = Timer {divisor: 10, remainder: 3, process: ProcessID(0)};
+    assert_eq!(timer.next_expiry(0), 3);
+    assert_eq!(timer.next_expiry(3), 3);
+    assert_eq!(timer.next_expiry(4), 13);
+    assert_eq!(next_occurrence(u64::MAX - 1, 10, 9), u64::MAX);
+}
+
+//Kernel structures: futex waiters are woken by address and time out at the earliest deadline, never one already passed
+#[test_case]
+fn futex_wait_wake() {
+    let waiting = |address: usize, deadline: Option<u64>| Thread {kernel_stack_start: LinearAddress(0), kernel_stack_end: LinearAddress(0), state: ThreadState::WaitFutex(address, deadline), priority: 0, user_cycles: 0, kernel_cycles: 0, switches: 0, wakeups: 0};
+    let slots = [MAX_THREADS - 3, MAX_THREADS - 2, MAX_THREADS - 1];
+    unsafe {
+        THREADS.insert_at(slots[0], waiting(0x1000, Some(50))).unwrap();
+        THREADS.insert_at(slots[1], waiting(0x1000, None)).unwrap();
+        THREADS.insert_at(slots[2], waiting(0x2000, Some(20))).unwrap();
+        assert_eq!(next_futex_deadline(10), Some(20));
+        assert_eq!(next_futex_deadline(30), Some(30));
+        assert_eq!(wake_futex(0x1000, 1), 1);
+        assert_eq!(THREADS.get(slots[0]).unwrap().state, ThreadState::Ready);
+        assert_eq!(wake_futex(0x1000, u64::MAX), 1);
+        assert_eq!(wake_futex(0x3000, u64::MAX), 0);
+        assert_eq!(wake_futex(0x2000, u64::MAX), 1);
+        assert_eq!(next_futex_deadline(0), None);
+        for slot in slots {THREADS.remove(slot).unwrap();}
+    }
+}
+
+//Process memory: ownership covers ranges spanning adjacent areas, but not gaps or areas owned by another process
+#[test_case]
+fn memory_ownership() {
+    let (owner, other) = (ProcessID(0x7F01), ProcessID(0x7F02));
+    let area = |process: ProcessID, start: usize, end: usize| ProcessMemory {process, start: LinearAddress(start), end: LinearAddress(end)};
+    unsafe {
+        let areas = [
+            PROCESS_MEMORY.insert(area(owner, 0x10000, 0x12000)).unwrap(),
+            PROCESS_MEMORY.insert(area(owner, 0x12000, 0x14000)).unwrap(),
+            PROCESS_MEMORY.insert(area(other, 0x14000, 0x16000)).unwrap(),
+            PROCESS_MEMORY.insert(area(owner, 0x16000, 0x18000)).unwrap(),
+            PROCESS_MEMORY.insert(area(owner, 0x1A000, 0x1C000)).unwrap(),
+        ];
+        assert!(owns_memory(owner, LinearAddress(0x10000), LinearAddress(0x14000)));
+        assert!(owns_memory(owner, LinearAddress(0x11000), LinearAddress(0x13000)));
+        assert!(owns_memory(owner, LinearAddress(0x16000), LinearAddress(0x18000)));
+        assert!(!owns_memory(owner, LinearAddress(0x13000), LinearAddress(0x17000)));
+        assert!(!owns_memory(owner, LinearAddress(0x17000), LinearAddress(0x1B000)));
+        assert!(!owns_memory(owner, LinearAddress(0x16000), LinearAddress(0x18001)));
+        assert!(!owns_memory(owner, LinearAddress(0x0F000), LinearAddress(0x11000)));
+        assert!(owns_memory(other, LinearAddress(0x14000), LinearAddress(0x16000)));
+        assert!(!owns_memory(other, LinearAddress(0x13000), LinearAddress(0x15000)));
+        assert!(owns_memory(other, LinearAddress(0x20000), LinearAddress(0x20000)));
+        for index in areas {PROCESS_MEMORY.remove(index).unwrap();}
+    }
+}
+
+//Memory ports: a port with no handles left is kept while a process still has it mapped
+#[test_case]
+fn memory_port_mappings() {
+    unsafe {
+        let port = MemoryPortID(MEMORY_PORTS.insert(MemPort {address: PhysicalAddress(0x100000), level: PageMapLevel::L1, pages: 1, cache_mode: CacheMode::WriteBack, data_type: DataType::Binary}).unwrap() as u64);
+        let mapping = PORT_MAPPINGS.insert(PortMapping {process: ProcessID(0x7F01), port, start: LinearAddress(0x10000), end: LinearAddress(0x11000)}).unwrap();
+        release_object(KernelObject::MemoryPort(port));
+        assert!(MEMORY_PORTS.get(port.0 as usize).is_ok());
+        PORT_MAPPINGS.remove(mapping).unwrap();
+        release_object(KernelObject::MemoryPort(port));
+        assert!(MEMORY_PORTS.get(port.0 as usize).is_err());
+    }
+}
+
+//Boot manifest: entries are parsed and malformed manifests are rejected
+#[test_case]
+fn manifest_entries() {
+    let manifest = Manifest::new(b"init neon/x86-64.elf priority=2 --verbose\ndata fonts/f1.bin name=font\n").unwrap();
+    let init = manifest.find("boot:///noble/neon/x86-64.elf").unwrap();
+    assert_eq!(init.kind, ManifestKind::Init);
+    assert_eq!(init.priority, 2);
+    assert_eq!(init.arguments, "--verbose");
+    assert_eq!(manifest.find("boot:///noble/fonts/f1.bin").unwrap().name, "font");
+    assert!(manifest.find("boot:///noble/other.elf").is_none());
+    //Paths match whole components only
+    let manifest = Manifest::new(b"program b.elf priority=3\nprogram noble/web.elf\n").unwrap();
+    assert_eq!(manifest.find("boot:///noble/web.elf").unwrap().priority, 0);
+    assert_eq!(manifest.find("boot:///b.elf").unwrap().priority, 3);
+    assert!(path_matches("b.elf", "b.elf"));
+    assert!(!path_matches("boot:///noble/web.elf", "b.elf"));
+    assert!(Manifest::new(b"init a.elf\ninit b.elf\n").is_err());
+    assert!(Manifest::new(b"program a.elf priority=99\n").is_err());
+}
+
+//Command line: settings are applied and invalid settings are reported
+#[test_case]
+fn command_line_settings() {
+    let settings = Settings::parse("log=debug serial=on tick=250 init=neon/x86-64.elf");
+    assert_eq!(settings.log_level, LogLevel::Debug);
+    assert!(settings.serial);
+    assert_eq!(settings.tick_rate, 250);
+    assert_eq!(settings.init_path, Some("neon/x86-64.elf"));
+    let mut errors = Settings::errors("tick=5 colour=red serial");
+    assert_eq!(errors.next(), Some(("tick=5", ReturnCode::InvalidData)));
+    assert_eq!(errors.next(), Some(("colour=red", ReturnCode::NotFound)));
+    assert_eq!(errors.next(), Some(("serial", ReturnCode::InvalidData)));
+    assert_eq!(errors.next(), None);
+}
@@ -4,6 +4,10 @@
 //Imports
 use gluon::x86_64::paging::MIB;
 
+//Paging mode requested from Limine (5-level paging, where supported, only when built with the five_level feature)
+#[cfg(not(feature = "five_level"))] const PAGING_MODE: limine::paging::Mode = limine::paging::Mode::FOUR_LEVEL;
+#[cfg(feature = "five_level")]      const PAGING_MODE: limine::paging::Mode = limine::paging::Mode::FIVE_LEVEL;
+
 #[no_mangle] #[used(linker)] pub static LIMINE_REVISION    : limine::BaseRevision                   = limine::BaseRevision::new();
 #[no_mangle] #[used(linker)] pub static LIMINE_INFO        : limine::request::BootloaderInfoRequest = limine::request::BootloaderInfoRequest::new();
 #[no_mangle] #[used(linker)] pub static LIMINE_FRAMEBUFFER : limine::request::FramebufferRequest    = limine::request::FramebufferRequest::new();
@@ -11,3 +15,6 @@ use gluon::x86_64::paging::MIB;
 #[no_mangle] #[used(linker)] pub static LIMINE_MEMMAP      : limine::request::MemoryMapRequest      = limine::request::MemoryMapRequest::new();
 #[no_mangle] #[used(linker)] pub static LIMINE_HHDM        : limine::request::HhdmRequest           = limine::request::HhdmRequest::new();
 #[no_mangle] #[used(linker)] pub static LIMINE_MODULES     : limine::request::ModuleRequest         = limine::request::ModuleRequest::new();
+#[no_mangle] #[used(linker)] pub static LIMINE_KERNEL_FILE : limine::request::KernelFileRequest     = limine::request::KernelFileRequest::new();
+#[no_mangle] #[used(linker)] pub static LIMINE_RSDP        : limine::request::RsdpRequest           = limine::request::RsdpRequest::new();
+#[no_mangle] #[used(linker)] pub static LIMINE_PAGING      : limine::request::PagingModeRequest     = limine::request::PagingModeRequest::new().with_mode(PAGING_MODE);
@@ -18,38 +18,61 @@
 #![allow(clippy::fn_to_numeric_cast)]
 #![feature(abi_x86_interrupt)]
 #![feature(asm_const)]
+#![feature(custom_test_frameworks)]
 #![feature(naked_functions)]
 #![feature(panic_info_message)]
 #![feature(start)]
 #![feature(used_with_arg)]
+#![test_runner(crate::ktest::runner)]
+#![reexport_test_harness_main = "test_main"]
 
 //Modules
 mod alloc;
+mod cmdline;
 mod gdt;
 mod kstruct;
+#[cfg(test)]
+mod ktest;
 mod limine_boot;
+mod manifest;
 mod pmm;
 
 //Imports
 use crate::alloc::*;
+use crate::cmdline::*;
 use crate::pmm::*;
 use crate::kstruct::*;
+use crate::manifest::*;
 use gluon::GLUON_VERSION;
 use gluon::noble::address_space::*;
 use gluon::noble::data_type::*;
+use gluon::noble::display::FramebufferInfo;
 use gluon::noble::file_system::MemoryVolume;
+use gluon::noble::handle::*;
 //use gluon::noble::file_system::*;
 use gluon::noble::input_events::*;
+use gluon::noble::return_code::*;
+use gluon::noble::signal::*;
+use gluon::noble::snapshot::*;
 use gluon::noble::system_calls::*;
 //use gluon::pc::fat::*;
 use gluon::pc::ports::*;
+use gluon::pc::acpi::*;
+use gluon::pc::clock::*;
+use gluon::pc::hpet::*;
 use gluon::pc::pci::*;
 use gluon::pc::pic;
-use gluon::pc::pit;
+use gluon::pc::power;
 use gluon::pc::ps2;
+use gluon::pc::rtc;
+use gluon::pc::rtc::DateTime;
+use gluon::pc::rtc::RTC_IRQ;
+use gluon::pc::serial::*;
 use gluon::sysv::executable::*;
 use gluon::x86_64::instructions::*;
+use gluon::x86_64::ioapic::*;
 use gluon::x86_64::lapic;
+use gluon::x86_64::page_operations::*;
 use gluon::x86_64::paging::*;
 use gluon::x86_64::port::*;
 use gluon::x86_64::registers::*;
@@ -61,6 +84,7 @@ use core::cell::RefCell;
 use core::convert::TryFrom;
 use core::fmt::Write;
 use core::panic::PanicInfo;
+use core::ptr::read_volatile;
 use core::ptr::write_volatile;
 use core::sync::atomic::AtomicU64;
 use core::sync::atomic::Ordering;
@@ -85,23 +109,36 @@ macro_rules!interrupt_halt_noe {
             let rflags = stack_frame.rflags_image();
             let cs = stack_frame.code_selector();
             let ss = stack_frame.stack_selector();
-            if let Some(printer_pointer) = GLOBAL_WRITE_POINTER {
-                let printer = &mut *printer_pointer;
+            if let Some(log_pointer) = GLOBAL_LOG_POINTER {
+                let printer = &mut (*log_pointer).at(LogLevel::Error);
                 writeln!(printer, "\n{}\nRIP:    {:016X}\nRSP:    {:016X}\nCS:     Index: {:02X} RPL: {:01X}\nSS:     Index: {:02X} RPL: {:01X}\nRFLAGS: {:016X}\nCR2:    {:016X}\n",
                 $text, rip, rsp,
                 cs.descriptor_table_index, cs.requested_privilege_level as u8,
                 ss.descriptor_table_index, ss.requested_privilege_level as u8,
                 rflags, read_cr2());
             }
+            if let PrivilegeLevel::User = cs.requested_privilege_level {fault_current_process()}
             loop {hlt();};
         }
         handler as unsafe extern "x86-interrupt" fn(InterruptStackFrame) as usize as u64
     }}
 }
 
-//Interrupt that halts (with error code)
+//Interrupt that passes an ISA IRQ to the port bound to its line
+macro_rules!interrupt_irq_user {
+    ($irq:expr) => {{
+        unsafe extern "x86-interrupt" fn handler() {
+            deliver_irq($irq);
+            lapic::end_int();
+        }
+        handler as unsafe extern "x86-interrupt" fn() as usize as u64
+    }}
+}
+
+//Interrupt that halts (with error code, optionally printing the page mapping of the faulting address in CR2)
 macro_rules!interrupt_halt_err {
-    ($text:expr) => {{
+    ($text:expr) => {interrupt_halt_err!($text, false)};
+    ($text:expr, $print_mapping:expr) => {{
         unsafe extern "x86-interrupt" fn handler(stack_frame: InterruptStackFrame, error_code: u64) {
             asm!("MOV SS, {:x}", in(reg) u16::from(gdt::SUPERVISOR_DATA));
             asm!("MOV DS, {:x}", in(reg) u16::from(gdt::SUPERVISOR_DATA));
@@ -110,14 +147,16 @@ macro_rules!interrupt_halt_err {
             let rflags = stack_frame.rflags_image();
             let cs = stack_frame.code_selector();
             let ss = stack_frame.stack_selector();
-            if let Some(printer_pointer) = GLOBAL_WRITE_POINTER {
-                let printer = &mut *printer_pointer;
+            if let Some(log_pointer) = GLOBAL_LOG_POINTER {
+                let printer = &mut (*log_pointer).at(LogLevel::Error);
                 writeln!(printer, "\n{}\nRIP:    {:016X}\nRSP:    {:016X}\nCS:     Index: {:02X} RPL: {:01X}\nSS:     Index: {:02X} RPL: {:01X}\nRFLAGS: {:016X}\nERROR:  {:016X}\nCR2:    {:016X}\n",
                 $text, rip, rsp,
                 cs.descriptor_table_index, cs.requested_privilege_level as u8,
                 ss.descriptor_table_index, ss.requested_privilege_level as u8,
                 rflags, error_code, read_cr2());
+                if $print_mapping {print_mapping(printer, LinearAddress(read_cr2() as usize));}
             }
+            if let PrivilegeLevel::User = cs.requested_privilege_level {fault_current_process()}
             loop {hlt();};
         }
         handler as unsafe extern "x86-interrupt" fn(InterruptStackFrame, u64) as usize as u64
@@ -135,11 +174,17 @@ pub extern "sysv64" fn _start() -> ! {
     // LIMINE SETUP
     let framebuffer = limine_boot::LIMINE_FRAMEBUFFER.get_response().unwrap().framebuffers().next().unwrap();
     let framebuffer_address = framebuffer.addr();
+    let command_line = match limine_boot::LIMINE_KERNEL_FILE.get_response() {
+        Some(response) => core::str::from_utf8(response.file().cmdline()).unwrap_or(""),
+        None => "",
+    };
+    let settings = Settings::parse(command_line);
 
     // GRAPHICS SETUP
     let pixel_renderer: PixelRendererHWD<ColorBGRX>;
     let character_renderer: CharacterTwoToneRenderer16x16<ColorBGRX>;
-    let mut printer: PrintWindow::<PRINT_LINES, PRINT_HEIGHT, PRINT_WIDTH, ColorBGRX, CharacterTwoTone<ColorBGRX>>;
+    let mut window: PrintWindow::<PRINT_LINES, PRINT_HEIGHT, PRINT_WIDTH, ColorBGRX, CharacterTwoTone<ColorBGRX>>;
+    let mut printer: KernelLog;
     let mut inputter: InputWindow::<INPUT_LENGTH, INPUT_WIDTH, ColorBGRX, CharacterTwoTone<ColorBGRX>>;
     {
         //Pixel Renderer
@@ -161,10 +206,18 @@ pub extern "sysv64" fn _start() -> ! {
         frame.render();
         //Globals
         inputter = InputWindow::<INPUT_LENGTH, INPUT_WIDTH, ColorBGRX, CharacterTwoTone<ColorBGRX>>::new(&character_renderer, WHITESPACE, INPUT_Y, INPUT_X);
-        printer = PrintWindow::<PRINT_LINES, PRINT_HEIGHT, PRINT_WIDTH, ColorBGRX, CharacterTwoTone<ColorBGRX>>::new(&character_renderer, WHITESPACE, WHITESPACE, PRINT_Y, PRINT_X);
-        unsafe {GLOBAL_WRITE_POINTER = Some(&mut printer as &mut dyn Write as *mut dyn Write)};
+        window = PrintWindow::<PRINT_LINES, PRINT_HEIGHT, PRINT_WIDTH, ColorBGRX, CharacterTwoTone<ColorBGRX>>::new(&character_renderer, WHITESPACE, WHITESPACE, PRINT_Y, PRINT_X);
+        //Kernel Log
+        let serial = if settings.serial {
+            let mut serial = SerialPort::new(unsafe {SERIAL_1.0});
+            serial.init(UART_FREQUENCY);
+            Some(serial)
+        } else {None};
+        printer = KernelLog {window: &mut window, serial, threshold: settings.log_level};
+        unsafe {GLOBAL_LOG_POINTER = Some(&mut printer as *mut KernelLog)};
+        unsafe {GLOBAL_WRITE_POINTER = Some(&mut PROGRAM_OUTPUT as &mut dyn Write as *mut dyn Write)};
         unsafe {GLOBAL_INPUT_POINTER = Some(&mut inputter as *mut InputWindow<INPUT_LENGTH, INPUT_WIDTH, ColorBGRX, CharacterTwoTone<ColorBGRX>>)};
-        unsafe {GLOBAL_PRINT_POINTER = Some(&mut printer as *mut PrintWindow<PRINT_LINES, PRINT_HEIGHT, PRINT_WIDTH, ColorBGRX, CharacterTwoTone<ColorBGRX>>)}
+        unsafe {GLOBAL_PRINT_POINTER = Some(&mut window as *mut PrintWindow<PRINT_LINES, PRINT_HEIGHT, PRINT_WIDTH, ColorBGRX, CharacterTwoTone<ColorBGRX>>)}
         //Print Welcome
         writeln!(printer, "Welcome to Noble OS");
         let boot_info = limine_boot::LIMINE_INFO.get_response().expect("No Bootloader Info");
@@ -172,6 +225,11 @@ pub extern "sysv64" fn _start() -> ! {
         writeln!(printer, "Helium Kernel           {}", HELIUM_VERSION);
         writeln!(printer, "Photon Graphics Library {}", PHOTON_VERSION);
         writeln!(printer, "Gluon Memory Library    {}", GLUON_VERSION);
+        //Print Settings
+        writeln!(printer.at(LogLevel::Debug), "Command Line: {}", command_line);
+        for (setting, error) in Settings::errors(command_line) {
+            writeln!(printer.at(LogLevel::Error), "SETTING REJECTED: {} ({:?})", setting, error);
+        }
     }
 
     // NEW MEMORY SYSTEM TESTING
@@ -181,15 +239,16 @@ pub extern "sysv64" fn _start() -> ! {
     let translator: OffsetIdentity;
     let mut allocator: MemoryStack;
     let mut memmap_xu: MapMemory;
-    let mut memmap_eu: MapMemory;
     let mut memunmap: UnmapMemory;
     unsafe {
         //Limine HHDM
         hhdm_address = limine_boot::LIMINE_HHDM.get_response().unwrap().offset() as usize;
         writeln!(printer, "HHDM Address: 0x{:016X}", hhdm_address);
+        //Paging levels (Limine places the HHDM according to them)
+        let root: PageMapLevel = root_level();
         //Physical to linear address translator
         translator = OffsetIdentity {
-            offset: PHYSICAL_MEMORY_PTR,
+            offset: physical_memory_ptr(root),
             limit: PAGE_SIZE_512G,
         };
         //Limine Setup
@@ -213,19 +272,34 @@ pub extern "sysv64" fn _start() -> ! {
             write: true,
             user: true,
             execute_disable: true,
+            largest_page: PageMapLevel::L1,
         };
-        //Page map
+        //Page map (a PML5 rather than a PML4 when the bootloader enabled 5-level paging)
         let pml4_physical = read_cr3_address();
-        pml4 = PageMap::new(translator.translate(pml4_physical).unwrap(), PageMapLevel::L4).unwrap();
+        pml4 = PageMap::new(translator.translate(pml4_physical).unwrap(), root).unwrap();
         writeln!(printer, "Successfully retrieved CR3: 0x{:016X}", pml4_physical.0);
+        writeln!(printer, "Paging Levels: {} (LA57 Supported: {})", root as u8, la57_check());
+        //Page attribute table (selects the cache mode of each page, without it the power-on layout applies, under which write-protect pages are write-back and write-combining pages write-through)
+        if pat_check() {
+            write_pat();
+            writeln!(printer, "Page Attribute Table: 0x{:016X}", pat_value());
+        }
+        else {writeln!(printer, "Page Attribute Table: UNSUPPORTED");}
+        //Process-context identifiers (tag TLB entries with the address space which made them)
+        if pcid_check() {
+            enable_pcid();
+            let (pcid, _) = PCIDS.assign(pml4_physical);
+            load_page_map(pml4_physical, pcid, true);
+        }
+        writeln!(printer, "PCID Supported: {} (INVPCID Supported: {})", pcid_check(), invpcid_check());
         //Setup operations
         let mut markinuse = MarkInUse {translator: &translator};
         let mut deprivilege = DePrivilege {translator: &translator};
         //Mark in use
-        virtual_memory_editor(pml4, &mut markinuse, LinearAddress(PHYSICAL_MEMORY_PTR), LinearAddress(PHYSICAL_MEMORY_PTR + page_size(PHYSICAL_MEMORY_LVL)));
+        virtual_memory_editor(pml4, &mut markinuse, LinearAddress(physical_memory_ptr(root)), LinearAddress(physical_memory_ptr(root) + page_size(PHYSICAL_MEMORY_LVL)));
         virtual_memory_editor(pml4, &mut markinuse, LinearAddress(KERNEL_CODE_PTR), LinearAddress(KERNEL_CODE_PTR - 1 + page_size(KERNEL_CODE_LVL)));
         //Deprivilege
-        virtual_memory_editor(pml4, &mut deprivilege, LinearAddress(PHYSICAL_MEMORY_PTR), LinearAddress(PHYSICAL_MEMORY_PTR + page_size(PHYSICAL_MEMORY_LVL)));
+        virtual_memory_editor(pml4, &mut deprivilege, LinearAddress(physical_memory_ptr(root)), LinearAddress(physical_memory_ptr(root) + page_size(PHYSICAL_MEMORY_LVL)));
         virtual_memory_editor(pml4, &mut deprivilege, LinearAddress(KERNEL_CODE_PTR), LinearAddress(KERNEL_CODE_PTR - 1 + page_size(KERNEL_CODE_LVL)));
         writeln!(printer, "Successfully sanitized page maps.");
         //Create memory stack
@@ -244,6 +318,7 @@ pub extern "sysv64" fn _start() -> ! {
             index: RefCell::new(stack_count),
             stack: stack_ptr.add(1),
             translator: &*(&translator as *const OffsetIdentity),
+            blocks: RefCell::new([BlockList::new(); 2]),
         };
         //Map and unmap operations
         memmap_xu = MapMemory {
@@ -252,25 +327,50 @@ pub extern "sysv64" fn _start() -> ! {
             write: true,
             user: true,
             execute_disable: true,
-        };
-        memmap_eu = MapMemory {
-            allocator: &*(&allocator as *const MemoryStack),
-            translator: &*(&translator as *const OffsetIdentity),
-            write: true,
-            user: true,
-            execute_disable: false,
+            largest_page: largest_page_level(),
         };
         memunmap = UnmapMemory {
             allocator: &*(&allocator as *const MemoryStack),
             translator: &*(&translator as *const OffsetIdentity),
+            invalidator: &PageInvalidator::ACTIVE,
+        };
+    }
+
+    // FRAMEBUFFER
+    writeln!(printer, "\n=== FRAMEBUFFER ===\n");
+    unsafe {
+        //Describe the framebuffer so that it can be handed to a display server
+        let size = framebuffer.pitch() as usize * framebuffer.height() as usize;
+        let info = FramebufferInfo {
+            size:           ((size + PAGE_SIZE_4KIB - 1) / PAGE_SIZE_4KIB * PAGE_SIZE_4KIB) as u64,
+            width:          framebuffer.width(),
+            height:         framebuffer.height(),
+            pitch:          framebuffer.pitch(),
+            bits_per_pixel: framebuffer.bpp(),
+            red_size:       framebuffer.red_mask_size(),
+            red_shift:      framebuffer.red_mask_shift(),
+            green_size:     framebuffer.green_mask_size(),
+            green_shift:    framebuffer.green_mask_shift(),
+            blue_size:      framebuffer.blue_mask_size(),
+            blue_shift:     framebuffer.blue_mask_shift(),
+        };
+        let port = MemPort {
+            address:         PhysicalAddress(framebuffer_address as usize - hhdm_address),
+            level:           PageMapLevel::L1,
+            pages:           info.size as usize / PAGE_SIZE_4KIB,
+            cache_mode:      CacheMode::WriteCombining,
+            data_type:       DataType::Binary,
         };
+        FRAMEBUFFER = Some((port, info));
+        writeln!(printer, "Framebuffer:   0x{:016X}, {} bytes", port.address.0, info.size);
+        writeln!(printer, "Resolution:    {}x{}, {} bpp, pitch {}", info.width, info.height, info.bits_per_pixel, info.pitch);
     }
 
     // HEAP ALLOCATION
     writeln!(printer, "\n=== HEAP ALLOCATION ===\n");
     unsafe {
         let heap_port_map_address = allocator.take_one().unwrap();
-        let heap_port = MemPort{address: heap_port_map_address, level: KERNEL_HEAP_LVL, data_type: DataType::Binary};
+        let heap_port = MemPort{address: heap_port_map_address, level: KERNEL_HEAP_LVL, pages: 1, cache_mode: CacheMode::WriteBack, data_type: DataType::Binary};
         let map_port = MapPort {
             allocator: &allocator,
             translator: &translator,
@@ -289,10 +389,12 @@ pub extern "sysv64" fn _start() -> ! {
             &mut memunmap as *mut UnmapMemory as *mut dyn PageOperation
         ).unwrap();
         //Testing
-        for i in 0..26 {
-            writeln!(printer, "Index: {:2}, Size: {:16X}", i, Heap1G::index_to_size(i));
+        if settings.self_tests {
+            for i in 0..26 {
+                writeln!(printer, "Index: {:2}, Size: {:16X}", i, Heap1G::index_to_size(i));
+            }
+            writeln!(printer, "{:?}", Heap1G::split(25, AllocPtr{ state: AllocState::Free, next_address: PAGE_SIZE_1GIB }));
         }
-        writeln!(printer, "{:?}", Heap1G::split(25, AllocPtr{ state: AllocState::Free, next_address: PAGE_SIZE_1GIB }));
     }
 
     // PCI TESTING
@@ -349,7 +451,7 @@ pub extern "sysv64" fn _start() -> ! {
         gdt = GlobalDescriptorTable{address: translator.translate(a).unwrap(), limit: 512};
         writeln!(printer, "GDT Linear Address:   0x{:016X}", gdt.address.0);
         //Substitute symbol in TSS entry for actual TSS
-        gdt::TASK_STATE_SEGMENT_ENTRY.base = &TASK_STATE_SEGMENT as *const TaskStateSegment as u64;
+        gdt::TASK_STATE_SEGMENT_ENTRY.base = &TASK_STATE_SEGMENT as *const TaskStateSegmentIo as u64;
         //Write TSS entry into GDT
         gdt.write_system_entry(gdt::TASK_STATE_SEGMENT_ENTRY, gdt::TASK_STATE_SEGMENT_POSITION).unwrap();
         //Write GDT code and data entries
@@ -392,7 +494,7 @@ pub extern "sysv64" fn _start() -> ! {
         int_exception.offset = interrupt_halt_err!("INTERRUPT VECTOR 0x0B (#NP): Segment Not Present");          idt.write_entry(&int_exception, 0x0B);
         int_exception.offset = interrupt_halt_err!("INTERRUPT VECTOR 0x0C (#SS): Stack Fault");                  idt.write_entry(&int_exception, 0x0C);
         int_exception.offset = interrupt_halt_err!("INTERRUPT VECTOR 0x0D (#GP): General Protection Fault");     idt.write_entry(&int_exception, 0x0D);
-        int_exception.offset = interrupt_halt_err!("INTERRUPT VECTOR 0x0E (#PF): Page Fault");                   idt.write_entry(&int_exception, 0x0E);
+        int_exception.offset = interrupt_halt_err!("INTERRUPT VECTOR 0x0E (#PF): Page Fault", true);             idt.write_entry(&int_exception, 0x0E);
         int_exception.offset = interrupt_halt_noe!("INTERRUPT VECTOR 0x10 (#MF): x87 FPU Floating Point Error"); idt.write_entry(&int_exception, 0x10);
         int_exception.offset = interrupt_halt_err!("INTERRUPT VECTOR 0x11 (#AC): Alignment Check");              idt.write_entry(&int_exception, 0x11);
         int_exception.offset = interrupt_halt_noe!("INTERRUPT VECTOR 0x12 (#MC): Machine Check");                idt.write_entry(&int_exception, 0x12);
@@ -410,6 +512,17 @@ pub extern "sysv64" fn _start() -> ! {
             descriptor_type: DescriptorType::InterruptGate,
         };
         for position in 0x20..0xFF {idt.write_entry(&int_user, position);}
+        //INT 20h - INT 2Fh
+        //ISA IRQs which may be bound to ports, replaced below for those handled by the kernel
+        let user_irq_handlers: [u64; ISA_IRQS] = [
+            interrupt_irq_user!(0x0), interrupt_irq_user!(0x1), interrupt_irq_user!(0x2), interrupt_irq_user!(0x3),
+            interrupt_irq_user!(0x4), interrupt_irq_user!(0x5), interrupt_irq_user!(0x6), interrupt_irq_user!(0x7),
+            interrupt_irq_user!(0x8), interrupt_irq_user!(0x9), interrupt_irq_user!(0xA), interrupt_irq_user!(0xB),
+            interrupt_irq_user!(0xC), interrupt_irq_user!(0xD), interrupt_irq_user!(0xE), interrupt_irq_user!(0xF),
+        ];
+        for (irq, handler) in user_irq_handlers.iter().enumerate() {
+            idt.write_entry(&InterruptDescriptor {offset: *handler, ..int_user}, 0x20 + irq as u16);
+        }
         //INT 20h
         //IRQ 0: Programmable Interval Timer
         let int_pit: InterruptDescriptor = InterruptDescriptor {
@@ -432,6 +545,17 @@ pub extern "sysv64" fn _start() -> ! {
             descriptor_type: DescriptorType::InterruptGate,
         };
         idt.write_entry(&int_keyboard, 0x21);
+        //INT 28h
+        //IRQ 8: Real-Time Clock
+        let int_rtc: InterruptDescriptor = InterruptDescriptor {
+            offset: interrupt_irq_08 as unsafe extern "x86-interrupt" fn() as usize as u64,
+            segment_selector: gdt::SUPERVISOR_CODE,
+            segment_present: true,
+            privilege_level: PrivilegeLevel::Supervisor,
+            interrupt_stack_table: 2,
+            descriptor_type: DescriptorType::InterruptGate,
+        };
+        idt.write_entry(&int_rtc, 0x28);
         //INT 30h
         //LAPIC Timer
         let int_timer: InterruptDescriptor = InterruptDescriptor {
@@ -492,64 +616,145 @@ pub extern "sysv64" fn _start() -> ! {
         virtual_memory_editor(pml4, &mut memmap_xu, start, end);
         let kernel_stack = (KERNEL_STACKS_PTR + PAGE_SIZE_4KIB * 4) as u64;
         //Update TSS
-        TASK_STATE_SEGMENT.rsp0 = kernel_stack;
-        TASK_STATE_SEGMENT.ist1 = kernel_stack;
-        TASK_STATE_SEGMENT.ist2 = kernel_stack;
+        TASK_STATE_SEGMENT.tss.rsp0 = kernel_stack;
+        TASK_STATE_SEGMENT.tss.ist1 = kernel_stack;
+        TASK_STATE_SEGMENT.tss.ist2 = kernel_stack;
+    }
+
+    // ACPI TABLES
+    writeln!(printer, "\n=== ADVANCED CONFIGURATION AND POWER INTERFACE ===\n");
+    let acpi_volume = MemoryVolume {offset: physical_memory_ptr(pml4.map_level), size: PAGE_SIZE_512G};
+    let acpi: Option<Acpi<MemoryVolume>> = limine_boot::LIMINE_RSDP.get_response().and_then(|response| {
+        //Older Limine revisions give the RSDP's higher half address
+        let rsdp_address = response.address() as usize;
+        let rsdp_address = if rsdp_address >= hhdm_address {rsdp_address - hhdm_address} else {rsdp_address};
+        match Acpi::new(&acpi_volume, rsdp_address as u64) {
+            Ok(acpi) => Some(acpi),
+            Err(error) => {writeln!(printer.at(LogLevel::Error), "ACPI REJECTED: {:?}", error); None},
+        }
+    });
+    if let Some(acpi) = &acpi {
+        writeln!(printer, "ACPI Revision: {}", acpi.rsdp.revision);
+        writeln!(printer, "ACPI OEM:      {}", core::str::from_utf8(&acpi.rsdp.oem_id).unwrap_or("?"));
+        for header in acpi.tables().flatten().filter_map(|address| TableHeader::new(&acpi_volume, address).ok()) {
+            writeln!(printer, "ACPI Table:    {} at 0x{:016X}", core::str::from_utf8(&header.signature).unwrap_or("?"), header.address);
+        }
+        if let Ok(madt) = acpi.madt() {
+            writeln!(printer, "LAPIC Address: 0x{:016X}", madt.local_apic_address);
+            for entry in madt.entries().flatten() {
+                match entry {
+                    MadtEntry::LocalApic {processor_id, apic_id, enabled, ..} => {writeln!(printer, "  Processor {:3}: LAPIC ID {:3}, Enabled: {}", processor_id, apic_id, enabled);},
+                    MadtEntry::IoApic {id, address, interrupt_base} => {writeln!(printer, "  IOAPIC {:3}:    0x{:08X}, GSI Base: {}", id, address, interrupt_base);},
+                    MadtEntry::InterruptSourceOverride {source, interrupt, flags, ..} => {writeln!(printer, "  IRQ {:2}:        GSI {:3}, {:?}", source, interrupt, flags);},
+                    _ => {},
+                }
+            }
+        }
+        //Keep what is needed to reset and power off, as the tables are only read during boot
+        unsafe {
+            POWER_FADT = acpi.fadt().ok();
+            POWER_S5 = acpi.sleep_type(5).ok();
+            match POWER_S5 {
+                Some(s5) => {writeln!(printer, "S5 Sleep:      PM1a {}, PM1b {}", s5.pm1a, s5.pm1b);},
+                None     => {writeln!(printer.at(LogLevel::Error), "S5 SLEEP TYPE NOT FOUND, POWER OFF UNAVAILABLE");},
+            }
+        }
     }
 
-    // PIC SETUP
-    writeln!(printer, "\n=== PROGRAMMABLE INTERRUPT CONTROLLER ===\n");
+    // INTERRUPT CONTROLLER SETUP
+    writeln!(printer, "\n=== INTERRUPT CONTROLLERS ===\n");
     unsafe {
-        //Remap PIC
+        //Move the PIC off the exception vectors and mask it entirely
         pic::remap(0x20, 0x28).unwrap();
-        //Enable IRQ 1
-        pic::enable_irq(0x1).unwrap();
-        //Test ability to read PIC inputs
+        pic::disable();
         writeln!(printer, "PIC Data: {:08b} {:08b}", PIC1_DATA.read(), PIC2_DATA.read());
+        //Enable the LAPIC, which receives interrupts from the IOAPICs
+        lapic::LAPIC_ADDRESS = (lapic::get_base() as usize + hhdm_address as usize) as *mut u8;
+        lapic::spurious(0xFF);
+        lapic::enable();
+        let boot_apic_id = (lapic::read_register(0x20).unwrap() >> 24) as u8;
+        //Find IOAPICs and interrupt source overrides (without ACPI, assume a single IOAPIC with ISA IRQs identity mapped)
+        match acpi.as_ref().and_then(|acpi| acpi.madt().ok()) {
+            Some(madt) => {
+                for entry in madt.entries().flatten() {
+                    match entry {
+                        MadtEntry::IoApic {id, address, interrupt_base} => {INTERRUPT_ROUTER.add_ioapic(IoApic::new((address as usize + hhdm_address) as *mut u8, id, interrupt_base)).unwrap();},
+                        MadtEntry::InterruptSourceOverride {bus: 0, source, interrupt, flags} => {INTERRUPT_ROUTER.add_override(source, interrupt, flags);},
+                        _ => {},
+                    }
+                }
+            },
+            None => {INTERRUPT_ROUTER.add_ioapic(IoApic::new((IOAPIC_ADDRESS + hhdm_address) as *mut u8, 0, 0)).unwrap();},
+        }
+        //Route ISA IRQs to the boot CPU, leaving the PIT masked as it is only read as a clock source
+        let pit_interrupt = INTERRUPT_ROUTER.route_isa(0x0, 0x20, boot_apic_id).unwrap();
+        INTERRUPT_ROUTER.set_masked(pit_interrupt, true).unwrap();
+        RTC_INTERRUPT = INTERRUPT_ROUTER.route_isa(RTC_IRQ, 0x28, boot_apic_id).unwrap();
+        INTERRUPT_ROUTER.set_masked(RTC_INTERRUPT, true).unwrap();
+        //Route the remaining ISA IRQs masked, to be unmasked when bound to a port (IRQ 2 is the PIC cascade and never raised)
+        for irq in 0..ISA_IRQS as u8 {
+            if KERNEL_IRQS.contains(&irq) || irq == 0x2 {continue}
+            if let Ok(interrupt) = INTERRUPT_ROUTER.route_isa(irq, 0x20 + irq, boot_apic_id) {
+                INTERRUPT_ROUTER.set_masked(interrupt, true).unwrap();
+                USER_IRQ_LINES[irq as usize] = Some(interrupt);
+            }
+        }
+        //The keyboard IRQ is decoded by the kernel until a user-mode driver binds it
+        let keyboard_interrupt = USER_IRQ_LINES[0x1].unwrap();
+        INTERRUPT_ROUTER.set_masked(keyboard_interrupt, false).unwrap();
+        IRQ_RELEASED = Some(irq_released);
+        writeln!(printer, "PIT IRQ:      GSI {}", pit_interrupt);
+        writeln!(printer, "Keyboard IRQ: GSI {}", keyboard_interrupt);
+        writeln!(printer, "RTC IRQ:      GSI {}", RTC_INTERRUPT);
     }
 
-    // PIT BUS SPEED MEASUREMENT
-    writeln!(printer, "\n=== PROGRAMMABLE INTERVAL TIMER ===\n");
-    let cpu_hz: u64;
+    // CLOCK SOURCES
+    writeln!(printer, "\n=== CLOCK SOURCES ===\n");
     unsafe {
-        //Enable IRQ 0
-        pic::enable_irq(0x0);
-        sti();
-        //Set PIT channel 0 to one shot mode
-        pit::send_command(pit::Channel::C1, pit::AccessMode::Full, pit::OperatingMode::RateGenerator, pit::BinaryMode::Binary);
-        //Set PIT to Interrupt in 1/41 of a second
-        pit::set_reload_full(pit::Channel::C1, (pit::PIT_FREQUENCY / 41) as u16);
-        //Get interval 1
-        hlt();
-        let interval_1: u64 = rdtsc();
-        //Get interval 2
-        hlt();
-        let interval_2: u64 = rdtsc();
-        //Get interval 3
-        hlt();
-        let interval_3: u64 = rdtsc();
-        //Get interval 4
-        hlt();
-        let interval_4: u64 = rdtsc();
-        //Get interval 5
-        hlt();
-        let interval_5: u64 = rdtsc();
-        //Disable IRQ 0
-        pic::disable_irq(0x0);
-        cli();
-        //Calculate Hz
-        let hz_2 = (interval_2 - interval_1) * 41;
-        let hz_3 = (interval_3 - interval_2) * 41;
-        let hz_4 = (interval_4 - interval_3) * 41;
-        let hz_5 = (interval_5 - interval_4) * 41;
-        cpu_hz = (hz_2 + hz_3 + hz_4 + hz_5) / 4;
-        //Diagnostic
-        writeln!(printer, "Interval 1:  {:16X}", interval_1);
-        writeln!(printer, "Interval 2:  {:16X} ({:5}MHz)", interval_2, hz_2 / 1_000_000);
-        writeln!(printer, "Interval 3:  {:16X} ({:5}MHz)", interval_3, hz_3 / 1_000_000);
-        writeln!(printer, "Interval 4:  {:16X} ({:5}MHz)", interval_4, hz_4 / 1_000_000);
-        writeln!(printer, "Interval 5:  {:16X} ({:5}MHz)", interval_5, hz_5 / 1_000_000);
-        writeln!(printer, "Average MHz: {:16}", cpu_hz / 1_000_000);
+        //Find the HPET
+        let hpet = acpi.as_ref().and_then(|acpi| acpi.hpet().ok()).filter(|table| table.base_address.address_space == AddressSpace::SystemMemory).and_then(|table| {
+            match HpetDevice::new((table.base_address.address as usize + hhdm_address) as *mut u8) {
+                Ok(device) => Some(device),
+                Err(error) => {writeln!(printer.at(LogLevel::Error), "HPET REJECTED: {:?}", error); None},
+            }
+        });
+        if let Some(device) = &hpet {
+            writeln!(printer, "HPET:          {} Hz, {} Comparators, 64-Bit: {}", device.frequency(), device.comparator_count, device.counter_64);
+        }
+        writeln!(printer, "Invariant TSC: {}", tsc_invariant());
+        //Pick the monotonic clock's source
+        let mut clock = Clock::select(hpet);
+        writeln!(printer, "Clock Source:  {} ({} Hz)", clock.name(), clock.frequency());
+        //Measure the LAPIC timer against it
+        lapic::divide_config(lapic::Divide::Divide_1);
+        lapic::timer(0x30, true, lapic::TimerMode::OneShot);
+        lapic::initial_count(u32::MAX);
+        let lapic_hz = calibrate(&mut clock, CALIBRATION_PERIOD, || (u32::MAX - lapic::current_count()) as u64);
+        lapic::initial_count(0);
+        writeln!(printer, "LAPIC Timer:   {} Hz", lapic_hz);
+        LAPIC_FREQUENCY = lapic_hz;
+        //Measure the TSC against it, for reporting thread CPU time
+        let tsc_hz = calibrate(&mut clock, CALIBRATION_PERIOD, rdtsc);
+        writeln!(printer, "TSC:           {} Hz", tsc_hz);
+        TSC_FREQUENCY = tsc_hz;
+        MONOTONIC_CLOCK = Some(MonotonicClock::new(clock));
+    }
+
+    // REAL-TIME CLOCK
+    writeln!(printer, "\n=== REAL-TIME CLOCK ===\n");
+    unsafe {
+        RTC_CENTURY = acpi.as_ref().and_then(|acpi| acpi.fadt().ok()).map(|fadt| fadt.century).filter(|century| *century != 0);
+        match DateTime::read(RTC_CENTURY) {
+            Ok(date_time) => {
+                set_wall_clock(date_time);
+                writeln!(printer, "Date:      {:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", date_time.year, date_time.month, date_time.day, date_time.hour, date_time.minute, date_time.second);
+                writeln!(printer, "Unix Time: {}", date_time.unix_time());
+                //Realign the wall clock to the start of the next second when its update interrupt arrives
+                rtc::set_update_interrupt(true);
+                INTERRUPT_ROUTER.set_masked(RTC_INTERRUPT, false).unwrap();
+            },
+            Err(error) => {writeln!(printer.at(LogLevel::Error), "RTC REJECTED: {:?}", error);},
+        }
     }
 
     // PS/2 BUS
@@ -603,20 +808,21 @@ pub extern "sysv64" fn _start() -> ! {
         //Diagnostic
         writeln!(printer, "APIC Present: {}", lapic::apic_check());
         writeln!(printer, "APIC Base: 0x{:16X}", lapic::get_base());
-        //Shift Base
-        lapic::LAPIC_ADDRESS = (lapic::get_base() as usize + hhdm_address as usize) as *mut u8;
-        //Spurious interrupt
-        lapic::spurious(0xFF);
-        //Diagnostic
         writeln!(printer, "APIC ID:   0x{:1X}", lapic::read_register(0x20).unwrap() >> 24);
         writeln!(printer, "APIC 0xF0: 0x{:08X}", lapic::read_register(0xF0).unwrap());
-        //Set Timer Mode
-        lapic::timer(0x30, false, lapic::TimerMode::Periodic);
+        //Set Timer Mode (TSC-deadline mode needs the monotonic clock to count TSC cycles)
+        let tsc_clock = MONOTONIC_CLOCK.map(|clock| matches!(clock.source, Clock::Tsc(_))) == Some(true);
+        SCHEDULER_TIMER = if tsc_clock && lapic::tsc_deadline_check() {lapic::TimerMode::TSCDeadline} else {lapic::TimerMode::OneShot};
+        TICK_RATE = settings.tick_rate as u64;
+        lapic::timer(0x30, false, SCHEDULER_TIMER);
         lapic::divide_config(lapic::Divide::Divide_1);
-        //Enable LAPIC
-        lapic::enable();
+        writeln!(printer, "Timer Mode: {:?}", SCHEDULER_TIMER);
     }
 
+    // KERNEL TESTS
+    #[cfg(test)]
+    test_main();
+
     // RAMDISK TESTING
     /*writeln!(printer, "\n=== RAMDISK TEST ===\n");
     unsafe {
@@ -635,7 +841,8 @@ pub extern "sysv64" fn _start() -> ! {
             Err(_) => {writeln!(printer, "Boot sector invalid.");},
         }*/
         let volume = MemoryVolume{offset: oct4_to_usize(RAMDISK_OCT).unwrap(), size: 8 * MIB};
-        let file_system = FATFileSystem::from_existing_volume(&volume).unwrap();
+        let mut file_system = FATFileSystem::from_existing_volume(&volume).unwrap();
+        file_system.clock = wall_clock_date_time;
         let root_directory_id = file_system.root().unwrap();
         writeln!(printer, "Root Directory ID:          {:?}", root_directory_id);
         let root_directory_open = file_system.open(root_directory_id).unwrap();
@@ -664,7 +871,7 @@ pub extern "sysv64" fn _start() -> ! {
 
     // MODULE LOADING
     writeln!(printer, "\n=== LIMINE MODULES ===\n");
-    let mut module_instruction_ptr: u64 = 0;
+    let mut programs: [Option<ProgramImage>; MAX_PROGRAMS] = [None; MAX_PROGRAMS];
     unsafe {
         //Executable loading
         let mut current_module_address = LinearAddress(MODULE_CODE_PTR);
@@ -672,6 +879,14 @@ pub extern "sysv64" fn _start() -> ! {
         let modules_response = limine_boot::LIMINE_MODULES.get_response().unwrap();
         let modules = modules_response.modules();
         writeln!(printer, "MODULE COUNT: {}", modules.len());
+        //Read manifest
+        let manifest: Option<Manifest<'static>> = modules.iter()
+            .find(|module| core::str::from_utf8_unchecked(module.path()).ends_with(MANIFEST_SUFFIX))
+            .and_then(|module| match Manifest::new(core::slice::from_raw_parts(module.addr() as *const u8, module.size() as usize)) {
+                Ok(manifest) => Some(manifest),
+                Err(error)   => {writeln!(printer.at(LogLevel::Error), "MANIFEST REJECTED:    {:?}", error); None},
+            });
+        writeln!(printer, "MANIFEST PRESENT:     {}\n", manifest.is_some());
         //Iterate over modules
         for module in modules {
             //Load module path
@@ -682,39 +897,93 @@ pub extern "sysv64" fn _start() -> ! {
             let module_file_size: usize = module.size() as usize;
             writeln!(printer, "MODULE FILE LOCATION: 0x{:016X}", module_file_location);
             writeln!(printer, "MODULE FILE SIZE:     0x{:016X}", module_file_size);
+            //Decide role of module (without a manifest, executables are started in order with the first as init)
+            let mut entry: Option<ManifestEntry<'static>> = match &manifest {
+                Some(_) if module_file_path.ends_with(MANIFEST_SUFFIX) => {writeln!(printer, "MODULE MANIFEST:      TRUE\n"); continue},
+                Some(manifest) => manifest.find(module_file_path),
+                None if module_file_path.ends_with("x86-64.elf") => Some(ManifestEntry {
+                    kind: if programs.iter().any(|program| program.is_some()) {ManifestKind::Program} else {ManifestKind::Init},
+                    path: module_file_path, name: module_file_path, priority: 0, arguments: "",
+                }),
+                None => None,
+            };
+            //An init program named on the command line replaces the one chosen above
+            if let Some(init_path) = settings.init_path {
+                if path_matches(module_file_path, init_path) {
+                    let mut init = entry.unwrap_or(ManifestEntry {kind: ManifestKind::Init, path: module_file_path, name: module_file_path, priority: 0, arguments: ""});
+                    init.kind = ManifestKind::Init;
+                    entry = Some(init);
+                }
+                else if let Some(other) = entry.as_mut() {
+                    if other.kind == ManifestKind::Init {other.kind = ManifestKind::Program}
+                }
+            }
             //Executable module
-            if module_file_path.ends_with("x86-64.elf") {
-                writeln!(printer, "MODULE EXECUTABLE:    TRUE");
-                //Setup file
-                let module_file: MemoryVolume = MemoryVolume {offset: module_file_location, size: module_file_size};
-                if let Ok(mut module) = ELFFile::new(&module_file) {
-                    //Check ELF header validity
-                    let valid_binary_interface: bool = module.header.binary_interface == ApplicationBinaryInterface::None;
-                    let valid_binary_interface_version: bool = module.header.binary_interface_version == 0x00;
-                    let valid_architecture: bool = module.header.architecture == InstructionSetArchitecture::EmX86_64;
-                    let valid_object_type: bool = module.header.object_type == ObjectType::Shared;
-                    let valid: bool = valid_binary_interface && valid_binary_interface_version && valid_architecture && valid_object_type;
-                    writeln!(printer, "MODULE VALID:         {}", valid);
-                    if valid {
-                        //Allocate memory for module
-                        let module_size: usize = module.program_memory_size() as usize;
-                        writeln!(printer, "MODULE SIZE:          0x{:016X}", module_size);
-                        virtual_memory_editor(pml4, &mut memmap_eu, current_module_address, current_module_address.add(module_size)).unwrap();
-                        //Load and relocate module
-                        let module_ptr: *mut u8 = current_module_address.0 as *mut u8;
-                        writeln!(printer, "LOADING MODULE AT:    0x{:016X}", module_ptr as usize);
-                        module.load(module_ptr).unwrap();
-                        module.relocate(module_ptr, module_ptr).unwrap();
-                        //Save module instruction pointer
-                        module_instruction_ptr = (current_module_address.0 as u64) + module.header.entry_point;
-                        writeln!(printer, "MODULE ENTRY POINT:   0x{:016X}", module_instruction_ptr);
-                        //Adjust next module location
-                        current_module_address = current_module_address.add(page_size(align_lvl(module_size)));
+            match entry {
+                Some(entry) if entry.kind != ManifestKind::Data => {
+                    writeln!(printer, "MODULE EXECUTABLE:    TRUE");
+                    writeln!(printer, "MODULE INIT:          {}", entry.kind == ManifestKind::Init);
+                    writeln!(printer, "MODULE PRIORITY:      {}", entry.priority);
+                    writeln!(printer, "MODULE ARGUMENTS:     {}", entry.arguments);
+                    //Setup file
+                    let module_file: MemoryVolume = MemoryVolume {offset: module_file_location, size: module_file_size};
+                    if let Ok(mut module) = ELFFile::new(&module_file) {
+                        //Check ELF header validity
+                        let valid_binary_interface: bool = module.header.binary_interface == ApplicationBinaryInterface::None;
+                        let valid_binary_interface_version: bool = module.header.binary_interface_version == 0x00;
+                        let valid_architecture: bool = module.header.architecture == InstructionSetArchitecture::EmX86_64;
+                        let valid_object_type: bool = module.header.object_type == ObjectType::Shared;
+                        let valid: bool = valid_binary_interface && valid_binary_interface_version && valid_architecture && valid_object_type;
+                        writeln!(printer, "MODULE VALID:         {}", valid);
+                        let slot = programs.iter_mut().find(|program| program.is_none());
+                        if valid && slot.is_none() {writeln!(printer.at(LogLevel::Error), "MODULE REJECTED:      {:?}", ReturnCode::OutOfResources);}
+                        else if let (true, Some(slot)) = (valid, slot) {
+                            //Load module with segment permissions
+                            let module_size: usize = module.program_memory_size() as usize;
+                            writeln!(printer, "MODULE SIZE:          0x{:016X}", module_size);
+                            writeln!(printer, "LOADING MODULE AT:    0x{:016X}", current_module_address.0);
+                            match load_module(pml4, &allocator, &translator, &mut module, current_module_address) {
+                                Ok(entry_point) => {
+                                    //Save program
+                                    *slot = Some(ProgramImage {
+                                        entry_point,
+                                        start: current_module_address,
+                                        end: current_module_address.add(module_size),
+                                        init: entry.kind == ManifestKind::Init,
+                                        priority: entry.priority,
+                                        arguments: entry.arguments,
+                                    });
+                                    writeln!(printer, "MODULE ENTRY POINT:   0x{:016X}", entry_point);
+                                    //Adjust next module location
+                                    current_module_address = current_module_address.add(page_size(align_lvl(module_size)));
+                                },
+                                Err(error) => {writeln!(printer.at(LogLevel::Error), "MODULE REJECTED:      {:?}", error);},
+                            }
+                        }
                     }
-                }
-                else {writeln!(printer, "MODULE CORRUPTED");}
+                    else {writeln!(printer.at(LogLevel::Error), "MODULE CORRUPTED");}
+                },
+                Some(entry) => {
+                    writeln!(printer, "MODULE DATA:          {}", entry.name);
+                    //Kept for the director to open as a memory port (Limine places modules on page boundaries)
+                    let module = DataModule {
+                        name: entry.name,
+                        size: module_file_size,
+                        port: MemPort {
+                            address:    PhysicalAddress(module_file_location - hhdm_address),
+                            level:      PageMapLevel::L1,
+                            pages:      module_file_size.div_ceil(PAGE_SIZE_4KIB),
+                            cache_mode: CacheMode::WriteBack,
+                            data_type:  DataType::Binary,
+                        },
+                    };
+                    let slot = DATA_MODULES.iter_mut().find(|slot| slot.is_none());
+                    if module_file_location % PAGE_SIZE_4KIB != 0 {writeln!(printer.at(LogLevel::Error), "MODULE REJECTED:      {:?}", ReturnCode::UnalignedAddress);}
+                    else if let Some(slot) = slot {*slot = Some(module);}
+                    else {writeln!(printer.at(LogLevel::Error), "MODULE REJECTED:      {:?}", ReturnCode::OutOfResources);}
+                },
+                None        => {writeln!(printer, "MODULE EXECUTABLE:    FALSE");},
             }
-            else {writeln!(printer, "MODULE EXECUTABLE:    FALSE");}
             writeln!(printer);
         }
     }
@@ -722,70 +991,223 @@ pub extern "sysv64" fn _start() -> ! {
     // CREATE THREADS
     writeln!(printer, "\n=== THREAD STACK TEST ===\n");
     unsafe {
-        //Stack pointers
-        let s0p = oct_to_usize_4(0, 0, 0, 0, 0).unwrap();
-        let s1p = oct_to_usize_4(0, 0, 1, 0, 0).unwrap();
-        let s2p = oct_to_usize_4(0, 0, 2, 0, 0).unwrap();
-        let s3p = oct_to_usize_4(0, 0, 3, 0, 0).unwrap();
-        let s4p = oct_to_usize_4(0, 0, 4, 0, 0).unwrap();
-        //Allocate stack space
-        virtual_memory_editor(pml4, &mut memmap_xu, LinearAddress(s0p + PAGE_SIZE_4KIB), LinearAddress(s1p));
-        virtual_memory_editor(pml4, &mut memmap_xu, LinearAddress(s1p + PAGE_SIZE_4KIB), LinearAddress(s2p));
-        virtual_memory_editor(pml4, &mut memmap_xu, LinearAddress(s2p + PAGE_SIZE_4KIB), LinearAddress(s3p));
-        virtual_memory_editor(pml4, &mut memmap_xu, LinearAddress(s3p + PAGE_SIZE_4KIB), LinearAddress(s4p));
-        //Instruction pointers
+        //Expose memory to system calls
+        GLOBAL_PAGE_MAP = Some(pml4);
+        GLOBAL_ALLOCATOR_POINTER = Some(&allocator as *const MemoryStack as *const dyn PhysicalAddressAllocator);
+        GLOBAL_TRANSLATOR_POINTER = Some(&translator as *const OffsetIdentity as *const dyn AddressTranslator);
+        //Audit the page map for memory which is both writable and executable
+        audit_write_execute(&mut printer);
+        //Kernel threads
         let i1p = read_loop as fn() as usize as u64;
         let i2p = ps2_keyboard as unsafe fn() as usize as u64;
-        let i3p = module_instruction_ptr;
-        //Create tasks
-        create_thread(1, pml4, &translator, &mut memmap_xu, i1p, gdt::SUPERVISOR_CODE, 0x00000202, s1p, gdt::SUPERVISOR_DATA);
-        create_thread(2, pml4, &translator, &mut memmap_xu, i2p, gdt::USER_CODE, 0x00000202, s2p, gdt::USER_DATA);
-        create_thread(3, pml4, &translator, &mut memmap_xu, i3p, gdt::USER_CODE, 0x00000202, s3p, gdt::USER_DATA);
+        virtual_memory_editor(pml4, &mut memmap_xu, user_stack_start(1), user_stack_end(1));
+        virtual_memory_editor(pml4, &mut memmap_xu, user_stack_start(2), user_stack_end(2));
+        create_thread(1, pml4, &translator, &mut memmap_xu, i1p, gdt::SUPERVISOR_CODE, 0x00000202, user_stack_end(1).0, gdt::SUPERVISOR_DATA, (0, 0));
+        create_thread(2, pml4, &translator, &mut memmap_xu, i2p, gdt::USER_CODE, 0x00000202, user_stack_end(2).0, gdt::USER_DATA, (0, 0));
+        let kernel_process = create_process(read_cr3_address(), None).unwrap();
+        for thread_index in 0..FIRST_USER_THREAD {attach_thread(kernel_process, ThreadID(thread_index as u64), kernel_stack_start(thread_index), kernel_stack_end(thread_index), 0).unwrap();}
         //Diagnostic
         writeln!(printer, "Thread 1 (PIPE READ AND PRINT):");
-        writeln!(printer, "  Stack Pointer Before Init: 0x{:16X}", s1p);
+        writeln!(printer, "  Stack Pointer Before Init: 0x{:16X}", user_stack_end(1).0);
         writeln!(printer, "  Stack Pointer After Init:  0x{:16X}", TASK_STACKS[1]);
         writeln!(printer, "  Instruction Pointer:       0x{:16X}", i1p);
         writeln!(printer, "Thread 2 (PS2 KEYBOARD):");
-        writeln!(printer, "  Stack Pointer Before Init: 0x{:16X}", s2p);
+        writeln!(printer, "  Stack Pointer Before Init: 0x{:16X}", user_stack_end(2).0);
         writeln!(printer, "  Stack Pointer After Init:  0x{:16X}", TASK_STACKS[2]);
         writeln!(printer, "  Instruction Pointer:       0x{:16X}", i2p);
-        writeln!(printer, "Thread 3 (TEST MODULE):");
-        writeln!(printer, "  Stack Pointer Before Init: 0x{:16X}", s3p);
-        writeln!(printer, "  Stack Pointer After Init:  0x{:16X}", TASK_STACKS[3]);
-        writeln!(printer, "  Instruction Pointer:       0x{:16X}", i3p);
+        //Program threads (init is started first so that it becomes the director)
+        let init_first = programs.iter().flatten().filter(|program| program.init).chain(programs.iter().flatten().filter(|program| !program.init));
+        for (offset, program) in init_first.enumerate() {
+            let thread_index = FIRST_USER_THREAD + offset;
+            writeln!(printer, "Thread {} (PROGRAM{}):", thread_index, if program.init {" INIT"} else {""});
+            match start_program(pml4, &mut memmap_xu, &translator, thread_index, program, kernel_process) {
+                Ok(process) => {
+                    writeln!(printer, "  Process:                   {}", process.0);
+                    writeln!(printer, "  Stack Pointer After Init:  0x{:16X}", TASK_STACKS[thread_index]);
+                    writeln!(printer, "  Instruction Pointer:       0x{:16X}", program.entry_point);
+                },
+                Err(error) => {writeln!(printer.at(LogLevel::Error), "  Start Failed:              {:?}", error);},
+            }
+        }
     }
 
     // FINISH LOADING
     writeln!(printer, "\n=== STARTUP COMPLETE ===\n");
     unsafe {
-        //Start LAPIC timer
-        lapic::initial_count((cpu_hz / 1000) as u32);
+        //Start counting thread CPU time and start LAPIC timer
+        ACCOUNTING_TSC = rdtsc();
+        arm_timer(Some(0));
         //Enable Interrupts
         sti();
-        //Halt init thread
-        loop{hlt();}
+        //Halt init thread, reaping orphaned processes as they exit and zeroing freed large page blocks
+        loop {
+            cli();
+            let orphan = PROCESSES.iter().find(|(index, process)| process.exit_code.is_some() && process_parent(ProcessID(*index as u64)) == Ok(KERNEL_PROCESS)).map(|(index, _)| ProcessID(index as u64));
+            if let Some(orphan) = orphan {reap_process(orphan, pml4, &mut memunmap);}
+            sti();
+            if !allocator.scrub() {hlt();}
+            //Let the scheduler run whatever the interrupt woke
+            asm!("INT 31h");
+        }
     }
 }
 
 
+// PROGRAM LOADING
+//Constants
+const MAX_PROGRAMS:      usize = MAX_THREADS - FIRST_USER_THREAD; //MAXIMUM NUMBER OF PROGRAMS STARTED AT BOOT
+const MAX_ARGUMENTS:     usize = PAGE_SIZE_4KIB;                  //MAXIMUM LENGTH OF A PROGRAM'S ARGUMENT STRING
+
+//Program Image
+#[derive(Clone, Copy)]
+struct ProgramImage {
+    entry_point: u64,
+    start: LinearAddress,
+    end: LinearAddress,
+    init: bool,
+    priority: u8,
+    arguments: &'static str,
+}
+
+//Start a loaded program as a new process with a single thread
+unsafe fn start_program(map: PageMap, mmap: &mut MapMemory, translator: &dyn AddressTranslator, thread_index: usize, program: &ProgramImage, parent: ProcessID) -> Result<ProcessID, ReturnCode> {
+    if thread_index >= MAX_THREADS {return Err(ReturnCode::OutOfResources)}
+    if program.arguments.len() > MAX_ARGUMENTS {return Err(ReturnCode::BufferTooLarge)}
+    let process = create_process(read_cr3_address(), Some(parent))?;
+    let mut unmap = UnmapMemory {allocator: mmap.allocator, translator, invalidator: &PageInvalidator::ACTIVE};
+    if let Err(error) = start_program_thread(map, mmap, &mut unmap, translator, thread_index, program, process) {
+        //The process is ended and reaped, unmapping the memory and thread attached to it (the original error is returned, a failure to clean up is only logged)
+        let mut unmap_port = UnmapPort {allocator: mmap.allocator, translator, invalidator: &PageInvalidator::ACTIVE};
+        let cleanup = exit_process(process, error as u64, map, &mut unmap, &mut unmap_port).and_then(|_| reap_process(process, map, &mut unmap));
+        if let Err(cleanup) = cleanup {
+            if let Some(log_pointer) = GLOBAL_LOG_POINTER {writeln!((*log_pointer).at(LogLevel::Error), "PROGRAM CLEANUP FAILED: {:?}", cleanup);}
+        }
+        return Err(error);
+    }
+    Ok(process)
+}
+
+//Attach a program's memory and thread to its process, unmapping memory which failed to be attached
+unsafe fn start_program_thread(map: PageMap, mmap: &mut MapMemory, unmap: &mut UnmapMemory, translator: &dyn AddressTranslator, thread_index: usize, program: &ProgramImage, process: ProcessID) -> Result<(), ReturnCode> {
+    if let Err(error) = attach_memory(process, program.start, program.end) {
+        let _ = virtual_memory_editor(map, unmap, program.start, program.end);
+        return Err(error);
+    }
+    //Allocate stack and copy arguments to the top of it
+    virtual_memory_editor(map, mmap, user_stack_start(thread_index), user_stack_end(thread_index))?;
+    if let Err(error) = attach_memory(process, user_stack_start(thread_index), user_stack_end(thread_index)) {
+        let _ = virtual_memory_editor(map, unmap, user_stack_start(thread_index), user_stack_end(thread_index));
+        return Err(error);
+    }
+    let arguments_ptr = (user_stack_end(thread_index).0 - program.arguments.len()) & !0xF;
+    core::ptr::copy_nonoverlapping(program.arguments.as_ptr(), arguments_ptr as *mut u8, program.arguments.len());
+    //Create thread, passing arguments as (pointer, length)
+    create_thread(thread_index, map, translator, mmap, program.entry_point, gdt::USER_CODE, 0x00000202, arguments_ptr, gdt::USER_DATA, (arguments_ptr as u64, program.arguments.len() as u64));
+    if let Err(error) = attach_thread(process, ThreadID(thread_index as u64), kernel_stack_start(thread_index), kernel_stack_end(thread_index), program.priority) {
+        let _ = virtual_memory_editor(map, unmap, kernel_stack_start(thread_index), kernel_stack_end(thread_index));
+        return Err(error);
+    }
+    Ok(())
+}
+
+//Load an executable module, mapping each loadable segment with its own permissions
+unsafe fn load_module(map: PageMap, allocator: &dyn PhysicalAddressAllocator, translator: &dyn AddressTranslator, module: &mut ELFFile<MemoryVolume>, location: LinearAddress) -> Result<u64, ReturnCode> {
+    //Reject unreadable program headers, and segments which are both writeable and executable, before anything is mapped
+    for program in module.programs() {
+        let program = program?;
+        if program.program_type == ProgramType::Loadable && program.writeable() && program.executable() {return Err(ReturnCode::SecurityViolation)}
+    }
+    //Allocate memory for module (writeable and non-executable while loading)
+    let module_size: usize = module.program_memory_size() as usize;
+    let mut memmap_load = MapMemory {allocator, translator, write: true, user: true, execute_disable: true, largest_page: largest_page_level()};
+    virtual_memory_editor(map, &mut memmap_load, location, location.add(module_size))?;
+    //Load, relocate, and protect module
+    let result = load_module_segments(map, allocator, translator, module, location);
+    if result.is_err() {
+        //The original error is returned, a failure to clean up is only logged
+        let mut memunmap_load = UnmapMemory {allocator, translator, invalidator: &PageInvalidator::ACTIVE};
+        if let Err(error) = virtual_memory_editor(map, &mut memunmap_load, location, location.add(module_size)) {
+            if let Some(log_pointer) = GLOBAL_LOG_POINTER {writeln!((*log_pointer).at(LogLevel::Error), "MODULE UNMAP FAILED: {:?}", error);}
+        }
+    }
+    result
+}
+
+//Load, relocate, and apply permissions to the segments of a module in already mapped memory
+unsafe fn load_module_segments(map: PageMap, allocator: &dyn PhysicalAddressAllocator, translator: &dyn AddressTranslator, module: &mut ELFFile<MemoryVolume>, location: LinearAddress) -> Result<u64, ReturnCode> {
+    //Load and relocate module
+    let module_ptr: *mut u8 = location.0 as *mut u8;
+    module.load(module_ptr)?;
+    module.relocate(module_ptr, module_ptr)?;
+    //Apply segment permissions
+    let mut previous: Option<(usize, bool, bool)> = None; //end of previous segment's pages, write, execute
+    for program in module.programs() {
+        let program = program?;
+        if program.program_type != ProgramType::Loadable {continue}
+        let write: bool = program.writeable();
+        let execute: bool = program.executable();
+        let mut start: usize = ((location.0 + program.virtual_address as usize) / PAGE_SIZE_4KIB) * PAGE_SIZE_4KIB;
+        let end: usize = ((location.0 + (program.virtual_address + program.memory_size) as usize + PAGE_SIZE_4KIB - 1) / PAGE_SIZE_4KIB) * PAGE_SIZE_4KIB;
+        //A page shared with the previous segment takes the permissions of both segments
+        if let Some((previous_end, previous_write, previous_execute)) = previous {
+            if start < previous_end {
+                if (write || previous_write) && (execute || previous_execute) {return Err(ReturnCode::SecurityViolation)}
+                let mut protect = ProtectMemory {allocator, translator, invalidator: &PageInvalidator::ACTIVE, write: write || previous_write, user: true, execute_disable: !(execute || previous_execute)};
+                virtual_memory_editor(map, &mut protect, LinearAddress(start), LinearAddress(start + PAGE_SIZE_4KIB))?;
+                start += PAGE_SIZE_4KIB;
+            }
+        }
+        if start < end {
+            let mut protect = ProtectMemory {allocator, translator, invalidator: &PageInvalidator::ACTIVE, write, user: true, execute_disable: !execute};
+            virtual_memory_editor(map, &mut protect, LinearAddress(start), LinearAddress(end))?;
+        }
+        previous = Some((end, write, execute));
+    }
+    //Apply relocation read-only protection to whole pages
+    for program in module.programs() {
+        let program = program?;
+        if program.program_type != ProgramType::GnuRelocationReadOnly {continue}
+        let start: usize = ((location.0 + program.virtual_address as usize) / PAGE_SIZE_4KIB) * PAGE_SIZE_4KIB;
+        let end: usize = ((location.0 + (program.virtual_address + program.memory_size) as usize) / PAGE_SIZE_4KIB) * PAGE_SIZE_4KIB;
+        if start < end {
+            let mut protect = ProtectMemory {allocator, translator, invalidator: &PageInvalidator::ACTIVE, write: false, user: true, execute_disable: true};
+            virtual_memory_editor(map, &mut protect, LinearAddress(start), LinearAddress(end))?;
+        }
+    }
+    //Return entry point
+    Ok((location.0 as u64) + module.header.entry_point)
+}
+
+
 // TASKING
 //Global variables
 static GLOBAL_TIME: AtomicU64 = AtomicU64::new(0);
+static mut MONOTONIC_CLOCK: Option<MonotonicClock<Clock>> = None;
+static mut WALL_CLOCK_BASE: Option<u64> = None; //Unix time in nanoseconds at which the monotonic clock started
+static mut SCHEDULER_TIMER: lapic::TimerMode = lapic::TimerMode::OneShot;
+static mut LAPIC_FREQUENCY: u64 = 0;
+static mut TICK_RATE: u64 = 1000;
+static mut TSC_FREQUENCY: u64 = 0;
+static mut ACCOUNTING_TSC: u64 = 0; //TSC value up to which the current thread's CPU time has been counted
 static mut GLOBAL_WRITE_POINTER: Option<*mut dyn Write> = None;
+static mut GLOBAL_LOG_POINTER: Option<*mut KernelLog> = None;
 static mut GLOBAL_PRINT_POINTER: Option<*mut PrintWindow::<PRINT_LINES, PRINT_HEIGHT, PRINT_WIDTH, ColorBGRX, CharacterTwoTone<ColorBGRX>>> = None;
 static mut GLOBAL_INPUT_POINTER: Option<*mut InputWindow::<INPUT_LENGTH, INPUT_WIDTH, ColorBGRX, CharacterTwoTone<ColorBGRX>>> = None;
+static mut GLOBAL_PAGE_MAP: Option<PageMap> = None;
+static mut GLOBAL_ALLOCATOR_POINTER: Option<*const dyn PhysicalAddressAllocator> = None;
+static mut GLOBAL_TRANSLATOR_POINTER: Option<*const dyn AddressTranslator> = None;
 static mut TASK_INDEX: usize = 0;
-static mut TASK_STACKS: [u64; 4] = [0;4];
+static mut TASK_STACKS: [u64; MAX_THREADS] = [0; MAX_THREADS];
+static mut LAST_USER_THREAD: usize = FIRST_USER_THREAD;
+const FIRST_USER_THREAD: usize = 3;    //INDEX OF THE FIRST THREAD WHICH RUNS A PROGRAM
+const USER_PERIOD:       u64   = 1000; //MILLISECONDS BETWEEN TURNS OF A PRIORITY 0 PROGRAM THREAD, HALVED FOR EACH PRIORITY LEVEL
 
 //Thread Creation Function
-unsafe fn create_thread(thread_index: usize, map: PageMap, translator: &dyn AddressTranslator, mmap: &mut MapMemory, instruction_pointer: u64, code_selector: SegmentSelector, eflags_image: u32, stack_pointer: usize, stack_selector: SegmentSelector) {
+unsafe fn create_thread(thread_index: usize, map: PageMap, translator: &dyn AddressTranslator, mmap: &mut MapMemory, instruction_pointer: u64, code_selector: SegmentSelector, eflags_image: u32, stack_pointer: usize, stack_selector: SegmentSelector, arguments: (u64, u64)) {
     //Create stack
-    assert!(map.map_level == PageMapLevel::L4);
-    let start = LinearAddress(KERNEL_STACKS_PTR + PAGE_SIZE_4KIB * (thread_index * 4 + 1));
-    let end   = LinearAddress(KERNEL_STACKS_PTR + PAGE_SIZE_4KIB * (thread_index * 4 + 4));
-    virtual_memory_editor(map, mmap, start, end);
-    let rsp = (KERNEL_STACKS_PTR as *mut u64).byte_add(PAGE_SIZE_4KIB * (thread_index * 4 + 4));
+    assert!(map.map_level == root_level());
+    virtual_memory_editor(map, mmap, kernel_stack_start(thread_index), kernel_stack_end(thread_index));
+    let rsp = kernel_stack_end(thread_index).0 as *mut u64;
     //Write stack frame
     write_volatile(rsp.sub(1), u16::from(stack_selector) as u64);
     write_volatile(rsp.sub(2), stack_pointer as u64);
@@ -796,24 +1218,220 @@ unsafe fn create_thread(thread_index: usize, map: PageMap, translator: &dyn Addr
     for i in 5..53 {
         write_volatile((stack_pointer as *mut u64).sub(i), 0);
     }
+    for i in 6..21 {
+        write_volatile(rsp.sub(i), 0);
+    }
+    //Pass arguments in RDI and RSI
+    write_volatile(rsp.sub(16), arguments.0);
+    write_volatile(rsp.sub(17), arguments.1);
     //Save stack pointer
     TASK_STACKS[thread_index] = rsp.sub(20) as u64;
 }
 
+//Kernel Stack Bounds (the first page of each thread's area is left unmapped as a guard)
+fn kernel_stack_start(thread_index: usize) -> LinearAddress {LinearAddress(KERNEL_STACKS_PTR + PAGE_SIZE_4KIB * (thread_index * 4 + 1))}
+fn kernel_stack_end(thread_index: usize)   -> LinearAddress {LinearAddress(KERNEL_STACKS_PTR + PAGE_SIZE_4KIB * (thread_index * 4 + 4))}
+
+//Terminate the current process after a fault in user code
+unsafe fn fault_current_process() -> ! {
+    if let Ok(process) = thread_process(ThreadID(TASK_INDEX as u64)) {
+        if process != KERNEL_PROCESS {
+            let invalidator = target_page_map(process).unwrap();
+            let mut unmap = UnmapMemory {
+                allocator: &*GLOBAL_ALLOCATOR_POINTER.unwrap(),
+                translator: &*GLOBAL_TRANSLATOR_POINTER.unwrap(),
+                invalidator: &invalidator,
+            };
+            let mut unmap_port = UnmapPort {
+                allocator: &*GLOBAL_ALLOCATOR_POINTER.unwrap(),
+                translator: &*GLOBAL_TRANSLATOR_POINTER.unwrap(),
+                invalidator: &invalidator,
+            };
+            if fault_process(process, GLOBAL_PAGE_MAP.unwrap(), &mut unmap, &mut unmap_port).is_ok() {
+                revoke_io_permissions(process);
+                //wait to be switched away from, an exited thread is never scheduled again
+                loop {sti(); hlt();}
+            }
+        }
+    }
+    loop {hlt();}
+}
+
+//Print what a linear address maps to in the kernel page map
+unsafe fn print_mapping(printer: &mut dyn Write, address: LinearAddress) {
+    let translator = match GLOBAL_TRANSLATOR_POINTER {Some(pointer) => &*pointer, None => return};
+    let map = match GLOBAL_PAGE_MAP {Some(map) => map, None => return};
+    match translate_address(map, address, &|physical| translator.translate(physical)) {
+        Ok(mapping) => {writeln!(printer, "MAPPING: {:016X} {:?} {} {:?}", mapping.physical.0, mapping.level, flag_letters(mapping.flags), mapping.flags.cache_mode);},
+        Err(error)  => {writeln!(printer, "MAPPING: {:?}", error);},
+    }
+}
+
+//Print every mapped region of the kernel page map
+unsafe fn print_regions(printer: &mut dyn Write) {
+    let translator = match GLOBAL_TRANSLATOR_POINTER {Some(pointer) => &*pointer, None => return};
+    let map = match GLOBAL_PAGE_MAP {Some(map) => map, None => return};
+    writeln!(printer, "LINEAR           SIZE (KIB)   PHYSICAL         FLAGS CACHE");
+    for region in PageWalker::new(map, &|physical| translator.translate(physical)).regions() {
+        write!(printer, "{:016X} {:12} ", region.linear.0, region.size / KIB);
+        match region.physical {
+            Some(physical) => {writeln!(printer, "{:016X} {}   {:?}", physical.0, flag_letters(region.flags), region.flags.cache_mode);},
+            None           => {writeln!(printer, "SCATTERED        {}   {:?}", flag_letters(region.flags), region.flags.cache_mode);},
+        }
+    }
+}
+
+//Report regions of the kernel page map which are both writable and executable
+unsafe fn audit_write_execute(printer: &mut KernelLog) {
+    let translator = match GLOBAL_TRANSLATOR_POINTER {Some(pointer) => &*pointer, None => return};
+    let map = match GLOBAL_PAGE_MAP {Some(map) => map, None => return};
+    let mut count = 0;
+    for region in PageWalker::new(map, &|physical| translator.translate(physical)).regions() {
+        if region.flags.write && !region.flags.execute_disable {
+            writeln!(printer.at(LogLevel::Debug), "  W^X: {:016X} {:12} KIB {}", region.linear.0, region.size / KIB, flag_letters(region.flags));
+            count += 1;
+        }
+    }
+    writeln!(printer, "Writable and Executable Regions: {}", count);
+}
+
+//Permissions of a mapping as letters (write, user, execute)
+fn flag_letters(flags: PageFlags) -> &'static str {
+    match (flags.write, flags.user, !flags.execute_disable) {
+        (true,  true,  true)  => "WUX",
+        (true,  true,  false) => "WU-",
+        (true,  false, true)  => "W-X",
+        (true,  false, false) => "W--",
+        (false, true,  true)  => "-UX",
+        (false, true,  false) => "-U-",
+        (false, false, true)  => "--X",
+        (false, false, false) => "---",
+    }
+}
+
+//User Stack Bounds (each thread has a 2MiB area below its initial stack pointer, the first page of which is left unmapped as a guard)
+fn user_stack_start(thread_index: usize) -> LinearAddress {LinearAddress(oct_to_usize_4(0, 0, thread_index - 1, 0, 0).unwrap() + PAGE_SIZE_4KIB)}
+fn user_stack_end(thread_index: usize)   -> LinearAddress {LinearAddress(oct_to_usize_4(0, 0, thread_index, 0, 0).unwrap())}
+
+//Test if a thread may be scheduled (futex waits whose deadline has passed are resumed so that they can time out)
+unsafe fn thread_ready(thread_index: usize) -> bool {
+    match THREADS.get(thread_index).map(|thread| thread.state) {
+        Ok(ThreadState::Ready)                        => true,
+        Ok(ThreadState::WaitFutex(_, Some(deadline))) => deadline <= GLOBAL_TIME.load(Ordering::Relaxed),
+        _                                             => false,
+    }
+}
+
+//First tick at or after a given time at which a program thread's turn comes
+unsafe fn user_thread_turn(thread_index: usize, from: u64) -> u64 {
+    let priority = THREADS.get(thread_index).map(|thread| thread.priority).unwrap_or(0);
+    next_occurrence(from, (milliseconds_to_ticks(USER_PERIOD) >> priority).max(1), 0)
+}
+
+//Choose a program thread whose turn has come between two times, preferring higher priorities and rotating between threads of equal priority
+unsafe fn user_thread_due(from: u64, until: u64) -> Option<usize> {
+    let mut chosen: Option<(usize, u8)> = None;
+    for offset in 1..=MAX_THREADS {
+        let thread_index = (LAST_USER_THREAD + offset) % MAX_THREADS;
+        if thread_index < FIRST_USER_THREAD || !thread_ready(thread_index) {continue}
+        let priority = THREADS.get(thread_index).map(|thread| thread.priority).unwrap_or(0);
+        if user_thread_turn(thread_index, from) > until {continue}
+        if chosen.map(|(_, chosen_priority)| priority > chosen_priority) != Some(false) {chosen = Some((thread_index, priority));}
+    }
+    if let Some((thread_index, _)) = chosen {LAST_USER_THREAD = thread_index;}
+    chosen.map(|(thread_index, _)| thread_index)
+}
+
+//Find the first tick at or after a given time at which any ready program thread's turn comes
+unsafe fn next_user_turn(from: u64) -> Option<u64> {
+    (FIRST_USER_THREAD..MAX_THREADS).filter(|thread_index| thread_ready(*thread_index)).map(|thread_index| user_thread_turn(thread_index, from)).min()
+}
+
+//Set the wall clock from a time read from the RTC
+unsafe fn set_wall_clock(date_time: DateTime) {
+    let now = MONOTONIC_CLOCK.as_mut().map(|clock| clock.nanoseconds()).unwrap_or(0);
+    WALL_CLOCK_BASE = u64::try_from(date_time.unix_time()).ok().map(|seconds| (seconds * NANOSECONDS).saturating_sub(now));
+}
+
+//Read the wall clock as a date and time, for file timestamps
+fn wall_clock_date_time() -> Option<DateTime> {
+    let nanoseconds = unsafe {syscall_wall_time()}.ok()?;
+    DateTime::from_unix_time((nanoseconds / NANOSECONDS) as i64).ok()
+}
+
+//Current tick, counted at the scheduler tick rate from the monotonic clock
+unsafe fn current_tick() -> u64 {
+    MONOTONIC_CLOCK.as_mut().map(|clock| nanoseconds_to_ticks(clock.nanoseconds(), TICK_RATE)).unwrap_or(0)
+}
+
+//Ticks in a number of milliseconds at the scheduler tick rate
+unsafe fn milliseconds_to_ticks(milliseconds: u64) -> u64 {
+    nanoseconds_to_ticks(milliseconds.saturating_mul(NANOSECONDS / 1000), TICK_RATE)
+}
+
+//Arm the LAPIC timer for the start of a tick, or with no deadline leave it disarmed
+unsafe fn arm_timer(deadline: Option<u64>) {
+    let clock = match &mut MONOTONIC_CLOCK {Some(clock) => clock, None => return};
+    let now = clock.nanoseconds();
+    //Wrapping clock sources must still be read before they wrap twice
+    let limit = now.saturating_add(clock.max_interval());
+    let target = match deadline {
+        Some(tick)                 => ticks_to_nanoseconds(tick.min(nanoseconds_to_ticks(limit, TICK_RATE)), TICK_RATE),
+        None if limit != u64::MAX => limit,
+        None => {
+            match SCHEDULER_TIMER {
+                lapic::TimerMode::TSCDeadline => lapic::tsc_deadline(0),
+                _                             => lapic::initial_count(0),
+            }
+            return
+        },
+    };
+    match SCHEDULER_TIMER {
+        lapic::TimerMode::TSCDeadline => lapic::tsc_deadline(clock.counter_at(target)),
+        _                             => lapic::one_shot(target.saturating_sub(now), LAPIC_FREQUENCY),
+    }
+}
+
+//Charge the TSC cycles since the last accounting point to the current thread
+unsafe fn account_cycles(kernel: bool) {
+    let now = rdtsc();
+    let cycles = now.wrapping_sub(ACCOUNTING_TSC);
+    ACCOUNTING_TSC = now;
+    if let Ok(thread) = THREADS.get_mut(TASK_INDEX) {
+        if kernel {thread.kernel_cycles += cycles} else {thread.user_cycles += cycles}
+    }
+}
+
 //Scheduler
 unsafe extern "sysv64" fn scheduler() -> u64 {
-    //Read current time
-    let time = GLOBAL_TIME.load(Ordering::Relaxed);
+    //Charge the outgoing thread in the mode it was interrupted in (the code segment of its interrupt frame follows the saved general registers and instruction pointer)
+    let interrupted_segment = *(TASK_STACKS[TASK_INDEX] as *const u64).add(16);
+    account_cycles(interrupted_segment & 0b11 == 0);
+    let previous = TASK_INDEX;
+    //Read the first unprocessed tick and the current tick (ticks from one to the other inclusive are processed, none if run twice in a tick)
+    let from = GLOBAL_TIME.load(Ordering::Relaxed);
+    let time = current_tick();
+    let next = from.max(time + 1);
     //Process thread to switch to
     TASK_INDEX = 
-    if time % 1000 == 0                                                                                   {3} else
+    if let Some(thread_index) = user_thread_due(from, time)                                               {thread_index} else
     if INPUT_PIPE.state  == RingBufferState::WriteWait                                                    {2} else
     if STRING_PIPE.state == RingBufferState::WriteWait || STRING_PIPE.state == RingBufferState::ReadBlock {1} else
                                                                                                           {0};
+    if TASK_INDEX != previous {
+        if let Ok(thread) = THREADS.get_mut(TASK_INDEX) {thread.switches += 1;}
+    }
     //Change task state segment to new task
-    TASK_STATE_SEGMENT.rsp0 = (KERNEL_STACKS_PTR as u64) + ((TASK_INDEX + 1) * 16 * KIB) as u64;
+    TASK_STATE_SEGMENT.tss.rsp0 = (KERNEL_STACKS_PTR as u64) + ((TASK_INDEX + 1) * 16 * KIB) as u64;
+    switch_io_permissions(thread_process(ThreadID(TASK_INDEX as u64)).unwrap_or(KERNEL_PROCESS));
+    switch_address_space(thread_process(ThreadID(TASK_INDEX as u64)).unwrap_or(KERNEL_PROCESS));
+    //Post signals for expired timers
+    expire_timers(from, time);
     //Update current time
-    GLOBAL_TIME.fetch_add(1, Ordering::Relaxed);
+    GLOBAL_TIME.store(next, Ordering::Relaxed);
+    //Arm the timer for the end of the time slice, or when idle for the next program thread turn or timer expiry
+    let deadline = if TASK_INDEX != 0 {Some(time + 1)} else {next_user_turn(next).into_iter().chain(next_timer_expiry(next)).chain(next_futex_deadline(next)).min()};
+    arm_timer(deadline);
     //Finish
     TASK_STACKS[TASK_INDEX]
 }
@@ -826,7 +1444,16 @@ fn read_loop() {unsafe {
     let printer = &mut *GLOBAL_WRITE_POINTER.unwrap();
     loop {
         write_volatile(&mut STRING_PIPE.state as *mut RingBufferState, RingBufferState::ReadBlock);
-        writeln!(printer, "{}", core::str::from_utf8(STRING_PIPE.read(&mut [0xFF; 4096])).unwrap());
+        let line = core::str::from_utf8(STRING_PIPE.read(&mut [0xFF; 4096])).unwrap();
+        writeln!(printer, "{}", line);
+        //Shell commands
+        match line.trim() {
+            "reboot"   => if let Err(error) = system_call_reboot()    {writeln!(printer, "REBOOT FAILED: {:?}", error);},
+            "poweroff" => if let Err(error) = system_call_power_off() {writeln!(printer, "POWER OFF FAILED: {:?}", error);},
+            "ps"       => print_snapshot(printer),
+            "maps"     => print_regions(printer),
+            _          => {},
+        }
         writeln!(printer, "SYSTEM CALL 00: 0x{:016X}", system_call_00());
         system_call_01();
         let a = system_call_02();
@@ -837,6 +1464,24 @@ fn read_loop() {unsafe {
     }
 }}
 
+//Print every process and thread with its CPU time and memory use
+fn print_snapshot(printer: &mut dyn Write) {
+    let mut snapshot = SystemSnapshot::EMPTY;
+    if let Err(error) = system_call_system_snapshot(&mut snapshot) {writeln!(printer, "SNAPSHOT FAILED: {:?}", error); return}
+    writeln!(printer, "UPTIME: {} MS", snapshot.uptime / 1_000_000);
+    writeln!(printer, "PROCESS PARENT THREADS MEMORY (KIB) EXIT CODE");
+    for process in snapshot.processes() {
+        write!(printer, "{:7} {:6} {:7} {:12}", process.process, process.parent, process.threads, process.memory / 1024);
+        if process.exited {writeln!(printer, " {}", process.exit_code);} else {writeln!(printer);}
+    }
+    writeln!(printer, "THREAD PROCESS PRIORITY USER (MS) KERNEL (MS) SWITCHES WAKEUPS STATUS");
+    for thread in snapshot.threads() {
+        let user = snapshot.nanoseconds(thread.user_cycles) / 1_000_000;
+        let kernel = snapshot.nanoseconds(thread.kernel_cycles) / 1_000_000;
+        writeln!(printer, "{:6} {:7} {:8} {:9} {:11} {:8} {:7} {:?}", thread.thread, thread.process, thread.priority, user, kernel, thread.switches, thread.wakeups, thread.status);
+    }
+}
+
 //Thread 2: Pipe Write
 fn byte_loop() {unsafe {
     loop {
@@ -862,6 +1507,9 @@ unsafe fn ps2_keyboard() {
         let mut buffer = [InputEvent{device_id: 0xFF, event_type: InputEventType::Blank, event_id: 0, event_data: 0}; 512];
         let input_events = INPUT_PIPE.read(&mut buffer);
         for input_event in input_events {
+            //The input line and scrollback are drawn on the screen, which belongs to the display server once handed over
+            //Keys typed after the handover are discarded rather than queued, as the kernel has no path to pass them on (the display server receives them by binding IRQ 1 to a keyboard driver instead)
+            if !CONSOLE_DISPLAY {continue}
             if input_event.event_type == InputEventType::DigitalKey {
                 match KeyID::try_from(input_event.event_id) {Ok(key_id) => {
                     match PressType::try_from(input_event.event_data) {Ok(press_type) => {
@@ -916,13 +1564,54 @@ unsafe fn ps2_keyboard() {
 //INT 20h-FFh: Immediate Return Interrupt
 unsafe extern "x86-interrupt" fn interrupt_immediate_return() {}
 
+//IOAPIC Routing
+static mut INTERRUPT_ROUTER: InterruptRouter = InterruptRouter::new();
+
 //INT 20h: PIT IRQ
-unsafe extern "x86-interrupt" fn interrupt_irq_00() {pic::end_irq(0x0);}
+unsafe extern "x86-interrupt" fn interrupt_irq_00() {lapic::end_int();}
+
+//INT 28h: RTC IRQ (the first update interrupt aligns the wall clock to the start of a second, after which it is masked)
+static mut RTC_INTERRUPT: u32 = 0;
+static mut RTC_CENTURY: Option<u8> = None;
+unsafe extern "x86-interrupt" fn interrupt_irq_08() {
+    if rtc::acknowledge() {
+        if let Ok(date_time) = DateTime::read(RTC_CENTURY) {set_wall_clock(date_time);}
+        rtc::set_update_interrupt(false);
+        INTERRUPT_ROUTER.set_masked(RTC_INTERRUPT, true);
+    }
+    lapic::end_int();
+}
 
-//INT 21h: PS/2 Keyboard IRQ
+//INT 20h-2Fh: ISA IRQs bound to ports (the line is masked until the binding process acknowledges it)
+const KERNEL_IRQS: [u8; 2] = [0x0, RTC_IRQ]; //ISA IRQS HANDLED BY THE KERNEL, WHICH CANNOT BE BOUND
+static mut USER_IRQ_LINES: [Option<u32>; ISA_IRQS] = [None; ISA_IRQS];
+unsafe fn deliver_irq(irq: u8) {
+    if let Some(interrupt) = USER_IRQ_LINES[irq as usize] {INTERRUPT_ROUTER.set_masked(interrupt, true);}
+    if let Some(port) = IRQ_BINDINGS[irq as usize] {
+        if let Ok(port) = PORTS.get_mut(port.0 as usize) {
+            let mut data = [0; MESSAGE_DATA_SIZE];
+            data[0] = irq as u64;
+            port.push(KernelMessage {data, handle: None});
+        }
+    }
+}
+
+//Return the keyboard line to the kernel when its binding is released (it may be masked awaiting acknowledgement), other lines stay masked until bound again
+unsafe fn irq_released(irq: u8) {
+    if irq == 0x1 {
+        if let Some(interrupt) = USER_IRQ_LINES[0x1] {INTERRUPT_ROUTER.set_masked(interrupt, false);}
+    }
+}
+
+//INT 21h: PS/2 Keyboard IRQ (passed to the port it is bound to, if a user-mode driver has bound it)
 static mut PS2_SCANCODES: [u8;9] = [0u8;9];
 static mut PS2_INDEX:   usize = 0x00;
 unsafe extern "x86-interrupt" fn interrupt_irq_01() {
+    if IRQ_BINDINGS[0x1].is_some() {
+        deliver_irq(0x1);
+        lapic::end_int();
+        return;
+    }
     while ps2::poll_output_buffer_status() {
         let scancode = ps2::read_output();
         PS2_SCANCODES[PS2_INDEX] = scancode;
@@ -937,13 +1626,13 @@ unsafe extern "x86-interrupt" fn interrupt_irq_01() {
                 ps2::Ps2Scan::Continue => {}
             }
             Err(error) => {
-                let printer = &mut *GLOBAL_WRITE_POINTER.unwrap();
+                let printer = &mut (*GLOBAL_LOG_POINTER.unwrap()).at(LogLevel::Error);
                 writeln!(printer, "PS/2 KEYBOARD ERROR: {:?} | {}", &PS2_SCANCODES[0..PS2_INDEX], error);
                 PS2_INDEX = 0;
             }
         }
     }
-    pic::end_irq(0x01).unwrap();
+    lapic::end_int();
     //asm!("INT 80h");
 }
 
@@ -1006,6 +1695,7 @@ unsafe extern "x86-interrupt" fn interrupt_irq_01() {
     //Code
     "PUSH RBX", "PUSH RBP", "PUSH R12", //Save registers
     "PUSH R13", "PUSH R14", "PUSH R15", //Save registers
+    "MOV R8, [RSP+56]",                 //Load caller code segment
     "CALL {handler}",                   //Call handler
     "POP R15", "POP R14", "POP R13",    //Load registers
     "POP R12", "POP RBP", "POP RBX",    //Load registers
@@ -1021,9 +1711,19 @@ extern "x86-interrupt" fn interrupt_spurious() {unsafe {lapic::end_int()}}
 
 
 // PANIC HANDLER
+#[cfg(test)]
+#[panic_handler]
+fn panic_handler(panic_info: &PanicInfo) -> ! {
+    ktest::panic(panic_info)
+}
+
+#[cfg(not(test))]
 #[panic_handler]
 unsafe fn panic_handler(panic_info: &PanicInfo) -> ! {
     cli();                                                            //Turn off interrupts
+    if let Some(log_pointer) = GLOBAL_LOG_POINTER {                   //Check for presence of kernel log
+        (*log_pointer).threshold = LogLevel::Debug;                   //Print the halt regardless of log level
+    }
     if let Some(printer_pointer) = GLOBAL_WRITE_POINTER {             //Check for presence of write routines
         let printer = &mut *printer_pointer;                          //Find write routines
         write!(printer, "\nKernel Halt: ");                           //Begin writing
@@ -1040,6 +1740,62 @@ unsafe fn panic_handler(panic_info: &PanicInfo) -> ! {
 }
 
 
+// KERNEL LOG
+//Screen output stops once a display server maps the framebuffer, leaving only serial output
+static mut CONSOLE_DISPLAY: bool = true;
+
+//Kernel output, printed to the screen and optionally mirrored to serial
+struct KernelLog {
+    window:    *mut PrintWindow::<PRINT_LINES, PRINT_HEIGHT, PRINT_WIDTH, ColorBGRX, CharacterTwoTone<ColorBGRX>>,
+    serial:    Option<SerialPort>,
+    threshold: LogLevel,
+}
+impl KernelLog {
+    //Writer for messages of a given level
+    fn at(&mut self, level: LogLevel) -> LogLine {
+        LogLine {log: self, level}
+    }
+
+    //Write a message if its level is within the threshold
+    fn write_level(&mut self, level: LogLevel, s: &str) -> core::fmt::Result {
+        if level == LogLevel::Quiet || level > self.threshold {return Ok(())}
+        self.write_output(s)
+    }
+
+    //Write regardless of the threshold
+    fn write_output(&mut self, s: &str) -> core::fmt::Result {
+        if let Some(serial) = &mut self.serial {serial.write_str(s)?;}
+        unsafe {if CONSOLE_DISPLAY {(*self.window).write_str(s)} else {Ok(())}}
+    }
+}
+impl Write for KernelLog {
+    //Unmarked messages are boot progress
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_level(LogLevel::Info, s)
+    }
+}
+
+//Program output (console threads, the print system call, and kernel halts), which shares the log's screen and serial port but is not a diagnostic and so is never filtered by log level
+struct ProgramOutput;
+static mut PROGRAM_OUTPUT: ProgramOutput = ProgramOutput;
+impl Write for ProgramOutput {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        unsafe {match GLOBAL_LOG_POINTER {Some(log_pointer) => (*log_pointer).write_output(s), None => Ok(())}}
+    }
+}
+
+//Kernel output at a given level
+struct LogLine<'l> {
+    log:   &'l mut KernelLog,
+    level: LogLevel,
+}
+impl<'l> Write for LogLine<'l> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.log.write_level(self.level, s)
+    }
+}
+
+
 // PIPING
 #[repr(C)]
 struct RingBuffer<TYPE, const SIZE: usize> {
@@ -1081,39 +1837,142 @@ pub enum RingBufferState {
 
 
 // TASK STATE SEGMENT
-pub static mut TASK_STATE_SEGMENT: TaskStateSegment = TaskStateSegment {
-    _0:    0,
-    rsp0:  0,
-    rsp1:  0,
-    rsp2:  0,
-    _1:    0,
-    ist1:  0,
-    ist2:  0,
-    ist3:  0,
-    ist4:  0,
-    ist5:  0,
-    ist6:  0,
-    ist7:  0,
-    _2:    0,
-    _3:    0,
-    iomba: 0,
+pub static mut TASK_STATE_SEGMENT: TaskStateSegmentIo = TaskStateSegmentIo {
+    tss: TaskStateSegment {
+        _0:    0,
+        rsp0:  0,
+        rsp1:  0,
+        rsp2:  0,
+        _1:    0,
+        ist1:  0,
+        ist2:  0,
+        ist3:  0,
+        ist4:  0,
+        ist5:  0,
+        ist6:  0,
+        ist7:  0,
+        _2:    0,
+        _3:    0,
+        iomba: TaskStateSegmentIo::IOMBA,
+    },
+    bitmap: IoPermissionBitmap::new(),
 };
 
+//Process whose granted I/O ports are allowed in the bitmap, which is rebuilt when a thread of another process is switched to
+static mut IO_PERMISSION_PROCESS: ProcessID = KERNEL_PROCESS;
+static mut IO_PERMISSION_ALLOWED: bool = false; //Some port is allowed, so the bitmap must be cleared before reuse
+unsafe fn switch_io_permissions(process: ProcessID) {
+    if process == IO_PERMISSION_PROCESS {return}
+    if IO_PERMISSION_ALLOWED {
+        TASK_STATE_SEGMENT.bitmap.clear();
+        IO_PERMISSION_ALLOWED = false;
+    }
+    for (_, grant) in IO_PORT_GRANTS.iter() {
+        if grant.process == process {
+            TASK_STATE_SEGMENT.bitmap.set(grant.first, grant.count, true).unwrap();
+            IO_PERMISSION_ALLOWED = true;
+        }
+    }
+    IO_PERMISSION_PROCESS = process;
+}
+
+//Clear the bitmap of an exited process whose ports are allowed in it, as a new process reusing its ID would otherwise take them over
+unsafe fn revoke_io_permissions(process: ProcessID) {
+    if process != IO_PERMISSION_PROCESS {return}
+    if IO_PERMISSION_ALLOWED {
+        TASK_STATE_SEGMENT.bitmap.clear();
+        IO_PERMISSION_ALLOWED = false;
+    }
+    IO_PERMISSION_PROCESS = KERNEL_PROCESS;
+}
+
+//Load the page map of a process when a thread of another address space is switched to, keeping the TLB entries of its PCID if it still holds one
+unsafe fn switch_address_space(process: ProcessID) {
+    let page_map = match PROCESSES.get(process.0 as usize) {Ok(process) => process.page_map_address, Err(_) => return};
+    if page_map.0 == read_cr3_address().0 {return}
+    if pcid_enabled() {
+        let (pcid, fresh) = PCIDS.assign(page_map);
+        load_page_map(page_map, pcid, fresh);
+    }
+    else {load_page_map(page_map, 0, true);}
+}
+
 
 // SYSTEM CALLS
 //Handle
 #[inline(never)]
-extern "sysv64" fn syscall_handler(call_number: u64, arg1: u64, arg2: u64, arg3: u64) -> (u64, u64) {
+extern "sysv64" fn syscall_handler(call_number: u64, arg1: u64, arg2: u64, arg3: u64, caller_segment: u64) -> (u64, u64) {
     let mut ret = (0, 0);
-    match call_number {
-        0 => {ret.0 = syscall_handler_00()},
-        1 => {syscall_handler_01()}
-        2 => {ret.0 = syscall_handler_02()}
-        _ => panic!("Invalid System Call")
-    }
+    unsafe {account_cycles(caller_segment & 0b11 == 0)}
+    unsafe {match call_number {
+        SYSTEM_CALL_DUMMY_RETURN     => {ret.0 = syscall_handler_00()},
+        SYSTEM_CALL_DUMMY_PRINT      => {syscall_handler_01()}
+        SYSTEM_CALL_TIME             => {ret.0 = syscall_handler_02()}
+        SYSTEM_CALL_HANDLE_CLOSE     => {ret = syscall_return(syscall_handle_close(Handle(arg1)))}
+        SYSTEM_CALL_HANDLE_DUPLICATE => {ret = syscall_return(syscall_handle_duplicate(Handle(arg1), HandleRights(arg2)))}
+        SYSTEM_CALL_PORT_CREATE      => {ret = syscall_return(syscall_port_create())}
+        SYSTEM_CALL_PORT_SEND        => {ret = syscall_return(syscall_port_send(Handle(arg1), arg2))}
+        SYSTEM_CALL_PORT_RECEIVE     => {ret = syscall_return(syscall_port_receive(Handle(arg1), arg2))}
+        SYSTEM_CALL_MEMORY_PORT_MAP  => {ret = syscall_return(syscall_memory_port_map(Handle(arg1), arg2))}
+        SYSTEM_CALL_TIMER_CREATE     => {ret = syscall_return(syscall_timer_create(arg1, arg2))}
+        SYSTEM_CALL_EXIT             => {syscall_exit(arg1)}
+        SYSTEM_CALL_WAIT             => {ret = syscall_return(syscall_wait(Handle(arg1)))}
+        SYSTEM_CALL_SIGNAL_THREAD    => {ret = syscall_return(syscall_signal_thread(Handle(arg1)))}
+        SYSTEM_CALL_SIGNAL_SEND      => {ret = syscall_return(syscall_signal_send(Handle(arg1), arg2))}
+        SYSTEM_CALL_SIGNAL_MASK      => {ret = syscall_return(syscall_signal_mask(arg1))}
+        SYSTEM_CALL_SIGNAL_POLL      => {ret = syscall_return(syscall_signal_poll())}
+        SYSTEM_CALL_SIGNAL_WAIT      => {ret = syscall_return(syscall_signal_wait())}
+        SYSTEM_CALL_WALL_TIME        => {ret = syscall_return(syscall_wall_time())}
+        SYSTEM_CALL_REBOOT           => {ret = syscall_return(syscall_reboot())}
+        SYSTEM_CALL_POWER_OFF        => {ret = syscall_return(syscall_power_off())}
+        SYSTEM_CALL_FRAMEBUFFER_OPEN => {ret = syscall_return(syscall_framebuffer_open(arg1))}
+        SYSTEM_CALL_INTERRUPT_BIND   => {ret = syscall_return(syscall_interrupt_bind(arg1, Handle(arg2)))}
+        SYSTEM_CALL_INTERRUPT_ACK    => {ret = syscall_return(syscall_interrupt_acknowledge(arg1, Handle(arg2)))}
+        SYSTEM_CALL_IO_PORT_GRANT    => {ret = syscall_return(syscall_io_port_grant(Handle(arg1), arg2, arg3))}
+        SYSTEM_CALL_SYSTEM_SNAPSHOT  => {ret = syscall_return(syscall_system_snapshot(arg1))}
+        SYSTEM_CALL_FUTEX_WAIT       => {ret = syscall_return(syscall_futex_wait(arg1, arg2, arg3))}
+        SYSTEM_CALL_FUTEX_WAKE       => {ret = syscall_return(syscall_futex_wake(arg1, arg2))}
+        SYSTEM_CALL_MEMORY_PROTECT   => {ret = syscall_return(syscall_memory_protect(arg1, arg2, arg3))}
+        SYSTEM_CALL_MODULE_OPEN      => {ret = syscall_return(syscall_module_open(arg1, arg2, arg3))}
+        _                            => {ret.0 = ReturnCode::UnsupportedFeature as u64}
+    }}
+    unsafe {account_cycles(true)}
     ret
 }
 
+//Convert the result of a system call into its return registers
+fn syscall_return(result: Result<u64, ReturnCode>) -> (u64, u64) {
+    match result {
+        Ok(value)  => (ReturnCode::NoError as u64, value),
+        Err(error) => (error as u64, 0),
+    }
+}
+
+//Process which made the current system call
+unsafe fn syscall_process() -> Result<&'static mut Process, ReturnCode> {
+    process(thread_process(ThreadID(TASK_INDEX as u64))?)
+}
+
+//Check a structure passed by pointer is accessible to the process which made the current system call
+unsafe fn syscall_pointer<T>(address: u64, write: bool) -> Result<*mut T, ReturnCode> {
+    let start = address as usize;
+    if start % core::mem::align_of::<T>() != 0 {return Err(ReturnCode::UnalignedAddress)}
+    let end = start.checked_add(core::mem::size_of::<T>()).ok_or(ReturnCode::MemoryOutOfBounds)?;
+    let mut check = CheckUserAccess {translator: &*GLOBAL_TRANSLATOR_POINTER.ok_or(ReturnCode::NotReady)?, write};
+    virtual_memory_editor(GLOBAL_PAGE_MAP.ok_or(ReturnCode::NotReady)?, &mut check, LinearAddress(start), LinearAddress(end))?;
+    Ok(start as *mut T)
+}
+
+//Check that a range of bytes is readable by the current process
+unsafe fn syscall_bytes(address: u64, length: u64) -> Result<&'static [u8], ReturnCode> {
+    let start = address as usize;
+    let end = start.checked_add(length as usize).ok_or(ReturnCode::MemoryOutOfBounds)?;
+    if start == end {return Ok(&[])}
+    let mut check = CheckUserAccess {translator: &*GLOBAL_TRANSLATOR_POINTER.ok_or(ReturnCode::NotReady)?, write: false};
+    virtual_memory_editor(GLOBAL_PAGE_MAP.ok_or(ReturnCode::NotReady)?, &mut check, LinearAddress(start), LinearAddress(end))?;
+    Ok(core::slice::from_raw_parts(start as *const u8, length as usize))
+}
+
 #[inline(never)]
 extern "sysv64" fn syscall_handler_00() -> u64 {
     0x1111_2222_3333_4444
@@ -1129,5 +1988,413 @@ extern "sysv64" fn syscall_handler_01() {
 
 #[inline(never)]
 extern "sysv64" fn syscall_handler_02() -> u64 {
-    GLOBAL_TIME.load(Ordering::SeqCst)
+    unsafe {MONOTONIC_CLOCK.as_mut().map(|clock| clock.nanoseconds()).unwrap_or(0)}
+}
+
+//Read the wall clock, in nanoseconds since the Unix epoch
+unsafe fn syscall_wall_time() -> Result<u64, ReturnCode> {
+    let base = WALL_CLOCK_BASE.ok_or(ReturnCode::NotReady)?;
+    Ok(base + MONOTONIC_CLOCK.as_mut().ok_or(ReturnCode::NotReady)?.nanoseconds())
+}
+
+//Power management state taken from the ACPI tables during boot
+static mut POWER_FADT: Option<Fadt> = None;
+static mut POWER_S5: Option<SleepType> = None;
+
+//Check the process which made the current system call is the kernel or the director
+unsafe fn syscall_privileged() -> Result<(), ReturnCode> {
+    let process = thread_process(ThreadID(TASK_INDEX as u64))?;
+    if process == KERNEL_PROCESS || DIRECTOR.as_ref().map(|director| director.is(process)) == Some(true) {Ok(())}
+    else {Err(ReturnCode::AccessDenied)}
+}
+
+//Reset the system
+unsafe fn syscall_reboot() -> Result<u64, ReturnCode> {
+    syscall_privileged()?;
+    power::reset(POWER_FADT.as_ref(), physical_memory_ptr(root_level()) as u64);
+    Err(ReturnCode::TimeOut)
+}
+
+//Power off the system through ACPI S5
+unsafe fn syscall_power_off() -> Result<u64, ReturnCode> {
+    syscall_privileged()?;
+    let fadt = POWER_FADT.as_ref().ok_or(ReturnCode::UnsupportedFeature)?;
+    power::power_off(fadt, POWER_S5.ok_or(ReturnCode::UnsupportedFeature)?)?;
+    Ok(0)
+}
+
+//Framebuffer memory port and layout, recorded during boot
+static mut FRAMEBUFFER: Option<(MemPort, FramebufferInfo)> = None;
+
+//Data modules named in the boot manifest, recorded during boot
+const MAX_DATA_MODULES: usize = 8;
+static mut DATA_MODULES: [Option<DataModule>; MAX_DATA_MODULES] = [None; MAX_DATA_MODULES];
+#[derive(Clone, Copy)]
+struct DataModule {
+    name: &'static str,
+    size: usize,
+    port: MemPort,
+}
+
+//Open a memory port covering the framebuffer, so that a display server can map it and take over the screen
+unsafe fn syscall_framebuffer_open(info: u64) -> Result<u64, ReturnCode> {
+    syscall_privileged()?;
+    let (port, framebuffer_info) = FRAMEBUFFER.ok_or(ReturnCode::UnsupportedFeature)?;
+    let pointer = syscall_pointer::<FramebufferInfo>(info, true)?;
+    let process = syscall_process()?;
+    let memory_port = MemoryPortID(MEMORY_PORTS.insert(port)? as u64);
+    match process.handles.open(KernelObject::MemoryPort(memory_port), HandleRights::ALL) {
+        Ok(handle) => {write_volatile(pointer, framebuffer_info); Ok(handle.0)},
+        Err(error) => {MEMORY_PORTS.remove(memory_port.0 as usize); Err(error)},
+    }
+}
+
+//Open a read-only memory port covering a data module, given its manifest name, so that the director can map it or hand it to a server
+unsafe fn syscall_module_open(name: u64, length: u64, size: u64) -> Result<u64, ReturnCode> {
+    syscall_privileged()?;
+    let name = syscall_bytes(name, length)?;
+    let module = DATA_MODULES.iter().flatten().find(|module| module.name.as_bytes() == name).ok_or(ReturnCode::NotFound)?;
+    let pointer = syscall_pointer::<u64>(size, true)?;
+    let process = syscall_process()?;
+    let memory_port = MemoryPortID(MEMORY_PORTS.insert(module.port)? as u64);
+    match process.handles.open(KernelObject::MemoryPort(memory_port), HandleRights::READ | HandleRights::MAP | HandleRights::DUPLICATE | HandleRights::TRANSFER) {
+        Ok(handle) => {write_volatile(pointer, module.size as u64); Ok(handle.0)},
+        Err(error) => {MEMORY_PORTS.remove(memory_port.0 as usize); Err(error)},
+    }
+}
+
+//Bind an ISA IRQ line to a port, unmasking it
+unsafe fn syscall_interrupt_bind(irq: u64, port: Handle) -> Result<u64, ReturnCode> {
+    syscall_privileged()?;
+    let port = syscall_process()?.handles.check_port(port, HandleRights::WRITE)?;
+    let irq = u8::try_from(irq).map_err(|_| ReturnCode::IndexOutOfBounds)?;
+    if KERNEL_IRQS.contains(&irq) {return Err(ReturnCode::AccessDenied)}
+    let interrupt = USER_IRQ_LINES.get(irq as usize).ok_or(ReturnCode::IndexOutOfBounds)?.ok_or(ReturnCode::UnsupportedFeature)?;
+    if IRQ_BINDINGS[irq as usize].is_some() {return Err(ReturnCode::AlreadyStarted)}
+    IRQ_BINDINGS[irq as usize] = Some(port);
+    INTERRUPT_ROUTER.set_masked(interrupt, false)?;
+    Ok(0)
+}
+
+//Unmask an ISA IRQ line after its message has been handled, given a readable handle to the port it is bound to
+unsafe fn syscall_interrupt_acknowledge(irq: u64, port: Handle) -> Result<u64, ReturnCode> {
+    let port = syscall_process()?.handles.check_port(port, HandleRights::READ)?;
+    let irq = u8::try_from(irq).map_err(|_| ReturnCode::IndexOutOfBounds)?;
+    if *IRQ_BINDINGS.get(irq as usize).ok_or(ReturnCode::IndexOutOfBounds)? != Some(port) {return Err(ReturnCode::AccessDenied)}
+    INTERRUPT_ROUTER.set_masked(USER_IRQ_LINES[irq as usize].ok_or(ReturnCode::UnsupportedFeature)?, false)?;
+    Ok(0)
+}
+
+//Allow a process to access a range of I/O ports from ring 3
+unsafe fn syscall_io_port_grant(process: Handle, first: u64, count: u64) -> Result<u64, ReturnCode> {
+    syscall_privileged()?;
+    let process = syscall_process()?.handles.check_process(process, HandleRights::WRITE)?;
+    let first = u16::try_from(first).map_err(|_| ReturnCode::IndexOutOfBounds)?;
+    if count == 0 || first as u64 + count > IO_PORT_COUNT as u64 {return Err(ReturnCode::IndexOutOfBounds)}
+    IO_PORT_GRANTS.insert(IoPortGrant {process, first, count: count as u32})?;
+    //Grants to the process whose ports are in the bitmap take effect immediately
+    if process == IO_PERMISSION_PROCESS {
+        TASK_STATE_SEGMENT.bitmap.set(first, count as u32, true).map_err(|_| ReturnCode::IndexOutOfBounds)?;
+        IO_PERMISSION_ALLOWED = true;
+    }
+    Ok(0)
+}
+
+//Record every process and thread with its state, CPU time, and memory use
+unsafe fn syscall_system_snapshot(snapshot: u64) -> Result<u64, ReturnCode> {
+    let snapshot = &mut *syscall_pointer::<SystemSnapshot>(snapshot, true)?;
+    *snapshot = SystemSnapshot::EMPTY;
+    snapshot.uptime = MONOTONIC_CLOCK.as_mut().map(|clock| clock.nanoseconds()).unwrap_or(0);
+    snapshot.tsc_frequency = TSC_FREQUENCY;
+    for (slot, (index, process)) in PROCESSES.iter().take(SNAPSHOT_PROCESSES).enumerate() {
+        let id = ProcessID(index as u64);
+        snapshot.processes[slot] = ProcessSnapshot {
+            process:   id.0,
+            parent:    process_parent(id).unwrap_or(id).0,
+            threads:   CHILD_THREADS.iter().filter(|(_, relation)| relation.process == id).count() as u64,
+            memory:    PROCESS_MEMORY.iter().filter(|(_, area)| area.process == id).map(|(_, area)| (area.end.0 - area.start.0) as u64).sum(),
+            exited:    process.exit_code.is_some(),
+            exit_code: process.exit_code.unwrap_or(0),
+        };
+        snapshot.process_count += 1;
+    }
+    for (slot, (index, thread)) in THREADS.iter().take(SNAPSHOT_THREADS).enumerate() {
+        let status = match thread.state {
+            _ if index == TASK_INDEX    => ThreadStatus::Running,
+            ThreadState::Ready          => ThreadStatus::Ready,
+            ThreadState::WaitProcess(_) => ThreadStatus::WaitProcess,
+            ThreadState::WaitSignal     => ThreadStatus::WaitSignal,
+            ThreadState::WaitFutex(..)  => ThreadStatus::WaitFutex,
+            ThreadState::Exited         => ThreadStatus::Exited,
+        };
+        snapshot.threads[slot] = ThreadSnapshot {
+            thread:        index as u64,
+            process:       thread_process(ThreadID(index as u64)).unwrap_or(KERNEL_PROCESS).0,
+            status,
+            priority:      thread.priority as u64,
+            user_cycles:   thread.user_cycles,
+            kernel_cycles: thread.kernel_cycles,
+            switches:      thread.switches,
+            wakeups:       thread.wakeups,
+        };
+        snapshot.thread_count += 1;
+    }
+    Ok(0)
+}
+
+//Close a handle
+unsafe fn syscall_handle_close(handle: Handle) -> Result<u64, ReturnCode> {
+    let entry = syscall_process()?.handles.close(handle)?;
+    release_object(entry.object);
+    Ok(0)
+}
+
+//Duplicate a handle with equal or fewer rights
+unsafe fn syscall_handle_duplicate(handle: Handle, rights: HandleRights) -> Result<u64, ReturnCode> {
+    let process = syscall_process()?;
+    let entry = process.handles.check(handle, HandleRights::DUPLICATE)?;
+    if !entry.rights.contains(rights) {return Err(ReturnCode::AccessDenied)}
+    Ok(process.handles.open(entry.object, rights)?.0)
+}
+
+//Create a message port
+unsafe fn syscall_port_create() -> Result<u64, ReturnCode> {
+    let process = syscall_process()?;
+    let port = PortID(PORTS.insert(MessagePort::new())? as u64);
+    match process.handles.open(KernelObject::Port(port), HandleRights::ALL) {
+        Ok(handle) => Ok(handle.0),
+        Err(error) => {PORTS.remove(port.0 as usize); Err(error)},
+    }
+}
+
+//Send a message through a port, moving any attached handle out of the sending process
+unsafe fn syscall_port_send(port: Handle, message: u64) -> Result<u64, ReturnCode> {
+    let process = syscall_process()?;
+    let port = process.handles.check_port(port, HandleRights::WRITE)?;
+    let message = read_volatile(syscall_pointer::<Message>(message, false)?);
+    let handle = if message.handle == Handle::NONE {None} else {Some(process.handles.check(message.handle, HandleRights::TRANSFER)?)};
+    PORTS.get_mut(port.0 as usize)?.push(KernelMessage {data: message.data, handle})?;
+    if handle.is_some() {process.handles.close(message.handle)?;}
+    Ok(0)
+}
+
+//Receive a message from a port, opening any attached handle in the receiving process
+unsafe fn syscall_port_receive(port: Handle, message: u64) -> Result<u64, ReturnCode> {
+    let process = syscall_process()?;
+    let port = PORTS.get_mut(process.handles.check_port(port, HandleRights::READ)?.0 as usize)?;
+    let pointer = syscall_pointer::<Message>(message, true)?;
+    let kernel_message = *port.peek()?;
+    let mut received = Message {data: kernel_message.data, handle: Handle::NONE};
+    if let Some(entry) = kernel_message.handle {received.handle = process.handles.open(entry.object, entry.rights)?;}
+    port.pop()?;
+    write_volatile(pointer, received);
+    Ok(0)
+}
+
+//Map a memory port into the address space of the calling process
+unsafe fn syscall_memory_port_map(memory_port: Handle, address: u64) -> Result<u64, ReturnCode> {
+    let process = syscall_process()?;
+    let entry = process.handles.check(memory_port, HandleRights::MAP)?;
+    let port_id = match entry.object {KernelObject::MemoryPort(id) => id, _ => return Err(ReturnCode::InvalidIdentifier)};
+    let memory_port = *MEMORY_PORTS.get(port_id.0 as usize)?;
+    if address as usize % page_size(memory_port.level) != 0 {return Err(ReturnCode::UnalignedAddress)}
+    let map_port = MapPort {
+        allocator: &*GLOBAL_ALLOCATOR_POINTER.ok_or(ReturnCode::NotReady)?,
+        translator: &*GLOBAL_TRANSLATOR_POINTER.ok_or(ReturnCode::NotReady)?,
+        write: entry.rights.contains(HandleRights::WRITE),
+        user: true,
+        execute_disable: true,
+    };
+    let map = GLOBAL_PAGE_MAP.ok_or(ReturnCode::NotReady)?;
+    let size = page_size(memory_port.level);
+    let mut page = 0;
+    while page < memory_port.pages {
+        let physical = memory_port.address.0 + page * size;
+        let linear = address as usize + page * size;
+        //Cover aligned runs of 4KiB pages with 2MiB pages
+        let level = if memory_port.level == PageMapLevel::L1 && physical % PAGE_SIZE_2MIB == 0 && linear % PAGE_SIZE_2MIB == 0 && memory_port.pages - page >= PAGE_NUMBER_1 {PageMapLevel::L2} else {memory_port.level};
+        let page_port = MemPort {address: PhysicalAddress(physical), level, pages: 1, ..memory_port};
+        if let Err(error) = map_port.map(map, page_port, LinearAddress(linear)) {
+            //Unmap the pages already mapped, returning the error which stopped the mapping
+            let mut unmap_port = UnmapPort {allocator: map_port.allocator, translator: map_port.translator, invalidator: &PageInvalidator::ACTIVE};
+            if page > 0 {let _ = virtual_memory_editor(map, &mut unmap_port, LinearAddress(address as usize), LinearAddress(linear));}
+            return Err(error);
+        }
+        page += page_size(level) / size;
+    }
+    //Record the mapping so that it is removed when the process exits, and the port is kept until then
+    let end = LinearAddress(address as usize + memory_port.pages * size);
+    if let Err(error) = attach_port_mapping(thread_process(ThreadID(TASK_INDEX as u64))?, port_id, LinearAddress(address as usize), end) {
+        let mut unmap_port = UnmapPort {allocator: map_port.allocator, translator: map_port.translator, invalidator: &PageInvalidator::ACTIVE};
+        let _ = virtual_memory_editor(map, &mut unmap_port, LinearAddress(address as usize), end);
+        return Err(error);
+    }
+    //The kernel stops drawing once the framebuffer belongs to a display server
+    if FRAMEBUFFER.map(|(port, _)| port.address.0 == memory_port.address.0) == Some(true) && CONSOLE_DISPLAY {
+        writeln!((*GLOBAL_LOG_POINTER.ok_or(ReturnCode::NotReady)?).at(LogLevel::Info), "DISPLAY HANDED TO PROCESS {}, CONSOLE IS NOW SERIAL ONLY", thread_process(ThreadID(TASK_INDEX as u64))?.0);
+        CONSOLE_DISPLAY = false;
+    }
+    Ok(0)
+}
+
+//Create a timer, given its period and offset in milliseconds
+unsafe fn syscall_timer_create(divisor: u64, remainder: u64) -> Result<u64, ReturnCode> {
+    if divisor == 0 || remainder >= divisor {return Err(ReturnCode::InvalidData)}
+    //Timers count scheduler ticks, so periods shorter than a tick are rounded up to one
+    let divisor = milliseconds_to_ticks(divisor).max(1);
+    let remainder = milliseconds_to_ticks(remainder).min(divisor - 1);
+    let process = syscall_process()?;
+    let timer = TimerID(TIMERS.insert(Timer {divisor, remainder, process: thread_process(ThreadID(TASK_INDEX as u64))?})? as u64);
+    match process.handles.open(KernelObject::Timer(timer), HandleRights::ALL) {
+        Ok(handle) => Ok(handle.0),
+        Err(error) => {TIMERS.remove(timer.0 as usize); Err(error)},
+    }
+}
+
+//Exit the calling process
+unsafe fn syscall_exit(code: u64) -> ! {
+    let thread = ThreadID(TASK_INDEX as u64);
+    if let Ok(process) = thread_process(thread) {
+        if process != KERNEL_PROCESS {
+            let invalidator = target_page_map(process).unwrap();
+            let mut unmap = UnmapMemory {
+                allocator: &*GLOBAL_ALLOCATOR_POINTER.unwrap(),
+                translator: &*GLOBAL_TRANSLATOR_POINTER.unwrap(),
+                invalidator: &invalidator,
+            };
+            let mut unmap_port = UnmapPort {
+                allocator: &*GLOBAL_ALLOCATOR_POINTER.unwrap(),
+                translator: &*GLOBAL_TRANSLATOR_POINTER.unwrap(),
+                invalidator: &invalidator,
+            };
+            if let Err(error) = exit_process(process, code, GLOBAL_PAGE_MAP.unwrap(), &mut unmap, &mut unmap_port) {panic!("Process exit failed: {:?}", error)}
+            revoke_io_permissions(process);
+        }
+    }
+    //Wait to be switched away from, an exited thread is never scheduled again
+    loop {sti(); hlt();}
+}
+
+//Wait for a child process to exit and return its exit code
+unsafe fn syscall_wait(child: Handle) -> Result<u64, ReturnCode> {
+    let thread = ThreadID(TASK_INDEX as u64);
+    let process = thread_process(thread)?;
+    let child = PROCESSES.get(process.0 as usize)?.handles.check_process(child, HandleRights::READ)?;
+    if process_parent(child)? != process {return Err(ReturnCode::AccessDenied)}
+    //Block until the child exits
+    while PROCESSES.get(child.0 as usize)?.exit_code.is_none() {
+        THREADS.get_mut(thread.0 as usize)?.state = ThreadState::WaitProcess(child);
+        sti(); hlt(); cli();
+    }
+    //Reap child, which also closes every handle to it
+    let mut unmap = UnmapMemory {
+        allocator: &*GLOBAL_ALLOCATOR_POINTER.ok_or(ReturnCode::NotReady)?,
+        translator: &*GLOBAL_TRANSLATOR_POINTER.ok_or(ReturnCode::NotReady)?,
+        invalidator: &PageInvalidator::ACTIVE,
+    };
+    reap_process(child, GLOBAL_PAGE_MAP.ok_or(ReturnCode::NotReady)?, &mut unmap)
+}
+
+//Designate a thread of the calling process to receive its signals
+unsafe fn syscall_signal_thread(thread: Handle) -> Result<u64, ReturnCode> {
+    let process = thread_process(ThreadID(TASK_INDEX as u64))?;
+    let thread = PROCESSES.get(process.0 as usize)?.handles.check_thread(thread, HandleRights::WRITE)?;
+    set_signal_thread(process, thread)?;
+    Ok(0)
+}
+
+//Post a signal to another process
+unsafe fn syscall_signal_send(target: Handle, signal: u64) -> Result<u64, ReturnCode> {
+    let process = thread_process(ThreadID(TASK_INDEX as u64))?;
+    let target = PROCESSES.get(process.0 as usize)?.handles.check_process(target, HandleRights::WRITE)?;
+    if signal < SIGNAL_USER_FIRST {return Err(ReturnCode::AccessDenied)}
+    post_signal(target, signal)?;
+    Ok(0)
+}
+
+//Replace the signal mask of the calling process, returning the previous mask
+unsafe fn syscall_signal_mask(mask: u64) -> Result<u64, ReturnCode> {
+    let process = syscall_process()?;
+    let previous = process.signals_mask;
+    process.signals_mask = mask;
+    Ok(previous)
+}
+
+//Take the unmasked signals pending for the calling process without blocking
+unsafe fn syscall_signal_poll() -> Result<u64, ReturnCode> {
+    take_signals(thread_process(ThreadID(TASK_INDEX as u64))?)
+}
+
+//Block the signal thread of the calling process until an unmasked signal is pending
+unsafe fn syscall_signal_wait() -> Result<u64, ReturnCode> {
+    let thread = ThreadID(TASK_INDEX as u64);
+    let process = thread_process(thread)?;
+    if signal_thread(process)? != thread {return Err(ReturnCode::AccessDenied)}
+    loop {
+        let signals = take_signals(process)?;
+        if signals != 0 {return Ok(signals)}
+        THREADS.get_mut(thread.0 as usize)?.state = ThreadState::WaitSignal;
+        sti(); hlt(); cli();
+    }
+}
+
+//Block the calling thread while a word holds an expected value, until it is woken or the timeout in nanoseconds passes (u64::MAX for none)
+unsafe fn syscall_futex_wait(address: u64, expected: u64, timeout: u64) -> Result<u64, ReturnCode> {
+    let thread = ThreadID(TASK_INDEX as u64);
+    let word = syscall_pointer::<u32>(address, false)?;
+    if read_volatile(word) as u64 != expected {return Err(ReturnCode::StaleData)}
+    if timeout == 0 {return Err(ReturnCode::TimeOut)}
+    //Wake at the first tick after the timeout passes
+    let deadline = match timeout {
+        u64::MAX => None,
+        _        => Some(nanoseconds_to_ticks(MONOTONIC_CLOCK.as_mut().map(|clock| clock.nanoseconds()).unwrap_or(0).saturating_add(timeout), TICK_RATE) + 1),
+    };
+    THREADS.get_mut(thread.0 as usize)?.state = ThreadState::WaitFutex(address as usize, deadline);
+    //Block until woken through the address or resumed after the deadline
+    loop {
+        sti(); hlt(); cli();
+        let waiting = THREADS.get_mut(thread.0 as usize)?;
+        match waiting.state {
+            ThreadState::WaitFutex(_, Some(deadline)) if deadline <= GLOBAL_TIME.load(Ordering::Relaxed) => {waiting.wake(); return Err(ReturnCode::TimeOut)},
+            ThreadState::WaitFutex(..) => {},
+            _                          => return Ok(0),
+        }
+    }
+}
+
+//Wake up to a number of threads waiting on a word, returning how many were woken
+unsafe fn syscall_futex_wake(address: u64, count: u64) -> Result<u64, ReturnCode> {
+    syscall_pointer::<u32>(address, false)?;
+    Ok(wake_futex(address as usize, count))
+}
+
+//Change whether pages of memory owned by the calling process may be written or executed
+unsafe fn syscall_memory_protect(address: u64, length: u64, flags: u64) -> Result<u64, ReturnCode> {
+    let process = thread_process(ThreadID(TASK_INDEX as u64))?;
+    if flags & !(PROTECT_WRITE | PROTECT_EXECUTE) != 0 {return Err(ReturnCode::InvalidData)}
+    let write = flags & PROTECT_WRITE != 0;
+    let execute = flags & PROTECT_EXECUTE != 0;
+    if write && execute {return Err(ReturnCode::SecurityViolation)}
+    //Cover whole pages of memory the process owns
+    if address as usize % PAGE_SIZE_4KIB != 0 {return Err(ReturnCode::UnalignedAddress)}
+    let start = address as usize;
+    let end = start.checked_add(length as usize).and_then(|end| end.checked_add(PAGE_SIZE_4KIB - 1)).ok_or(ReturnCode::MemoryOutOfBounds)? / PAGE_SIZE_4KIB * PAGE_SIZE_4KIB;
+    if start == end {return Ok(0)}
+    if !owns_memory(process, LinearAddress(start), LinearAddress(end)) {return Err(ReturnCode::AccessDenied)}
+    //Check every page is mapped before changing any, so that only running out of memory to split a large page can leave the range part protected
+    let map = GLOBAL_PAGE_MAP.ok_or(ReturnCode::NotReady)?;
+    let translator = &*GLOBAL_TRANSLATOR_POINTER.ok_or(ReturnCode::NotReady)?;
+    let mut check = CheckUserAccess {translator, write: false};
+    virtual_memory_editor(map, &mut check, LinearAddress(start), LinearAddress(end))?;
+    //Large pages split as needed, and each changed page is invalidated
+    let mut protect = ProtectMemory {
+        allocator: &*GLOBAL_ALLOCATOR_POINTER.ok_or(ReturnCode::NotReady)?,
+        translator,
+        invalidator: &PageInvalidator::ACTIVE,
+        write,
+        user: true,
+        execute_disable: !execute,
+    };
+    virtual_memory_editor(map, &mut protect, LinearAddress(start), LinearAddress(end))?;
+    Ok(0)
 }
@@ -0,0 +1,119 @@
+// HELIUM: BOOT MANIFEST
+// Structs and functions for reading the boot manifest, which names the programs and data modules the kernel starts with
+//
+// The manifest is a text module whose path ends in "manifest.cfg". Each line takes one of the forms:
+//   init    <module path> [priority=<0-9>] [arguments...]
+//   program <module path> [priority=<0-9>] [arguments...]
+//   data    <module path> [name=<name>]
+// Empty lines and lines beginning with '#' are ignored. A module path matches any module whose path ends with it as whole path components.
+// Data modules are not executed, the director opens them by name (the path unless given a name) with the Module Open system call.
+
+
+// HEADER
+//Imports
+use gluon::noble::return_code::ReturnCode;
+
+//Constants
+pub const MANIFEST_SUFFIX: &str = "manifest.cfg"; //PATH ENDING WHICH MARKS A MODULE AS THE BOOT MANIFEST
+pub const MAX_PRIORITY:    u8   = 9;              //HIGHEST PRIORITY A PROGRAM MAY BE GIVEN
+
+
+// MANIFEST
+//Manifest Entry Kind
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ManifestKind {
+    Init,    //Program started first, which becomes the director
+    Program, //Program started after the init program
+    Data,    //Module which is not executed, such as a ramdisk or a font
+}
+
+//Manifest Entry
+#[derive(Clone, Copy, Debug)]
+pub struct ManifestEntry<'s> {
+    pub kind: ManifestKind,
+    pub path: &'s str,
+    pub name: &'s str,
+    pub priority: u8,
+    pub arguments: &'s str,
+}
+impl<'s> ManifestEntry<'s> {
+    //Test if this entry describes a module
+    pub fn matches(&self, module_path: &str) -> bool {
+        path_matches(module_path, self.path)
+    }
+}
+
+//Test if a module path is a path or ends with it as whole path components (so "b.elf" does not match "web.elf")
+pub fn path_matches(module_path: &str, path: &str) -> bool {
+    match module_path.strip_suffix(path) {
+        Some(prefix) => prefix.is_empty() || prefix.ends_with('/'),
+        None         => false,
+    }
+}
+
+//Manifest
+pub struct Manifest<'s> {
+    text: &'s str,
+}
+impl<'s> Manifest<'s> {
+    //Constructor
+    pub fn new(bytes: &'s [u8]) -> Result<Self, ReturnCode> {
+        let manifest = Self {text: core::str::from_utf8(bytes).map_err(|_| ReturnCode::InvalidCharacter)?};
+        //check every line once so that later lookups cannot fail
+        let mut init_count = 0;
+        for entry in manifest.entries() {
+            if entry?.kind == ManifestKind::Init {init_count += 1;}
+        }
+        if init_count > 1 {return Err(ReturnCode::InvalidData)}
+        Ok(manifest)
+    }
+
+    //Iterate over entries
+    pub fn entries(&self) -> impl Iterator<Item = Result<ManifestEntry<'s>, ReturnCode>> + 's {
+        self.text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')).map(parse_line)
+    }
+
+    //Find the entry describing a module
+    pub fn find(&self, module_path: &str) -> Option<ManifestEntry<'s>> {
+        self.entries().flatten().find(|entry| entry.matches(module_path))
+    }
+}
+
+//Parse a single manifest line
+fn parse_line(line: &str) -> Result<ManifestEntry, ReturnCode> {
+    let (kind, rest) = split_word(line);
+    let kind = match kind {
+        "init"    => ManifestKind::Init,
+        "program" => ManifestKind::Program,
+        "data"    => ManifestKind::Data,
+        _         => return Err(ReturnCode::InvalidData),
+    };
+    let (path, mut rest) = split_word(rest);
+    if path.is_empty() {return Err(ReturnCode::InvalidData)}
+    let mut entry = ManifestEntry {kind, path, name: path, priority: 0, arguments: ""};
+    //Options precede arguments
+    loop {
+        let (word, after) = split_word(rest);
+        if let Some(value) = word.strip_prefix("priority=") {
+            entry.priority = value.parse().map_err(|_| ReturnCode::InvalidData)?;
+            if entry.priority > MAX_PRIORITY {return Err(ReturnCode::InvalidData)}
+        }
+        else if let Some(value) = word.strip_prefix("name=") {
+            entry.name = value;
+        }
+        else {break}
+        rest = after;
+    }
+    if kind == ManifestKind::Data && !rest.is_empty() {return Err(ReturnCode::InvalidData)}
+    entry.arguments = rest;
+    Ok(entry)
+}
+
+//Split the first whitespace separated word from a string
+fn split_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    match text.find(char::is_whitespace) {
+        Some(index) => (&text[..index], text[index..].trim_start()),
+        None        => (text, ""),
+    }
+}
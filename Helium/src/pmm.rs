@@ -7,12 +7,18 @@ use core::cell::RefCell;
 use core::ptr::read_volatile;
 use core::ptr::write_volatile;
 use core::intrinsics::write_bytes;
+use gluon::x86_64::page_operations::*;
 use gluon::x86_64::paging::*;
 use gluon::noble::return_code::*;
 
 use crate::kstruct::MemPort;
 use crate::kstruct::port_type;
 
+//Constants
+const BLOCK_LEVELS:     [PageMapLevel; 2] = [PageMapLevel::L2, PageMapLevel::L3]; //LARGE PAGE LEVELS KEPT IN BLOCK LISTS
+const BLOCK_LIST_SIZE:  usize = 8;                                                //WHOLE BLOCKS KEPT PER LEVEL
+const BLOCK_SCAN_LIMIT: usize = 0x80000;                                          //STACK ENTRIES SEARCHED FOR A BLOCK (2 GIB OF PAGES)
+
 
 // MEMORY MANAGEMENT
 //Address translator which cannot allocate
@@ -63,41 +69,7 @@ impl PageAllocator for StackAllocator {
 }
 
 
-// ADDRESS TRANSLATION
-//Address Translator Trait
-pub trait AddressTranslator {
-    fn translate(&self, physical: PhysicalAddress) -> Result<LinearAddress, ReturnCode>;
-}
-
-//Offset Identity Address Translator
-pub struct OffsetIdentity {
-    pub offset: usize,
-    pub limit: usize,
-}
-impl AddressTranslator for OffsetIdentity {
-    fn translate(&self, physical: PhysicalAddress) -> Result<LinearAddress, ReturnCode> {
-        if physical.0 > self.limit {return Err(ReturnCode::MemoryOutOfBounds)}
-        Ok(LinearAddress(physical.0 + self.offset))
-    }
-}
-
-
 // PHYSICAL ADDRESS ALLOCATION
-//Physical Allocator Trait
-pub trait PhysicalAddressAllocator {
-    fn take(&self, pages: &mut [PhysicalAddress]) -> Result<(), ReturnCode>;
-    fn give(&self, pages: &[PhysicalAddress]) -> Result<(), ReturnCode>;
-    fn take_one(&self) -> Result<PhysicalAddress, ReturnCode> {
-        let mut buffer: [PhysicalAddress; 1] = [PhysicalAddress(0)];
-        self.take(&mut buffer)?;
-        Ok(buffer[0])
-    }
-    fn give_one(&self, page: PhysicalAddress) -> Result<(), ReturnCode> {
-        let array: [PhysicalAddress; 1] = [page];
-        self.give(&array)
-    }
-}
-
 //Iterator Allocator
 pub struct IteratorAllocator<'s> {
     pub iter_ref: RefCell<&'s mut dyn Iterator<Item = PhysicalAddress>>,
@@ -122,9 +94,18 @@ pub struct MemoryStack<'s> {
     pub index: RefCell<usize>,
     pub stack: *const PhysicalAddress,
     pub translator: &'s dyn AddressTranslator,
+    pub blocks: RefCell<[BlockList; 2]>,
 }
 impl<'i> PhysicalAddressAllocator for MemoryStack<'i> {
     fn take(&self, pages: &mut [PhysicalAddress]) -> Result<(), ReturnCode> {
+        //Break whole blocks back into pages if the stack runs short
+        while pages.len() > *self.index.borrow() {
+            let block = self.blocks.borrow_mut().iter_mut().enumerate().find_map(|(list, blocks)| blocks.pop_dirty().or_else(|| blocks.pop_clean()).map(|block| (block, list)));
+            match block {
+                Some((block, list)) => self.give_pages(block, BLOCK_LEVELS[list]),
+                None => return Err(ReturnCode::OutOfResources),
+            }
+        }
         //CRITICAL SECTION
         {
             if pages.len() > *self.index.borrow() {return Err(ReturnCode::OutOfResources)}
@@ -152,170 +133,124 @@ impl<'i> PhysicalAddressAllocator for MemoryStack<'i> {
         }
         Ok(())
     }
-}
-
 
-// PAGE OPERATIONS
-//Page Operation Trait
-pub trait PageOperation {
-    fn op(&mut self, entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode>;
-}
-
-//Map Memory
-pub struct MapMemory<'s> {
-    pub allocator: &'s dyn PhysicalAddressAllocator,
-    pub translator: &'s dyn AddressTranslator,
-    pub write: bool,
-    pub user: bool,
-    pub execute_disable: bool,
-}
-impl<'i> PageOperation for MapMemory<'i> {
-    fn op(&mut self, entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode> {
-        match (entry.in_use, entry.entry_level, entry.entry_type) {
-            (true,  _, PageMapEntryType::Memory) => {Err(ReturnCode::Test03)}, //throw error due to previously allocated memory
-            (true,  _, PageMapEntryType::Table)  => {
-                //use existing table and recurse
-                let map = PageMap::new(self.translator.translate(entry.physical)?, entry.entry_level.sub()?)?;
-                virtual_memory_editor(map, self, start, end)?;
-                //writeln!(self.printer, "tlb: {:?}", entry);
-                Ok(entry)
-            },
-            (false, PageMapLevel::L1, _) => {
-                //allocate a single page as memory
-                let address = self.allocator.take_one()?;
-                let value = PageMapEntry::new(PageMapLevel::L1, PageMapEntryType::Memory, address, true, self.write, self.user, self.execute_disable);
-                //writeln!(self.printer, "nwm: {:?}", value);
-                value
-            },
-            (false, _, _) => {
-                //allocate a single page as a table and recurse
-                let physical = self.allocator.take_one()?;
-                let linear = self.translator.translate(physical)?;
-                let map = PageMap::new(linear, entry.entry_level.sub()?)?;
-                virtual_memory_editor(map, self, start, end)?;
-                let value = PageMapEntry::new(entry.entry_level, PageMapEntryType::Table, physical, true, self.write, self.user, self.execute_disable);
-                //writeln!(self.printer, "nwt: {:?}", value);
-                value
-            },
+    fn take_block(&self, level: PageMapLevel) -> Result<PhysicalAddress, ReturnCode> {
+        //Prefer a whole block already zeroed while idle, then a whole block still to be zeroed
+        let list = BLOCK_LEVELS.iter().position(|block_level| *block_level == level);
+        if let Some(list) = list {
+            let (clean, dirty) = {
+                let blocks = &mut self.blocks.borrow_mut()[list];
+                match blocks.pop_clean() {
+                    Some(block) => (Some(block), None),
+                    None        => (None, blocks.pop_dirty()),
+                }
+            };
+            if let Some(block) = clean {return Ok(block)}
+            if let Some(block) = dirty {return self.zero_block(block, level)}
         }
+        //Otherwise carve a block out of the stack
+        let size: usize = page_size(level);
+        let count: usize = size / PAGE_SIZE_4KIB;
+        let stack = self.stack as *mut PhysicalAddress;
+        //CRITICAL SECTION
+        let block = {
+            let index: usize = *self.index.borrow();
+            if count > index {return Err(ReturnCode::OutOfResources)}
+            //Find a run of consecutive pages starting on an aligned address in one pass down from the top of the stack (pages are stacked in ascending order at boot), giving up past the scan limit
+            let mut run: usize = 0;
+            let position: usize = (index.saturating_sub(BLOCK_SCAN_LIMIT.max(count))..index).rev().find(|position| unsafe {
+                let base = (*stack.add(*position)).0;
+                run = if run > 0 && (*stack.add(position + 1)).0 == base + PAGE_SIZE_4KIB {run + 1} else {1};
+                run >= count && base % size == 0
+            }).ok_or(ReturnCode::OutOfResources)?;
+            let block = unsafe {*stack.add(position)};
+            //Close the gap left in the stack
+            unsafe {core::ptr::copy(stack.add(position + count), stack.add(position), index - position - count)};
+            *self.index.borrow_mut() = index - count;
+            block
+        };
+        self.zero_block(block, level)
     }
-}
 
-//Unmap Memory
-pub struct UnmapMemory<'s> {
-    pub allocator: &'s dyn PhysicalAddressAllocator,
-    pub translator: &'s dyn AddressTranslator,
+    fn give_block(&self, block: PhysicalAddress, level: PageMapLevel) -> Result<(), ReturnCode> {
+        //Keep the block whole for the next large page, it is zeroed when idle or when taken
+        if let Some(list) = BLOCK_LEVELS.iter().position(|block_level| *block_level == level) {
+            if self.blocks.borrow_mut()[list].push_dirty(block) {return Ok(())}
+        }
+        self.give_pages(block, level);
+        Ok(())
+    }
 }
-impl<'i> PageOperation for UnmapMemory<'i> {
-    fn op(&mut self, entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode> {
-        match (entry.in_use, entry.entry_type, entry.entry_level) {
-            (false, _, _) => Err(ReturnCode::Test05), //throw error due to deallocating area not in use
-            (true, PageMapEntryType::Table, _) => {
-                //recurse through existing table
-                //writeln!(self.printer, "traverse: {:?}", entry.physical);
-                let map = PageMap::new(self.translator.translate(entry.physical)?, entry.entry_level.sub()?)?;
-                virtual_memory_editor(map, self, start, end)?;
-                //test if map is empty
-                let mut map_empty: bool = true;
-                for position in 0usize..512 {
-                    let map_entry = map.read_entry(position)?;
-                    if map_entry.in_use {
-                        map_empty = false;
-                        break
-                    }
-                }
-                //finish
-                //return Ok(entry); // PROBLEM SOMEWHERE HERE
-                if map_empty {
-                    let physical = entry.physical;
-                    self.allocator.give(&[physical])?;
-                    PageMapEntry::from_u64(0, entry.entry_level)
+impl<'i> MemoryStack<'i> {
+    //Zero one whole block given back since it was last zeroed, returning whether there was one (run while idle, with interrupts enabled)
+    pub fn scrub(&self) -> bool {
+        for (list, level) in BLOCK_LEVELS.iter().enumerate() {
+            let dirty = self.blocks.borrow_mut()[list].pop_dirty();
+            if let Some(block) = dirty {
+                if let Ok(block) = self.zero_block(block, *level) {
+                    if !self.blocks.borrow_mut()[list].push_clean(block) {self.give_pages(block, *level);}
                 }
-                else {
-                    Ok(entry)
-                }
-            },
-            (true, PageMapEntryType::Memory, PageMapLevel::L1) => {
-                //deallocate 4KB memory block
-                let physical = entry.physical;
-                //writeln!(self.printer, "dealloc: {:?}", physical);
-                self.allocator.give(&[physical])?;
-                PageMapEntry::from_u64(0, PageMapLevel::L1)
-            },
-            (true, PageMapEntryType::Memory, _) => Err(ReturnCode::Test04), //throw error due to deallocating non-4KB memory block
+                return true;
+            }
         }
+        false
     }
-}
 
-//Mark in use
-pub struct MarkInUse<'s> {
-    pub translator: &'s dyn AddressTranslator,
-}
-impl<'i> PageOperation for MarkInUse<'i> {
-    fn op(&mut self, mut entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode> {
-        match (entry.in_use, entry.present, entry.entry_type) {
-            (true,  _,     _) => {},
-            (_,     false, _) => {},
-            (false, true,  PageMapEntryType::Memory) => {
-                entry.in_use = true;
-            },
-            (false, true,  PageMapEntryType::Table) => {
-                let map = PageMap::new(self.translator.translate(entry.physical)?, entry.entry_level.sub()?)?;
-                virtual_memory_editor(map, self, start, end)?;
-                entry.in_use = true;
-            },
-        }
-        Ok(entry)
+    //Zero a whole block
+    fn zero_block(&self, block: PhysicalAddress, level: PageMapLevel) -> Result<PhysicalAddress, ReturnCode> {
+        let linear = self.translator.translate(block)?;
+        unsafe {write_bytes(linear.0 as *mut u8, 0, page_size(level))}
+        Ok(block)
     }
-}
 
-//Deprivilege
-pub struct DePrivilege<'s> {
-    pub translator: &'s dyn AddressTranslator,
-}
-impl<'i> PageOperation for DePrivilege<'i> {
-    fn op(&mut self, mut entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode> {
-        match (entry.in_use, entry.entry_type) {
-            (true, PageMapEntryType::Memory) => {
-                entry.user = true;
-            },
-            (true, PageMapEntryType::Table) => {
-                let map = PageMap::new(self.translator.translate(entry.physical)?, entry.entry_level.sub()?)?;
-                virtual_memory_editor(map, self, start, end)?;
-                entry.user = true;
-            },
-            (_, _) => {},
-        }
-        Ok(entry)
+    //Push the pages of a whole block onto the stack
+    fn give_pages(&self, block: PhysicalAddress, level: PageMapLevel) {
+        let index: usize = *self.index.borrow();
+        let stack = self.stack as *mut PhysicalAddress;
+        let count: usize = page_size(level) / PAGE_SIZE_4KIB;
+        for page in 0..count {unsafe {*stack.add(index + page) = block.add(page * PAGE_SIZE_4KIB)};}
+        *self.index.borrow_mut() = index + count;
     }
 }
 
-// PAGE OPERATION
-//Virtual Memory Editor
-pub fn virtual_memory_editor(map: PageMap, operation: &mut dyn PageOperation, start: LinearAddress, end: LinearAddress) -> Result<(), ReturnCode> {
-    //
-    canonical_48(start)?; canonical_48(end)?;
-    if end.0 <= start.0 {return Err(ReturnCode::Test01)}
-    //
-    let index_start: usize = extract_index(start, map.map_level);
-    let index_end: usize = extract_index(end.sub(1), map.map_level);
-    let mut start_current: LinearAddress = start;
-    let p = page_size(map.map_level);
-    //
-    for index_current in index_start..index_end+1 {
-        //
-        let end_current: LinearAddress =
-            if index_current == index_end {end}
-            else {LinearAddress(((start_current.0 + p)/p) * p)};
-        //
-        let entry_read: PageMapEntry = map.read_entry(index_current)?;
-        let entry_write: PageMapEntry = operation.op(entry_read, start_current, end_current)?;
-        map.write_entry(index_current, entry_write)?;
-        //
-        start_current = end_current;
+//Block List (whole large page blocks given back, the first `clean` of which have been zeroed)
+#[derive(Clone, Copy)]
+pub struct BlockList {
+    blocks: [PhysicalAddress; BLOCK_LIST_SIZE],
+    count:  usize,
+    clean:  usize,
+}
+impl BlockList {
+    pub const fn new() -> Self {
+        Self {blocks: [PhysicalAddress(0); BLOCK_LIST_SIZE], count: 0, clean: 0}
+    }
+    fn push_dirty(&mut self, block: PhysicalAddress) -> bool {
+        if self.count == BLOCK_LIST_SIZE {return false}
+        self.blocks[self.count] = block;
+        self.count += 1;
+        true
+    }
+    fn push_clean(&mut self, block: PhysicalAddress) -> bool {
+        if self.count == BLOCK_LIST_SIZE {return false}
+        self.blocks[self.count] = self.blocks[self.clean];
+        self.blocks[self.clean] = block;
+        self.clean += 1;
+        self.count += 1;
+        true
+    }
+    fn pop_clean(&mut self) -> Option<PhysicalAddress> {
+        if self.clean == 0 {return None}
+        self.clean -= 1;
+        self.count -= 1;
+        let block = self.blocks[self.clean];
+        self.blocks[self.clean] = self.blocks[self.count];
+        Some(block)
+    }
+    fn pop_dirty(&mut self) -> Option<PhysicalAddress> {
+        if self.count == self.clean {return None}
+        self.count -= 1;
+        Some(self.blocks[self.count])
     }
-    //
-    Ok(())
 }
 
 
@@ -328,29 +263,62 @@ pub struct MapPort<'s> {
     pub execute_disable: bool,
 }
 impl<'i> MapPort<'i> {
-    pub fn map(&self, parent_map: PageMap, port: MemPort, address: LinearAddress) -> Result<(), ReturnCode> {
-        //
-        canonical_48(address)?;
+    pub fn map(&self, root_map: PageMap, port: MemPort, address: LinearAddress) -> Result<(), ReturnCode> {
+        canonical(address, root_map.map_level)?;
+        self.map_below(root_map, port, address)
+    }
+
+    fn map_below(&self, parent_map: PageMap, port: MemPort, address: LinearAddress) -> Result<(), ReturnCode> {
         //
         let index: usize = extract_index(address, parent_map.map_level);
         let entry: PageMapEntry = parent_map.read_entry(index)?;
         if port.level == parent_map.map_level {
             if entry.in_use {return Err(ReturnCode::Test00)}
             else {
-                parent_map.write_entry(index, PageMapEntry::new(port.level, port_type(port.level), port.address, true, self.write, self.user, self.execute_disable)?)?;
+                let mut page = PageMapEntry::new(port.level, port_type(port.level), port.address, true, self.write, self.user, self.execute_disable)?;
+                page.set_cache_mode(port.cache_mode)?;
+                parent_map.write_entry(index, page)?;
             }
         }
         else if entry.in_use {
             if entry.entry_type == PageMapEntryType::Table {
-                self.map(PageMap::new(self.translator.translate(entry.physical)?, parent_map.map_level.sub()?)?, port, address)?;
+                self.map_below(PageMap::new(self.translator.translate(entry.physical)?, parent_map.map_level.sub()?)?, port, address)?;
             }
             else {return Err(ReturnCode::Test01)}
         }
         else {
             let new_map_address = self.allocator.take_one()?;
-            parent_map.write_entry(index, PageMapEntry::new(parent_map.map_level, port_type(port.level), new_map_address, true, self.write, self.user, self.execute_disable)?)?;
-            self.map(PageMap::new(self.translator.translate(new_map_address)?, parent_map.map_level.sub()?)?, port, address)?;
+            parent_map.write_entry(index, PageMapEntry::new(parent_map.map_level, PageMapEntryType::Table, new_map_address, true, self.write, self.user, self.execute_disable)?)?;
+            self.map_below(PageMap::new(self.translator.translate(new_map_address)?, parent_map.map_level.sub()?)?, port, address)?;
         }
         Ok(())
     }
 }
+
+//Unmap Port (removes the pages of a memory port, whose physical memory is never the allocator's, and frees tables left empty)
+pub struct UnmapPort<'s> {
+    pub allocator: &'s dyn PhysicalAddressAllocator,
+    pub translator: &'s dyn AddressTranslator,
+    pub invalidator: &'s dyn TlbInvalidator,
+}
+impl<'i> PageOperation for UnmapPort<'i> {
+    fn op(&mut self, entry: PageMapEntry, start: LinearAddress, end: LinearAddress) -> Result<PageMapEntry, ReturnCode> {
+        match (entry.in_use, entry.entry_type, entry.entry_level) {
+            (false, _, _) => Err(ReturnCode::NoMapping),
+            (true, PageMapEntryType::Table, _) => {
+                let map = PageMap::new(self.translator.translate(entry.physical)?, entry.entry_level.sub()?)?;
+                page_map_editor(map, self, start, end)?;
+                for position in 0usize..512 {
+                    if map.read_entry(position)?.in_use {return Ok(entry)}
+                }
+                self.allocator.give_one(entry.physical)?;
+                PageMapEntry::from_u64(0, entry.entry_level)
+            },
+            (true, PageMapEntryType::Memory, level) if level == PageMapLevel::L1 || end.0 - start.0 == page_size(level) => {
+                self.invalidator.invalidate(start);
+                PageMapEntry::from_u64(0, level)
+            },
+            (true, PageMapEntryType::Memory, _) => Err(ReturnCode::InvalidData), //ports are mapped in whole pages
+        }
+    }
+}